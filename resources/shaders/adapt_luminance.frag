@@ -0,0 +1,13 @@
+#version 140
+uniform sampler2D luminance_sampler;
+uniform float average_level;
+uniform sampler2D previous_sampler;
+uniform float rate;
+
+out vec4 color;
+
+void main() {
+  float current = textureLod(luminance_sampler, vec2(0.5, 0.5), average_level).r;
+  float previous = texelFetch(previous_sampler, ivec2(0, 0), 0).r;
+  color = vec4(mix(previous, current, rate), 0.0, 0.0, 1.0);
+}
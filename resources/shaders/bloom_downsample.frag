@@ -0,0 +1,41 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D source_sampler;
+uniform vec2 source_texel;
+
+out vec4 color;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+vec3 tap(vec2 uv, float x, float y) {
+  return texture(source_sampler, uv + vec2(x, y) * source_texel).rgb;
+}
+
+// 13 tap filter from the call of duty advanced warfare presentation
+void main() {
+  vec2 uv = tex_coords();
+
+  vec3 a = tap(uv, -2.0,  2.0);
+  vec3 b = tap(uv,  0.0,  2.0);
+  vec3 c = tap(uv,  2.0,  2.0);
+  vec3 d = tap(uv, -2.0,  0.0);
+  vec3 e = tap(uv,  0.0,  0.0);
+  vec3 f = tap(uv,  2.0,  0.0);
+  vec3 g = tap(uv, -2.0, -2.0);
+  vec3 h = tap(uv,  0.0, -2.0);
+  vec3 i = tap(uv,  2.0, -2.0);
+  vec3 j = tap(uv, -1.0,  1.0);
+  vec3 k = tap(uv,  1.0,  1.0);
+  vec3 l = tap(uv, -1.0, -1.0);
+  vec3 m = tap(uv,  1.0, -1.0);
+
+  vec3 res = e * 0.125;
+  res += (a + c + g + i) * 0.03125;
+  res += (b + d + f + h) * 0.0625;
+  res += (j + k + l + m) * 0.125;
+
+  color = vec4(res, 1.0);
+}
@@ -0,0 +1,31 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D source_sampler;
+uniform vec2 source_texel;
+uniform float threshold;
+uniform float knee;
+
+out vec4 color;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+void main() {
+  // four bilinear taps average a 4x4 block of the full resolution scene
+  vec2 uv = tex_coords();
+  vec3 hdr = 0.25 * (texture(source_sampler, uv + vec2(-1.0, -1.0) * source_texel).rgb
+                     + texture(source_sampler, uv + vec2( 1.0, -1.0) * source_texel).rgb
+                     + texture(source_sampler, uv + vec2(-1.0,  1.0) * source_texel).rgb
+                     + texture(source_sampler, uv + vec2( 1.0,  1.0) * source_texel).rgb);
+
+  // quadratic soft knee around the threshold instead of a hard cut
+  float brightness = max(hdr.r, max(hdr.g, hdr.b));
+  float soft_width = threshold * knee + 0.00001;
+  float soft = clamp(brightness - threshold + soft_width, 0.0, 2.0 * soft_width);
+  soft = (soft * soft) / (4.0 * soft_width);
+  float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+
+  color = vec4(hdr * contribution, 1.0);
+}
@@ -0,0 +1,27 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D source_sampler;
+uniform vec2 source_texel;
+uniform float radius;
+
+out vec4 color;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+vec3 tap(vec2 uv, float x, float y) {
+  return texture(source_sampler, uv + vec2(x, y) * radius * source_texel).rgb;
+}
+
+// 3x3 tent filter, the result is added onto the next bigger level
+void main() {
+  vec2 uv = tex_coords();
+
+  vec3 res = tap(uv, 0.0, 0.0) * 4.0;
+  res += (tap(uv, 0.0, 1.0) + tap(uv, -1.0, 0.0) + tap(uv, 1.0, 0.0) + tap(uv, 0.0, -1.0)) * 2.0;
+  res += tap(uv, -1.0, 1.0) + tap(uv, 1.0, 1.0) + tap(uv, -1.0, -1.0) + tap(uv, 1.0, -1.0);
+
+  color = vec4(res / 16.0, 1.0);
+}
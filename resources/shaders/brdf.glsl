@@ -0,0 +1,44 @@
+// the cook-torrance terms of the metallic roughness shading
+
+const float PI = 3.14159265359;
+
+// trowbridge-reitz normal distribution, a is the squared roughness
+float distribution_ggx(float n_dot_h, float a) {
+  float a2 = a * a;
+  float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PI * denom * denom);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float rough) {
+  float r = rough + 1.0;
+  float k = (r * r) / 8.0;
+  float gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+  float gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+  return gv * gl;
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+  return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// light reflected towards the viewer per incoming radiance from light_dir, includes the cosine of the incidence
+vec3 cook_torrance(vec3 normal, vec3 view_dir, vec3 light_dir, vec3 albedo, float metal, float rough) {
+  vec3 half_dir = normalize(light_dir + view_dir);
+  float n_dot_v = max(dot(normal, view_dir), 0.0001);
+  float n_dot_l = max(dot(normal, light_dir), 0.0);
+  float n_dot_h = max(dot(normal, half_dir), 0.0);
+
+  vec3 f0 = mix(vec3(0.04), albedo, metal);
+  vec3 f = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+  float d = distribution_ggx(n_dot_h, rough * rough);
+  float g = geometry_smith(n_dot_v, n_dot_l, rough);
+  vec3 specular = (d * g * f) / (4.0 * n_dot_v * n_dot_l + 0.0001);
+  vec3 k_d = (vec3(1.0) - f) * (1.0 - metal);
+
+  return (k_d * albedo / PI + specular) * n_dot_l;
+}
+
+// the fresnel term of the image based lighting, rough surfaces reflect less at grazing angles
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float rough) {
+  return f0 + (max(vec3(1.0 - rough), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
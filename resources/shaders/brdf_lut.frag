@@ -0,0 +1,56 @@
+#version 140
+in vec2 out_position;
+
+out vec4 color;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 1024u;
+
+float radical_inverse(uint bits) {
+  bits = (bits << 16u) | (bits >> 16u);
+  bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+  bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+  bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+  bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+  return float(bits) * 2.3283064365386963e-10;
+}
+
+float geometry_schlick_ggx(float n_dot_v, float roughness) {
+  float k = (roughness * roughness) / 2.0;
+  return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// split sum approximation: scale (x) and bias (y) applied to the fresnel term at normal incidence
+void main() {
+  vec2 uv = 0.5 * out_position + 0.5;
+  float n_dot_v = max(uv.x, 0.001);
+  float roughness = uv.y;
+  float a = roughness * roughness;
+
+  vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+  float scale = 0.0;
+  float bias = 0.0;
+
+  for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+    vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radical_inverse(i));
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    vec3 l = normalize(2.0 * dot(view, h) * h - view);
+
+    float n_dot_l = max(l.z, 0.0);
+    float n_dot_h = max(h.z, 0.0);
+    float v_dot_h = max(dot(view, h), 0.0);
+
+    if (n_dot_l > 0.0) {
+      float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+      float g_vis = (g * v_dot_h) / (n_dot_h * n_dot_v);
+      float fc = pow(1.0 - v_dot_h, 5.0);
+      scale += (1.0 - fc) * g_vis;
+      bias += fc * g_vis;
+    }
+  }
+
+  color = vec4(scale / float(SAMPLE_COUNT), bias / float(SAMPLE_COUNT), 0.0, 1.0);
+}
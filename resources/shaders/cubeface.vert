@@ -0,0 +1,13 @@
+#version 140
+in vec2 position;
+
+uniform vec3 face_dir;
+uniform vec3 face_s;
+uniform vec3 face_t;
+
+out vec3 out_direction;
+
+void main() {
+  gl_Position = vec4(position, 0.0, 1.0);
+  out_direction = face_dir + position.x * face_s + position.y * face_t;
+}
@@ -0,0 +1,32 @@
+#version 140
+uniform vec3 camera_pos;
+// screen space ambient occlusion of the g-buffer, white without ssao
+uniform sampler2D occlusion_sampler;
+
+#include "gbuffer_read.glsl"
+#include "fog.glsl"
+#include "brdf.glsl"
+#include "ibl.glsl"
+
+out vec4 color;
+
+// the occluded ambient light of the g-buffer, added like a light to the emissive light and fog of the geometry pass
+void main() {
+  vec2 uv = gl_FragCoord.xy / resolution;
+  float depth = texture(depth_sampler, uv).r;
+  if (depth == clear_depth) {
+    discard;
+  }
+
+  vec3 position = world_position(uv, depth);
+  vec3 albedo = texture(albedo_sampler, uv).rgb;
+  vec3 normal = normalize(texture(normal_sampler, uv).xyz);
+  vec4 material = texture(material_sampler, uv);
+  vec3 view_dir = normalize(camera_pos - position);
+
+  float ao = material.b * texture(occlusion_sampler, gl_FragCoord.xy / vec2(textureSize(occlusion_sampler, 0))).r;
+  vec3 ambient = image_based_lighting(normal, view_dir, albedo, material.r, material.g) * ao;
+
+  float visibility = fog_visibility(length(position - camera_pos), position.y - camera_pos.y);
+  color = vec4(visibility * ambient, 0.0);
+}
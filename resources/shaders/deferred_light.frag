@@ -0,0 +1,55 @@
+#version 140
+uniform vec3 camera_pos;
+uniform vec3 camera_front;
+
+// one light in the layout of the light block, see drawing/lighting.rs
+uniform vec4 light_position_kind;
+uniform vec4 light_direction_intensity;
+uniform vec4 light_color_outer;
+uniform vec4 light_attenuation_inner;
+// zero for lights drawn over the whole screen
+uniform float light_radius;
+
+// negative for lights without a slot in the shadow blocks
+uniform int shadow_index;
+
+#include "gbuffer_read.glsl"
+#include "lights.glsl"
+#include "shadows.glsl"
+#include "fog.glsl"
+#include "brdf.glsl"
+
+out vec4 color;
+
+void main() {
+  vec2 uv = gl_FragCoord.xy / resolution;
+  float depth = texture(depth_sampler, uv).r;
+  // nothing was drawn here, the skybox fills it later
+  if (depth == clear_depth) {
+    discard;
+  }
+
+  vec3 position = world_position(uv, depth);
+  vec3 albedo = texture(albedo_sampler, uv).rgb;
+  vec3 normal = normalize(texture(normal_sampler, uv).xyz);
+  vec4 material = texture(material_sampler, uv);
+  float metal = material.r;
+  float rough = material.g;
+
+  // the volume only covers the screen, pixels behind or in front of it are skipped here
+  if (light_radius > 0.0 && distance(light_position_kind.xyz, position) > light_radius) {
+    discard;
+  }
+
+  vec3 light_dir;
+  float falloff = light_falloff(light_position_kind, light_direction_intensity, light_color_outer, light_attenuation_inner,
+                                position, light_dir);
+  float shadow = material.a > 0.5 ? shadow_factor(shadow_index, position, normal, light_dir) : 1.0;
+  vec3 radiance = falloff * shadow * light_direction_intensity.w * light_color_outer.rgb;
+
+  vec3 view_dir = normalize(camera_pos - position);
+
+  // the fog color itself was already written by the geometry pass
+  float visibility = fog_visibility(length(position - camera_pos), position.y - camera_pos.y);
+  color = vec4(visibility * cook_torrance(normal, view_dir, light_dir, albedo, metal, rough) * radiance, 0.0);
+}
@@ -0,0 +1,15 @@
+#version 140
+in vec3 out_direction;
+
+uniform sampler2D equirect;
+
+out vec4 color;
+
+const float PI = 3.14159265359;
+
+void main() {
+  vec3 dir = normalize(out_direction);
+  // longitude around the y axis, latitude from the top row of the image
+  vec2 uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+  color = vec4(texture(equirect, uv).rgb, 1.0);
+}
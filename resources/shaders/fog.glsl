@@ -0,0 +1,24 @@
+// shared by the lit shaders that include it, they have to declare a camera_pos uniform before the include
+
+// fog, see drawing/fog.rs for the layout
+uniform vec4 fog_color_mode;
+uniform vec4 fog_params;
+uniform vec2 fog_height;
+
+// fraction of the surface that is still visible through the fog, rise is how far the view ray climbs
+float fog_visibility(float dist, float rise) {
+  int mode = int(fog_color_mode.w + 0.5);
+  if (mode == 1) {
+    return clamp((fog_params.y - dist) / max(fog_params.y - fog_params.x, 0.0001), 0.0, 1.0);
+  } else if (mode == 2) {
+    return exp(-fog_params.z * dist);
+  } else if (mode == 3) {
+    // the density falls off exponentially above the fog height and is integrated along the ray
+    float falloff = max(fog_height.y, 0.0001);
+    float camera_density = fog_params.z * exp(-falloff * (camera_pos.y - fog_height.x));
+    float climb = falloff * rise;
+    float integral = abs(climb) > 0.0001 ? (1.0 - exp(-climb)) / climb : 1.0;
+    return exp(-camera_density * dist * integral);
+  }
+  return 1.0;
+}
@@ -0,0 +1,154 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D source_sampler;
+uniform vec2 source_texel;
+
+out vec4 color;
+
+// contrast below max(EDGE_MIN, EDGE_THRESHOLD * brightest neighbour) is not treated as an edge
+const float EDGE_THRESHOLD = 0.125;
+const float EDGE_MIN = 0.0312;
+const float SUBPIXEL_QUALITY = 0.75;
+const int SEARCH_STEPS = 12;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+// the input is gamma encoded already, which is close enough to perceived brightness
+float luma(vec2 uv) {
+  return dot(texture(source_sampler, uv).rgb, vec3(0.299, 0.587, 0.114));
+}
+
+// the edge search takes bigger steps the further it gets
+float search_step(int i) {
+  if (i < 5) {
+    return 1.0;
+  } else if (i < 8) {
+    return 2.0;
+  } else if (i < 10) {
+    return 4.0;
+  }
+  return 8.0;
+}
+
+void main() {
+  vec2 uv = tex_coords();
+  vec4 center = texture(source_sampler, uv);
+  float luma_center = dot(center.rgb, vec3(0.299, 0.587, 0.114));
+
+  float luma_down = luma(uv + vec2(0.0, -source_texel.y));
+  float luma_up = luma(uv + vec2(0.0, source_texel.y));
+  float luma_left = luma(uv + vec2(-source_texel.x, 0.0));
+  float luma_right = luma(uv + vec2(source_texel.x, 0.0));
+
+  float luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+  float luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+  float luma_range = luma_max - luma_min;
+
+  if (luma_range < max(EDGE_MIN, luma_max * EDGE_THRESHOLD)) {
+    color = center;
+    return;
+  }
+
+  float luma_down_left = luma(uv + vec2(-source_texel.x, -source_texel.y));
+  float luma_up_right = luma(uv + vec2(source_texel.x, source_texel.y));
+  float luma_up_left = luma(uv + vec2(-source_texel.x, source_texel.y));
+  float luma_down_right = luma(uv + vec2(source_texel.x, -source_texel.y));
+
+  float luma_down_up = luma_down + luma_up;
+  float luma_left_right = luma_left + luma_right;
+  float luma_left_corners = luma_down_left + luma_up_left;
+  float luma_down_corners = luma_down_left + luma_down_right;
+  float luma_right_corners = luma_down_right + luma_up_right;
+  float luma_up_corners = luma_up_right + luma_up_left;
+
+  // which way the edge runs, from the second derivatives across both directions
+  float edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+                        + abs(-2.0 * luma_center + luma_down_up) * 2.0
+                        + abs(-2.0 * luma_right + luma_right_corners);
+  float edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+                      + abs(-2.0 * luma_center + luma_left_right) * 2.0
+                      + abs(-2.0 * luma_down + luma_down_corners);
+  bool horizontal = edge_horizontal >= edge_vertical;
+
+  // the side of the pixel with the steeper gradient is where the edge lies
+  float luma1 = horizontal ? luma_down : luma_left;
+  float luma2 = horizontal ? luma_up : luma_right;
+  float gradient1 = luma1 - luma_center;
+  float gradient2 = luma2 - luma_center;
+  bool steepest1 = abs(gradient1) >= abs(gradient2);
+  float gradient_scaled = 0.25 * max(abs(gradient1), abs(gradient2));
+
+  float step_length = horizontal ? source_texel.y : source_texel.x;
+  float luma_local_average;
+  if (steepest1) {
+    step_length = -step_length;
+    luma_local_average = 0.5 * (luma1 + luma_center);
+  } else {
+    luma_local_average = 0.5 * (luma2 + luma_center);
+  }
+
+  // walk along the edge, half a texel towards it, in both directions until its ends are found
+  vec2 edge_uv = uv;
+  if (horizontal) {
+    edge_uv.y += 0.5 * step_length;
+  } else {
+    edge_uv.x += 0.5 * step_length;
+  }
+  vec2 offset = horizontal ? vec2(source_texel.x, 0.0) : vec2(0.0, source_texel.y);
+
+  vec2 uv1 = edge_uv - offset;
+  vec2 uv2 = edge_uv + offset;
+  float luma_end1 = 0.0;
+  float luma_end2 = 0.0;
+  bool reached1 = false;
+  bool reached2 = false;
+
+  for (int i = 0; i < SEARCH_STEPS; i++) {
+    if (!reached1) {
+      luma_end1 = luma(uv1) - luma_local_average;
+      reached1 = abs(luma_end1) >= gradient_scaled;
+    }
+    if (!reached2) {
+      luma_end2 = luma(uv2) - luma_local_average;
+      reached2 = abs(luma_end2) >= gradient_scaled;
+    }
+    if (reached1 && reached2) {
+      break;
+    }
+    if (!reached1) {
+      uv1 -= offset * search_step(i);
+    }
+    if (!reached2) {
+      uv2 += offset * search_step(i);
+    }
+  }
+
+  float distance1 = horizontal ? (uv.x - uv1.x) : (uv.y - uv1.y);
+  float distance2 = horizontal ? (uv2.x - uv.x) : (uv2.y - uv.y);
+  bool closer1 = distance1 < distance2;
+  float distance_final = min(distance1, distance2);
+  float edge_length = distance1 + distance2;
+  float pixel_offset = -distance_final / edge_length + 0.5;
+
+  // only move if the closer end of the edge varies the same way as the pixel itself
+  bool center_smaller = luma_center < luma_local_average;
+  bool correct_variation = ((closer1 ? luma_end1 : luma_end2) < 0.0) != center_smaller;
+  float final_offset = correct_variation ? pixel_offset : 0.0;
+
+  // single pixel features get blended with their neighbourhood instead
+  float luma_average = (2.0 * (luma_down_up + luma_left_right) + luma_left_corners + luma_right_corners) / 12.0;
+  float subpixel = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+  subpixel = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+  final_offset = max(final_offset, subpixel * subpixel * SUBPIXEL_QUALITY);
+
+  vec2 final_uv = uv;
+  if (horizontal) {
+    final_uv.y += final_offset * step_length;
+  } else {
+    final_uv.x += final_offset * step_length;
+  }
+  color = vec4(texture(source_sampler, final_uv).rgb, center.a);
+}
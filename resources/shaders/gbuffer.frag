@@ -0,0 +1,57 @@
+#version 140
+in vec3 out_normal;
+in vec3 out_position;
+in vec2 out_tex_coords;
+in vec3 out_world_position;
+in vec3 out_world_normal;
+in vec4 out_world_tangent;
+
+uniform vec3 camera_pos;
+
+// material, the maps are multiplied with the factors
+uniform vec4 base_color;
+uniform float metallic;
+uniform float roughness;
+uniform vec3 emissive;
+uniform sampler2D albedo_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D normal_map;
+uniform sampler2D ao_map;
+uniform bool has_normal_map;
+uniform bool alpha_test;
+uniform float alpha_cutoff;
+uniform bool receive_shadows;
+
+#include "fog.glsl"
+#include "normal_mapping.glsl"
+
+// emissive light and fog, the ambient and light passes add the rest
+out vec4 color;
+out vec4 gbuffer_albedo;
+out vec4 gbuffer_normal;
+out vec4 gbuffer_material;
+
+void main() {
+  vec4 albedo_sample = texture(albedo_map, out_tex_coords);
+  float alpha = albedo_sample.a * base_color.a;
+  if (alpha_test && alpha < alpha_cutoff) {
+    discard;
+  }
+  vec3 albedo = pow(albedo_sample.rgb, vec3(2.2)) * base_color.rgb;
+  vec4 mr_sample = texture(metallic_roughness_map, out_tex_coords);
+  float metal = clamp(metallic * mr_sample.b, 0.0, 1.0);
+  float rough = clamp(roughness * mr_sample.g, 0.04, 1.0);
+  float ao = texture(ao_map, out_tex_coords).r;
+
+  vec3 normal = normalize(out_world_normal);
+  if (has_normal_map) {
+    normal = mapped_normal(out_world_normal);
+  }
+
+  // the ambient and light passes scale their contribution by the same visibility
+  float visibility = fog_visibility(length(out_position), out_world_position.y - camera_pos.y);
+  color = vec4(mix(fog_color_mode.rgb, emissive, visibility), alpha);
+  gbuffer_albedo = vec4(albedo, alpha);
+  gbuffer_normal = vec4(normal, 0.0);
+  gbuffer_material = vec4(metal, rough, ao, receive_shadows ? 1.0 : 0.0);
+}
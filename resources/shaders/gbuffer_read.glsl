@@ -0,0 +1,16 @@
+// the g-buffer as the screen space passes of deferred shading read it
+
+uniform sampler2D albedo_sampler;
+uniform sampler2D normal_sampler;
+uniform sampler2D material_sampler;
+uniform sampler2D depth_sampler;
+uniform vec2 resolution;
+
+uniform mat4 inverse_view_projection;
+// depth of pixels nothing was drawn to
+uniform float clear_depth;
+
+vec3 world_position(vec2 uv, float depth) {
+  vec4 position = inverse_view_projection * vec4(2.0 * uv - 1.0, 2.0 * depth - 1.0, 1.0);
+  return position.xyz / position.w;
+}
@@ -0,0 +1,19 @@
+// split sum image based lighting, the shaders that include it have to include brdf.glsl before
+
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform float prefiltered_levels;
+uniform sampler2D brdf_lut;
+
+// the diffuse and specular light of the environment reflected towards the viewer, before any occlusion
+vec3 image_based_lighting(vec3 normal, vec3 view_dir, vec3 albedo, float metal, float rough) {
+  float n_dot_v = max(dot(normal, view_dir), 0.0001);
+  vec3 f0 = mix(vec3(0.04), albedo, metal);
+  vec3 f = fresnel_schlick_roughness(n_dot_v, f0, rough);
+  vec3 k_d = (vec3(1.0) - f) * (1.0 - metal);
+  vec3 diffuse = texture(irradiance_map, normal).rgb * albedo;
+  vec3 reflected = reflect(-view_dir, normal);
+  vec3 prefiltered = textureLod(prefiltered_map, reflected, rough * (prefiltered_levels - 1.0)).rgb;
+  vec2 brdf = texture(brdf_lut, vec2(n_dot_v, rough)).rg;
+  return k_d * diffuse + prefiltered * (f * brdf.x + brdf.y);
+}
@@ -0,0 +1,31 @@
+#version 140
+in vec3 out_direction;
+
+uniform samplerCube environment;
+
+out vec4 color;
+
+const float PI = 3.14159265359;
+
+// cosine weighted convolution of the environment over the hemisphere around the normal
+void main() {
+  vec3 normal = normalize(out_direction);
+  vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+  vec3 right = normalize(cross(up, normal));
+  up = cross(normal, right);
+
+  vec3 irradiance = vec3(0.0);
+  float sample_count = 0.0;
+  float delta = 0.025;
+
+  for (float phi = 0.0; phi < 2.0 * PI; phi += delta) {
+    for (float theta = 0.0; theta < 0.5 * PI; theta += delta) {
+      vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+      vec3 sample_dir = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+      irradiance += texture(environment, sample_dir).rgb * cos(theta) * sin(theta);
+      sample_count += 1.0;
+    }
+  }
+
+  color = vec4(PI * irradiance / sample_count, 1.0);
+}
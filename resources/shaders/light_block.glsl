@@ -0,0 +1,7 @@
+// all lights of the scene for the forward shaders, they have to include lights.glsl before
+
+// every light takes four vec4s
+layout(std140) uniform LightBlock {
+  vec4 light_data[4 * MAX_LIGHTS];
+};
+uniform int light_count;
@@ -0,0 +1,14 @@
+#version 140
+in vec3 position;
+
+// both computed by the camera, see event_handling/camera_transformations.rs
+uniform mat4 view_matrix;
+uniform mat4 projection_matrix;
+uniform vec3 light_center;
+uniform float light_radius;
+
+// the unit sphere scaled to the reach of the light
+void main() {
+  vec3 world_pos = light_center + light_radius * position;
+  gl_Position = projection_matrix * view_matrix * vec4(world_pos, 1.0);
+}
@@ -0,0 +1,23 @@
+// the light model of the lit shaders, see drawing/lighting.rs for the layout of a light
+
+const int MAX_LIGHTS = 16;
+
+// attenuation of a light at a position and the direction towards it, spots fade out between their cones
+float light_falloff(vec4 pos_kind, vec4 dir_intensity, vec4 color_outer, vec4 atten_inner, vec3 position, out vec3 light_dir) {
+  int kind = int(pos_kind.w + 0.5);
+  if (kind == 0) {
+    light_dir = normalize(-dir_intensity.xyz);
+    return 1.0;
+  }
+
+  vec3 to_light = pos_kind.xyz - position;
+  float dist = length(to_light);
+  light_dir = to_light / dist;
+  float falloff = 1.0 / max(atten_inner.x + atten_inner.y * dist + atten_inner.z * dist * dist, 0.0001);
+
+  if (kind == 2) {
+    float cos_angle = dot(-light_dir, dir_intensity.xyz);
+    falloff *= smoothstep(color_outer.w, max(atten_inner.w, color_outer.w + 0.0001), cos_angle);
+  }
+  return falloff;
+}
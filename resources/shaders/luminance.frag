@@ -0,0 +1,17 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D color_sampler;
+
+out vec4 color;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+void main() {
+  vec3 hdr = texture(color_sampler, tex_coords()).rgb;
+  float luminance = dot(hdr, vec3(0.2126, 0.7152, 0.0722));
+  // averaging the logarithm keeps a few bright pixels from dominating
+  color = vec4(log(max(luminance, 0.0001)), 0.0, 0.0, 1.0);
+}
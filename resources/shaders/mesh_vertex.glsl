@@ -0,0 +1,48 @@
+// the vertex stage of the mesh shaders, they only differ in their fragment stage
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec4 tangent;
+in mat4 instance_transform;
+
+// both computed by the camera, see event_handling/camera_transformations.rs
+uniform mat4 view_matrix;
+uniform mat4 projection_matrix;
+uniform mat4 trans_mat;
+uniform vec3 offset;
+uniform float scale;
+
+out vec3 out_normal;
+out vec3 out_position;
+out vec2 out_tex_coords;
+out vec3 out_world_position;
+out vec3 out_world_normal;
+out vec4 out_world_tangent;
+
+// the inverse transpose of m times its determinant, normals stay perpendicular to the surface with it under any scale
+mat3 cofactors(mat3 m) {
+  return mat3(cross(m[1], m[2]), cross(m[2], m[0]), cross(m[0], m[1]));
+}
+
+void main() {
+  vec4 p = trans_mat * vec4(position, 1.0);
+  p.xyz = (scale / p.w) * p.xyz;
+  vec3 world_pos = (instance_transform * vec4(p.xyz + offset, 1.0)).xyz;
+  mat3 model = mat3(instance_transform) * mat3(trans_mat);
+  // the sign of the determinant, mirroring turns the normals and the handedness of the tangent frame around
+  float mirrored = sign(dot(model[0], cross(model[1], model[2])));
+  vec3 world_normal = normalize(mirrored * (cofactors(model) * normal));
+
+  vec4 view_pos = view_matrix * vec4(world_pos, 1.0);
+  
+  gl_Position = projection_matrix * view_pos;
+  
+  out_position = view_pos.xyz;
+  out_normal = mat3(view_matrix) * world_normal;
+  out_tex_coords = tex_coords;
+  out_world_position = world_pos;
+  out_world_normal = world_normal;
+  // tangents lie in the surface, they are transformed like positions
+  out_world_tangent = vec4(normalize(model * tangent.xyz), mirrored * tangent.w);
+}
@@ -0,0 +1,9 @@
+// normal maps in the tangent frame written by mesh_vertex.glsl, the shaders that include it have to declare its
+// outputs and the normal_map uniform before
+
+// mikktspace frame: the interpolated vectors are used without normalizing them first
+vec3 mapped_normal(vec3 vertex_normal) {
+  vec3 bitangent = out_world_tangent.w * cross(vertex_normal, out_world_tangent.xyz);
+  vec3 map_normal = 2.0 * texture(normal_map, out_tex_coords).xyz - 1.0;
+  return normalize(map_normal.x * out_world_tangent.xyz + map_normal.y * bitangent + map_normal.z * vertex_normal);
+}
@@ -0,0 +1,71 @@
+#version 140
+in vec3 out_direction;
+
+uniform samplerCube environment;
+uniform float roughness;
+uniform float environment_size;
+
+out vec4 color;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 512u;
+
+float radical_inverse(uint bits) {
+  bits = (bits << 16u) | (bits >> 16u);
+  bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+  bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+  bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+  bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+  return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint n) {
+  return vec2(float(i) / float(n), radical_inverse(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float a) {
+  float phi = 2.0 * PI * xi.x;
+  float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+  float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+  vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+  vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+  vec3 tangent = normalize(cross(up, normal));
+  vec3 bitangent = cross(normal, tangent);
+  return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+float distribution_ggx(float n_dot_h, float a) {
+  float a2 = a * a;
+  float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PI * denom * denom);
+}
+
+void main() {
+  vec3 normal = normalize(out_direction);
+  vec3 view = normal;
+  float a = roughness * roughness;
+
+  vec3 prefiltered = vec3(0.0);
+  float total_weight = 0.0;
+
+  for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+    vec3 h = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, a);
+    vec3 l = normalize(2.0 * dot(view, h) * h - view);
+    float n_dot_l = max(dot(normal, l), 0.0);
+
+    if (n_dot_l > 0.0) {
+      // sampling a blurrier mip of the source for unlikely directions avoids bright speckles
+      float n_dot_h = max(dot(normal, h), 0.0);
+      float pdf = distribution_ggx(n_dot_h, a) * 0.25 + 0.0001;
+      float texel_solid_angle = 4.0 * PI / (6.0 * environment_size * environment_size);
+      float sample_solid_angle = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+      float lod = roughness == 0.0 ? 0.0 : 0.5 * log2(sample_solid_angle / texel_solid_angle);
+
+      prefiltered += textureLod(environment, l, lod).rgb * n_dot_l;
+      total_weight += n_dot_l;
+    }
+  }
+
+  color = vec4(prefiltered / total_weight, 1.0);
+}
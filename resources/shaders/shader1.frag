@@ -2,20 +2,69 @@
 in vec3 out_normal;
 in vec3 out_position;
 in vec2 out_tex_coords;
-in vec3 light;
+in vec3 out_world_position;
+in vec3 out_world_normal;
+in vec4 out_world_tangent;
 
 uniform sampler2D tex;
+// only the alpha of the material is used, the color comes from the mesh texture
+uniform vec4 base_color;
+uniform sampler2D normal_map;
+uniform bool has_normal_map;
+uniform bool alpha_test;
+uniform float alpha_cutoff;
+// screen space ambient occlusion of the opaque meshes, white without ssao
+uniform sampler2D occlusion_sampler;
+uniform vec3 camera_pos;
+uniform vec3 camera_front;
+uniform bool receive_shadows;
+
+#include "lights.glsl"
+#include "light_block.glsl"
+#include "shadows.glsl"
+#include "fog.glsl"
+#include "normal_mapping.glsl"
 
 out vec4 color;
 
-void main() {
-  float diffuse = max(0.05, dot(normalize(out_normal), normalize(light - out_position)));
+const float ambient = 0.05;
+
+vec3 shade_light(int i, vec3 normal, vec3 view_dir) {
+  vec4 dir_intensity = light_data[4 * i + 1];
+  vec4 color_outer = light_data[4 * i + 2];
+  vec3 light_dir;
+  float falloff = light_falloff(light_data[4 * i], dir_intensity, color_outer, light_data[4 * i + 3], out_world_position, light_dir);
 
-  vec3 light_dir = normalize(light - out_position);
-  vec3 view_dir = normalize(-out_position);
+  float diffuse = max(dot(normal, light_dir), 0.0);
   vec3 half_dir = normalize(light_dir + view_dir);
-  
-  float specular = pow(max(dot(normalize(out_normal), half_dir), 0.0), 16.0);
-  
-  color = (specular + diffuse) * texture(tex, out_tex_coords);
+  float specular = pow(max(dot(normal, half_dir), 0.0), 16.0);
+
+  float shadow = receive_shadows ? shadow_factor(i, out_world_position, normal, light_dir) : 1.0;
+
+  return (diffuse + specular) * falloff * shadow * dir_intensity.w * color_outer.rgb;
+}
+
+void main() {
+  // textures are stored gamma encoded, lighting happens in linear space
+  vec4 tex_color = texture(tex, out_tex_coords);
+  float alpha = tex_color.a * base_color.a;
+  if (alpha_test && alpha < alpha_cutoff) {
+    discard;
+  }
+
+  vec3 normal = normalize(out_world_normal);
+  if (has_normal_map) {
+    normal = mapped_normal(out_world_normal);
+  }
+  vec3 view_dir = normalize(camera_pos - out_world_position);
+
+  float occlusion = texture(occlusion_sampler, gl_FragCoord.xy / vec2(textureSize(occlusion_sampler, 0))).r;
+  vec3 lighting = vec3(ambient * occlusion);
+  for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+    lighting += shade_light(i, normal, view_dir);
+  }
+
+  vec3 lit = lighting * pow(tex_color.rgb, vec3(2.2));
+  float visibility = fog_visibility(length(out_position), out_world_position.y - camera_pos.y);
+  color = vec4(mix(fog_color_mode.rgb, lit, visibility), alpha);
 }
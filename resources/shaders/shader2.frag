@@ -0,0 +1,81 @@
+#version 140
+in vec3 out_normal;
+in vec3 out_position;
+in vec2 out_tex_coords;
+in vec3 out_world_position;
+in vec3 out_world_normal;
+in vec4 out_world_tangent;
+
+uniform vec3 camera_pos;
+uniform vec3 camera_front;
+
+// material, the maps are multiplied with the factors
+uniform vec4 base_color;
+uniform float metallic;
+uniform float roughness;
+uniform vec3 emissive;
+uniform sampler2D albedo_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D normal_map;
+uniform sampler2D ao_map;
+uniform bool has_normal_map;
+// cutout materials discard everything below the cutoff
+uniform bool alpha_test;
+uniform float alpha_cutoff;
+// screen space ambient occlusion of the opaque meshes, white without ssao
+uniform sampler2D occlusion_sampler;
+
+uniform bool receive_shadows;
+
+#include "lights.glsl"
+#include "light_block.glsl"
+#include "shadows.glsl"
+#include "fog.glsl"
+#include "brdf.glsl"
+#include "ibl.glsl"
+#include "normal_mapping.glsl"
+
+out vec4 color;
+
+// incoming radiance and direction of light i at this fragment
+vec3 light_radiance(int i, vec3 normal, out vec3 light_dir) {
+  vec4 dir_intensity = light_data[4 * i + 1];
+  vec4 color_outer = light_data[4 * i + 2];
+  float falloff = light_falloff(light_data[4 * i], dir_intensity, color_outer, light_data[4 * i + 3], out_world_position, light_dir);
+  float shadow = receive_shadows ? shadow_factor(i, out_world_position, normal, light_dir) : 1.0;
+
+  return falloff * shadow * dir_intensity.w * color_outer.rgb;
+}
+
+void main() {
+  vec4 albedo_sample = texture(albedo_map, out_tex_coords);
+  float alpha = albedo_sample.a * base_color.a;
+  if (alpha_test && alpha < alpha_cutoff) {
+    discard;
+  }
+  vec3 albedo = pow(albedo_sample.rgb, vec3(2.2)) * base_color.rgb;
+  vec4 mr_sample = texture(metallic_roughness_map, out_tex_coords);
+  float metal = clamp(metallic * mr_sample.b, 0.0, 1.0);
+  float rough = clamp(roughness * mr_sample.g, 0.04, 1.0);
+
+  vec3 normal = normalize(out_world_normal);
+  if (has_normal_map) {
+    normal = mapped_normal(out_world_normal);
+  }
+  vec3 view_dir = normalize(camera_pos - out_world_position);
+
+  vec3 radiance_sum = vec3(0.0);
+  for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+    vec3 light_dir;
+    vec3 radiance = light_radiance(i, normal, light_dir);
+    radiance_sum += cook_torrance(normal, view_dir, light_dir, albedo, metal, rough) * radiance;
+  }
+
+  float ao = texture(ao_map, out_tex_coords).r;
+  ao *= texture(occlusion_sampler, gl_FragCoord.xy / vec2(textureSize(occlusion_sampler, 0))).r;
+  vec3 ambient = image_based_lighting(normal, view_dir, albedo, metal, rough) * ao;
+
+  // linear radiance, exposure and tone mapping happen in a later pass
+  float visibility = fog_visibility(length(out_position), out_world_position.y - camera_pos.y);
+  color = vec4(mix(fog_color_mode.rgb, ambient + radiance_sum + emissive, visibility), alpha);
+}
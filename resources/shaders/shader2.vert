@@ -0,0 +1,2 @@
+#version 140
+#include "mesh_vertex.glsl"
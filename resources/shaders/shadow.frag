@@ -0,0 +1,12 @@
+#version 140
+in vec2 out_tex_coords;
+
+// cutout materials only cast shadows where they are drawn, see drawing/shadows.rs
+uniform sampler2D alpha_map;
+uniform float alpha_threshold;
+
+void main() {
+  if (texture(alpha_map, out_tex_coords).a < alpha_threshold) {
+    discard;
+  }
+}
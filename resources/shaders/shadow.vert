@@ -0,0 +1,19 @@
+#version 140
+in vec3 position;
+in vec2 tex_coords;
+in mat4 instance_transform;
+
+uniform mat4 light_matrix;
+uniform mat4 trans_mat;
+uniform vec3 offset;
+uniform float scale;
+
+out vec2 out_tex_coords;
+
+void main() {
+  vec4 p = trans_mat * vec4(position, 1.0);
+  p.xyz = (scale / p.w) * p.xyz;
+
+  out_tex_coords = tex_coords;
+  gl_Position = light_matrix * instance_transform * vec4(p.xyz + offset, 1.0);
+}
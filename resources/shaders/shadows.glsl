@@ -0,0 +1,59 @@
+// the shadow maps of the lights, the shaders that include it have to include lights.glsl and declare the camera_pos
+// and camera_front uniforms before
+
+const int MAX_SHADOW_MAPS = 8;
+
+// x: first shadow map layer of the light (negative without shadows), y: number of cascades
+layout(std140) uniform ShadowInfoBlock {
+  vec4 shadow_info[MAX_LIGHTS];
+};
+layout(std140) uniform ShadowMatrixBlock {
+  mat4 shadow_matrices[MAX_SHADOW_MAPS];
+};
+uniform sampler2DArrayShadow shadow_maps;
+uniform vec4 cascade_splits;
+
+// fraction of the light with the given slot in the shadow blocks that reaches the position, negative slots are never shadowed
+float shadow_factor(int index, vec3 world_position, vec3 normal, vec3 light_dir) {
+  if (index < 0) {
+    return 1.0;
+  }
+  vec4 info = shadow_info[index];
+  if (info.x < 0.0) {
+    return 1.0;
+  }
+
+  int layer = int(info.x + 0.5);
+  if (info.y > 1.5) {
+    float view_depth = dot(world_position - camera_pos, camera_front);
+    if (view_depth > cascade_splits.w) {
+      return 1.0;
+    }
+    if (view_depth > cascade_splits.y) {
+      layer += 2;
+    } else if (view_depth > cascade_splits.x) {
+      layer += 1;
+    }
+  }
+
+  // normal offset bias against acne on surfaces at a grazing angle to the light
+  float cos_theta = clamp(dot(normal, light_dir), 0.0, 1.0);
+  vec3 biased_position = world_position + 0.02 * (1.0 - cos_theta) * normal;
+  vec4 light_space = shadow_matrices[layer] * vec4(biased_position, 1.0);
+  vec3 map_pos = 0.5 * (light_space.xyz / light_space.w) + 0.5;
+
+  if (map_pos.x < 0.0 || map_pos.x > 1.0 || map_pos.y < 0.0 || map_pos.y > 1.0 || map_pos.z > 1.0) {
+    return 1.0;
+  }
+
+  // 3x3 percentage closer filtering
+  float bias = max(0.002 * (1.0 - cos_theta), 0.0005);
+  vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+  float lit = 0.0;
+  for (int x = -1; x <= 1; x++) {
+    for (int y = -1; y <= 1; y++) {
+      lit += texture(shadow_maps, vec4(map_pos.xy + vec2(x, y) * texel, float(layer), map_pos.z - bias));
+    }
+  }
+  return lit / 9.0;
+}
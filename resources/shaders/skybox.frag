@@ -0,0 +1,33 @@
+#version 140
+in vec3 out_direction;
+
+uniform samplerCube environment_map;
+uniform float intensity;
+uniform vec3 camera_pos;
+
+// fog, see drawing/fog.rs for the layout
+uniform vec4 fog_color_mode;
+uniform vec4 fog_params;
+uniform vec2 fog_height;
+
+out vec4 color;
+
+// the sky is infinitely far away, so it only shows through the fog away from the horizon
+float sky_visibility(vec3 direction) {
+  int mode = int(fog_color_mode.w + 0.5);
+  if (mode == 3) {
+    // a ray that climbs leaves the fog after a finite amount of it, downwards it never does
+    float falloff = max(fog_height.y, 0.0001);
+    float camera_density = fog_params.z * exp(-falloff * (camera_pos.y - fog_height.x));
+    return direction.y > 0.0 ? exp(-camera_density / (falloff * direction.y)) : 0.0;
+  } else if (mode != 0) {
+    return smoothstep(0.0, max(fog_params.w, 0.0001), direction.y);
+  }
+  return 1.0;
+}
+
+void main() {
+  vec3 direction = normalize(out_direction);
+  vec3 sky = intensity * texture(environment_map, direction).rgb;
+  color = vec4(mix(fog_color_mode.rgb, sky, sky_visibility(direction)), 1.0);
+}
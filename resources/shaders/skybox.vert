@@ -0,0 +1,20 @@
+#version 140
+in vec2 position;
+
+uniform mat4 view_matrix;
+uniform mat4 inverse_projection;
+// the far plane in normalized device coordinates, 1 or -1 for reversed z
+uniform float far_ndc;
+
+out vec3 out_direction;
+
+void main() {
+  // on the far plane so everything else drawn before stays in front
+  gl_Position = vec4(position, far_ndc, 1.0);
+
+  // the pixel's view ray between the near plane and a point behind it, rotated back into world space.
+  // both points are finite even without a far plane and the ray also works for orthographic views
+  vec4 near_point = inverse_projection * vec4(position, -far_ndc, 1.0);
+  vec4 inner_point = inverse_projection * vec4(position, 0.0, 1.0);
+  out_direction = transpose(mat3(view_matrix)) * (inner_point.xyz / inner_point.w - near_point.xyz / near_point.w);
+}
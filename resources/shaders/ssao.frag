@@ -0,0 +1,97 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D depth_sampler;
+// world space normals of the g-buffer, only read if has_normals is set
+uniform sampler2D normal_sampler;
+uniform bool has_normals;
+uniform vec2 source_texel;
+
+uniform mat4 view_matrix;
+uniform mat4 projection_matrix;
+uniform mat4 inverse_projection;
+// depth of pixels nothing was drawn to
+uniform float clear_depth;
+
+const int MAX_SAMPLES = 64;
+
+// points in the hemisphere around +z, see drawing/ssao.rs
+layout(std140) uniform SsaoKernelBlock {
+  vec4 kernel[MAX_SAMPLES];
+};
+uniform int sample_count;
+uniform float radius;
+uniform float bias;
+uniform float intensity;
+
+out vec4 color;
+
+// view space has x to the right, y up and z into the screen like the camera basis
+vec3 view_position(vec2 uv) {
+  float depth = texture(depth_sampler, uv).r;
+  vec4 position = inverse_projection * vec4(2.0 * uv - 1.0, 2.0 * depth - 1.0, 1.0);
+  return position.xyz / position.w;
+}
+
+vec2 project(vec3 position) {
+  vec4 clip = projection_matrix * vec4(position, 1.0);
+  return 0.5 * (clip.xy / clip.w) + 0.5;
+}
+
+// the smaller of the two differences to the neighbours, so edges don't bend the normal
+vec3 closer(vec3 center, vec3 a, vec3 b) {
+  return abs(a.z - center.z) < abs(b.z - center.z) ? center - a : b - center;
+}
+
+vec3 view_normal(vec2 uv, vec3 position) {
+  if (has_normals) {
+    vec3 world_normal = texture(normal_sampler, uv).xyz;
+    return normalize(mat3(view_matrix) * world_normal);
+  }
+
+  vec3 dx = closer(position, view_position(uv - vec2(source_texel.x, 0.0)), view_position(uv + vec2(source_texel.x, 0.0)));
+  vec3 dy = closer(position, view_position(uv - vec2(0.0, source_texel.y)), view_position(uv + vec2(0.0, source_texel.y)));
+  vec3 normal = normalize(cross(dx, dy));
+  // towards the camera
+  return dot(normal, position) > 0.0 ? -normal : normal;
+}
+
+// per pixel rotation of the kernel, the blur afterwards hides the pattern
+float interleaved_gradient_noise(vec2 pixel) {
+  return fract(52.9829189 * fract(dot(pixel, vec2(0.06711056, 0.00583715))));
+}
+
+void main() {
+  vec2 uv = (0.5 * out_position) + vec2(0.5, 0.5);
+  if (texture(depth_sampler, uv).r == clear_depth) {
+    color = vec4(1.0);
+    return;
+  }
+
+  vec3 position = view_position(uv);
+  vec3 normal = view_normal(uv, position);
+
+  float angle = 6.28318530718 * interleaved_gradient_noise(gl_FragCoord.xy);
+  vec3 random_dir = vec3(cos(angle), sin(angle), 0.0);
+  // a random direction parallel to the view plane can't be parallel to a normal facing the camera
+  vec3 tangent = normalize(random_dir - normal * dot(random_dir, normal));
+  vec3 bitangent = cross(normal, tangent);
+  mat3 tbn = mat3(tangent, bitangent, normal);
+
+  float occlusion = 0.0;
+  int count = min(sample_count, MAX_SAMPLES);
+  for (int i = 0; i < count; i++) {
+    vec3 sample_pos = position + radius * (tbn * kernel[i].xyz);
+    // behind the camera nothing can be looked up
+    if (sample_pos.z <= 0.0) {
+      continue;
+    }
+    float scene_z = view_position(project(sample_pos)).z;
+    // occluders far in front of the sample belong to other objects and fade out
+    float range = smoothstep(0.0, 1.0, radius / max(abs(position.z - scene_z), 0.0001));
+    occlusion += (scene_z <= sample_pos.z - bias ? 1.0 : 0.0) * range;
+  }
+
+  float ao = clamp(1.0 - intensity * occlusion / float(max(count, 1)), 0.0, 1.0);
+  color = vec4(ao, ao, ao, 1.0);
+}
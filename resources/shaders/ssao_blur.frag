@@ -0,0 +1,46 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D ao_sampler;
+uniform sampler2D depth_sampler;
+// one texel along the axis that is blurred
+uniform vec2 direction;
+uniform int blur_radius;
+uniform mat4 inverse_projection;
+// depth of pixels nothing was drawn to
+uniform float clear_depth;
+
+out vec4 color;
+
+// how quickly samples at a different depth lose their weight
+const float SHARPNESS = 20.0;
+
+float view_depth(vec2 uv) {
+  vec4 position = inverse_projection * vec4(2.0 * uv - 1.0, 2.0 * texture(depth_sampler, uv).r - 1.0, 1.0);
+  return position.z / position.w;
+}
+
+// gaussian weights that also fall off with the depth difference, so occlusion doesn't bleed over edges
+void main() {
+  vec2 uv = (0.5 * out_position) + vec2(0.5, 0.5);
+  if (texture(depth_sampler, uv).r == clear_depth) {
+    color = vec4(1.0);
+    return;
+  }
+
+  float center_depth = view_depth(uv);
+  float sigma = max(0.5 * float(blur_radius), 0.5);
+  float sum = 0.0;
+  float weight_sum = 0.0;
+
+  for (int i = -blur_radius; i <= blur_radius; i++) {
+    vec2 sample_uv = uv + float(i) * direction;
+    float depth_difference = abs(view_depth(sample_uv) - center_depth) / center_depth;
+    float weight = exp(-float(i * i) / (2.0 * sigma * sigma)) * exp(-SHARPNESS * depth_difference);
+    sum += weight * texture(ao_sampler, sample_uv).r;
+    weight_sum += weight;
+  }
+
+  float ao = sum / weight_sum;
+  color = vec4(ao, ao, ao, 1.0);
+}
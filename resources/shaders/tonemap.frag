@@ -0,0 +1,53 @@
+#version 140
+in vec2 out_position;
+
+uniform sampler2D color_sampler;
+uniform sampler2D adapted_sampler;
+uniform sampler2D bloom_sampler;
+uniform float bloom_intensity;
+uniform int tone_operator;
+uniform float exposure;
+uniform bool auto_exposure;
+uniform float key;
+
+out vec4 color;
+
+vec2 tex_coords() {
+  return (0.5 * out_position) + vec2(0.5, 0.5);
+}
+
+vec3 reinhard(vec3 hdr) {
+  return hdr / (hdr + vec3(1.0));
+}
+
+// fit of the ACES reference rendering transform by Krzysztof Narkowicz
+vec3 aces(vec3 hdr) {
+  const float a = 2.51;
+  const float b = 0.03;
+  const float c = 2.43;
+  const float d = 0.59;
+  const float e = 0.14;
+  return clamp((hdr * (a * hdr + b)) / (hdr * (c * hdr + d) + e), 0.0, 1.0);
+}
+
+void main() {
+  vec4 hdr = texture(color_sampler, tex_coords());
+  hdr.rgb += bloom_intensity * texture(bloom_sampler, tex_coords()).rgb;
+
+  float scene_exposure = exposure;
+  if (auto_exposure) {
+    // the adapted texture holds the average log luminance
+    float average_luminance = exp(texelFetch(adapted_sampler, ivec2(0, 0), 0).r);
+    scene_exposure *= key / max(average_luminance, 0.0001);
+  }
+
+  vec3 exposed = hdr.rgb * scene_exposure;
+  vec3 mapped;
+  if (tone_operator == 1) {
+    mapped = aces(exposed);
+  } else {
+    mapped = reinhard(exposed);
+  }
+
+  color = vec4(pow(mapped, vec3(1.0 / 2.2)), hdr.a);
+}
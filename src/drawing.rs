@@ -1,138 +1,503 @@
 use std::cmp::Ordering;
 
-use glium::framebuffer::{SimpleFrameBuffer, DepthRenderBuffer};
+use glium::framebuffer::SimpleFrameBuffer;
 use glium::VertexBuffer;
 use glium::index::{PrimitiveType, NoIndices};
-use glium::texture::DepthFormat;
+use glium::texture::{DepthFormat, DepthTexture2d, MipmapsOption, UncompressedFloatFormat};
 use glium::texture::texture2d::Texture2d;
 use glium::implement_vertex;
 use glium::uniform;
-use glium::Surface;
+use glium::uniforms::{DepthTextureComparison, MagnifySamplerFilter, MinifySamplerFilter, Sampler};
+use glium::{Program, Surface};
 
 use crate::event_handling::camera_transformations::Camera;
+use crate::event_handling::camera_transformations::linalg;
+use antialiasing::{AntiAliasing, Fxaa, MsaaTargets};
+use bloom::Bloom;
+use culling::Frustum;
+use deferred::{DeferredRenderer, RenderPath, GBUFFER_SHADER};
+use environment::{Environment, PREFILTERED_LEVELS};
+use fog::FogSettings;
+use lighting::LightSet;
+use lod::LodMetrics;
+use material::{BlendMode, MaterialLibrary};
+use postprocessing::PostProcessChain;
 use shader_compilation::ShaderProg;
+use shadows::ShadowMaps;
+use skybox::Skybox;
+use ssao::Ssao;
 use texture::Texture;
+use tonemapping::{ToneMapper, ToneMapSettings};
 
+pub mod antialiasing;
+pub mod bloom;
+pub mod culling;
+pub mod deferred;
+pub mod environment;
+pub mod fog;
+pub mod lighting;
+pub mod lod;
+pub mod material;
 pub mod mesh;
+pub mod postprocessing;
+pub mod render_state;
 pub mod shader_compilation;
+pub mod shadows;
+pub mod skybox;
+pub mod ssao;
 pub mod texture;
+pub mod tonemapping;
 
 #[derive(Clone)]
 #[derive(Copy)]
 #[derive(Debug)]
-struct SpriteVertex {
+pub struct SpriteVertex {
     position: [f32; 2]
 }
 
 implement_vertex!(SpriteVertex, position);
 
-pub fn render_meshes(meshes: Vec<&mesh::Mesh>,
-                     camera: &Camera,
-                     display: &glium::Display,
-                     shaders: Vec<&ShaderProg>,
-                     postpr_shader: Option<&ShaderProg>,
-                     textures: Vec<&Texture>,
-                     time: f32) {
-    let mut target = display.draw();
-    let target_dimensions = target.get_dimensions();
-    let target_color = Texture2d::empty(display,
-                                        target_dimensions.0,
-                                        target_dimensions.1).unwrap();
-    let target_depth = DepthRenderBuffer::new(display,
-                                              DepthFormat::I24,
-                                              target_dimensions.0,
-                                              target_dimensions.1).unwrap();
-    let mut framebuffer = SimpleFrameBuffer::with_depth_buffer(display,
-                                                               &target_color,
-                                                               &target_depth).unwrap();
-    
-    target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
-    framebuffer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+//two triangles covering the whole screen
+pub fn fullscreen_quad(display: &glium::Display) -> VertexBuffer<SpriteVertex> {
+    VertexBuffer::new(display,
+                      &[
+                          SpriteVertex{ position: [-1.0, -1.0] },
+                          SpriteVertex{ position: [-1.0,  1.0] },
+                          SpriteVertex{ position: [ 1.0,  1.0] },
+                          SpriteVertex{ position: [ 1.0, -1.0] },
+                          SpriteVertex{ position: [-1.0, -1.0] },
+                          SpriteVertex{ position: [ 1.0,  1.0] }
+                      ]).unwrap()
+}
+
+pub struct Renderer {
+    target_dimensions: (u32, u32),
+    target_color: Texture2d,
+    target_depth: DepthTexture2d,
+    anti_aliasing: AntiAliasing,
+    //the scene is drawn into these instead and resolved into the targets above
+    msaa: Option<MsaaTargets>,
+    fxaa: Option<Fxaa>,
+    //only there when the scene is shaded in screen space
+    deferred: Option<DeferredRenderer>,
+    postpr_vertex_buffer: VertexBuffer<SpriteVertex>,
+    ssao: Option<Ssao>,
+    bloom: Option<Bloom>,
+    tone_mapper: ToneMapper,
+    postpr_chain: PostProcessChain,
+    light_set: LightSet,
+    shadow_maps: ShadowMaps,
+    materials: MaterialLibrary,
+    environment: Environment,
+    skybox: Option<Skybox>,
+    fog: FogSettings,
+    //debug view that draws every filled mesh as lines
+    wireframe: bool
+}
+
+impl Renderer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(display: &glium::Display,
+               postpr_chain: PostProcessChain,
+               light_set: LightSet,
+               shadow_maps: ShadowMaps,
+               materials: MaterialLibrary,
+               environment: Environment,
+               skybox: Option<Skybox>,
+               fog: FogSettings,
+               ssao: Option<Ssao>,
+               bloom: Option<Bloom>,
+               tone_mapping: ToneMapSettings,
+               anti_aliasing: AntiAliasing,
+               render_path: RenderPath) -> Renderer {
+        let target_dimensions = display.get_framebuffer_dimensions();
+        let (target_color, target_depth) = Self::create_render_targets(display, target_dimensions);
+        let msaa = match anti_aliasing {
+            AntiAliasing::Msaa(samples) => Some(MsaaTargets::new(display, target_dimensions, samples)),
+            AntiAliasing::Off | AntiAliasing::Fxaa => None
+        };
+        let fxaa = match anti_aliasing {
+            AntiAliasing::Fxaa => Some(Fxaa::new(display)),
+            AntiAliasing::Off | AntiAliasing::Msaa(_) => None
+        };
+        let deferred = match render_path {
+            RenderPath::Deferred => Some(DeferredRenderer::new(display, target_dimensions)),
+            RenderPath::Forward => None
+        };
+        let postpr_vertex_buffer = fullscreen_quad(display);
+        let tone_mapper = ToneMapper::new(display, tone_mapping, target_dimensions);
+
+        Renderer {
+            target_dimensions,
+            target_color,
+            target_depth,
+            anti_aliasing,
+            msaa,
+            fxaa,
+            deferred,
+            postpr_vertex_buffer,
+            ssao,
+            bloom,
+            tone_mapper,
+            postpr_chain,
+            light_set,
+            shadow_maps,
+            materials,
+            environment,
+            skybox,
+            fog,
+            wireframe: false
+        }
+    }
+
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        self.wireframe = wireframe;
+    }
+
+    fn create_render_targets(display: &glium::Display,
+                             dimensions: (u32, u32)) -> (Texture2d, DepthTexture2d) {
+        //floating point so lighting is not clamped before tone mapping
+        let target_color = Texture2d::empty_with_format(display,
+                                                        UncompressedFloatFormat::F16F16F16F16,
+                                                        MipmapsOption::NoMipmap,
+                                                        dimensions.0,
+                                                        dimensions.1).unwrap();
+        //a depth texture instead of a render buffer so postprocessing passes can sample it
+        let target_depth = DepthTexture2d::empty_with_format(display,
+                                                             DepthFormat::I24,
+                                                             MipmapsOption::NoMipmap,
+                                                             dimensions.0,
+                                                             dimensions.1).unwrap();
+        (target_color, target_depth)
+    }
+
+    //only reallocates the offscreen targets if the window size actually changed
+    fn update_render_targets(&mut self, display: &glium::Display, dimensions: (u32, u32)) {
+        if dimensions != self.target_dimensions {
+            let (target_color, target_depth) = Self::create_render_targets(display, dimensions);
+            self.target_color = target_color;
+            self.target_depth = target_depth;
+            if let AntiAliasing::Msaa(samples) = self.anti_aliasing {
+                self.msaa = Some(MsaaTargets::new(display, dimensions, samples));
+            }
+            if let Some(deferred) = &mut self.deferred {
+                deferred.update_targets(display, dimensions);
+            }
+            self.target_dimensions = dimensions;
+        }
+    }
+
+    pub fn render_meshes(&mut self,
+                         meshes: Vec<&mesh::Mesh>,
+                         camera: &Camera,
+                         display: &glium::Display,
+                         shaders: Vec<&ShaderProg>,
+                         textures: Vec<&Texture>,
+                         time: f32) {
+        //nothing to draw into while the window is minimized
+        let viewport = camera.viewport(display.get_framebuffer_dimensions());
+        if viewport.width == 0 || viewport.height == 0 {
+            return;
+        }
+
+        self.shadow_maps.render(display, &meshes, camera, self.light_set.get_lights(), &self.materials, &textures);
+
+        //the offscreen targets only cover the viewport, the letterbox bars stay cleared
+        let mut target = display.draw();
+        let target_dimensions = (viewport.width, viewport.height);
+        self.update_render_targets(display, target_dimensions);
+
+        //the framebuffer object itself is cached by glium, only the attachments need to persist
+        let mut framebuffer = match &self.msaa {
+            Some(msaa) => SimpleFrameBuffer::with_depth_buffer(display, &msaa.color, &msaa.depth).unwrap(),
+            None => SimpleFrameBuffer::with_depth_buffer(display, &self.target_color, &self.target_depth).unwrap()
+        };
+
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), camera.clear_depth());
+        framebuffer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), camera.clear_depth());
     
-    let params = glium::DrawParameters {
-        depth: glium::Depth {
-                test: glium::draw_parameters::DepthTest::IfLess,
-                write: true,
-                .. Default::default()
+        //culling, polygon mode and the depth test are set per mesh from its render state
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                    write: true,
+                    .. Default::default()
+                },
+            .. Default::default()
+        };
+
+        //blended meshes are tested against the opaque depth but don't hide each other
+        let blended_params = glium::DrawParameters {
+            depth: glium::Depth {
+                    write: false,
+                    .. Default::default()
+                },
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::SourceAlpha,
+                    destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
+                },
+                alpha: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::OneMinusSourceAlpha
+                },
+                constant_value: (0.0, 0.0, 0.0, 0.0)
             },
-        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise,
-        .. Default::default()
-    };
-    
-    for mesh in meshes.iter() {
-        let shader_id = mesh.shader_id;
-        let current_shader_index = shaders.binary_search_by(|prog| {
-            if prog.id < shader_id {
-                Ordering::Less
-            } else if prog.id > shader_id {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
+            .. Default::default()
+        };
+
+        //shadows are rendered before this, casters outside the view can still throw shadows into it
+        let frustum = Frustum::from_camera(camera);
+        let mut opaque_queue : Vec<&mesh::Mesh> = Vec::new();
+        let mut blended_queue : Vec<(&mesh::Mesh, f32)> = Vec::new();
+
+        for mesh in meshes.iter() {
+            let distance = match mesh.world_bounds() {
+                Some(bounds) => {
+                    if !frustum.intersects(&bounds) {
+                        continue;
+                    }
+                    //the shadow passes of the next frame use the level picked here
+                    let distance = linalg::mag(linalg::sub(bounds.sphere.center, camera.position));
+                    mesh.update_lod(LodMetrics::new(distance, bounds.sphere.radius, camera.half_view_height(distance)));
+                    distance
+                },
+                None => {
+                    let offset = [mesh.offset.0, mesh.offset.1, mesh.offset.2];
+                    linalg::mag(linalg::sub(offset, camera.position))
+                }
+            };
+
+            match self.materials.get(mesh.material_id).config.blend_mode {
+                BlendMode::Blended => blended_queue.push((mesh, distance)),
+                BlendMode::Opaque | BlendMode::Cutout => opaque_queue.push(mesh)
             }
-        }).unwrap();
-
-        let current_shader = shaders.get(current_shader_index).unwrap();
-
-        let current_texture_index = textures.binary_search_by( |tex| {
-            let tex_id = mesh.texture_id;
-            if tex.get_id() < tex_id {
-                Ordering::Less
-            } else if tex.get_id() > tex_id {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
+        }
+
+        match &self.deferred {
+            Some(deferred) => {
+                //the g-buffer has its own framebuffer, the one above is used again from the skybox on
+                let mut geometry = deferred.geometry_framebuffer(display, &self.target_color, &self.target_depth);
+                geometry.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), camera.clear_depth());
+                let (gbuffer_queue, forward_queue) : (Vec<&mesh::Mesh>, Vec<&mesh::Mesh>) = opaque_queue.into_iter()
+                    .partition(|mesh| mesh.shader_id == GBUFFER_SHADER);
+                for mesh in gbuffer_queue {
+                    self.draw_mesh(&mut geometry, mesh, camera, deferred.get_gbuffer_prog(), &textures, &params);
+                }
+                if let Some(ssao) = &mut self.ssao {
+                    ssao.compute(display, &self.postpr_vertex_buffer, &self.target_depth, Some(deferred.get_normals()), camera);
+                }
+                let occlusion = self.ssao.as_ref()
+                    .and_then(|ssao| ssao.get_occlusion())
+                    .unwrap_or(self.materials.get_white());
+                deferred.render_ambient(display,
+                                        &self.postpr_vertex_buffer,
+                                        &self.target_color,
+                                        &self.target_depth,
+                                        camera,
+                                        &self.environment,
+                                        occlusion,
+                                        &self.fog);
+                deferred.render_lights(display,
+                                       &self.postpr_vertex_buffer,
+                                       &self.target_color,
+                                       &self.target_depth,
+                                       camera,
+                                       &self.light_set,
+                                       &self.shadow_maps,
+                                       &self.fog);
+                //tested against the depth of the g-buffer pass, like the blended meshes below
+                for mesh in forward_queue {
+                    self.draw_mesh(&mut framebuffer, mesh, camera, mesh_program(&shaders, mesh), &textures, &params);
+                }
+            },
+            None => {
+                /* the occlusion has to be there before the ambient light is shaded, so the opaque meshes
+                 * lay down their depth once without color first */
+                if self.ssao.is_some() {
+                    let prepass_params = glium::DrawParameters {
+                        color_mask: (false, false, false, false),
+                        .. params.clone()
+                    };
+                    let mut prepass = SimpleFrameBuffer::with_depth_buffer(display, &self.target_color, &self.target_depth).unwrap();
+                    prepass.clear_depth(camera.clear_depth());
+                    for mesh in opaque_queue.iter() {
+                        self.draw_mesh(&mut prepass, mesh, camera, mesh_program(&shaders, mesh), &textures, &prepass_params);
+                    }
+                    if let Some(ssao) = &mut self.ssao {
+                        ssao.compute(display, &self.postpr_vertex_buffer, &self.target_depth, None, camera);
+                    }
+                    framebuffer.clear_depth(camera.clear_depth());
+                }
+                for mesh in opaque_queue {
+                    self.draw_mesh(&mut framebuffer, mesh, camera, mesh_program(&shaders, mesh), &textures, &params);
+                }
             }
-        }).unwrap();
+        }
+
+        if let Some(skybox) = &self.skybox {
+            skybox.render(&mut framebuffer, &self.postpr_vertex_buffer, camera, &self.environment, &self.fog);
+        }
+
+        //after the skybox so it shows through, the furthest mesh is blended first
+        blended_queue.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (mesh, _) in blended_queue {
+            self.draw_mesh(&mut framebuffer, mesh, camera, mesh_program(&shaders, mesh), &textures, &blended_params);
+        }
 
-        let current_texture = textures.get(current_texture_index).unwrap();
-        
-        match &mesh.vertex_buf {
-            Some(buf) => { framebuffer.draw(buf,
+        if let Some(msaa) = &self.msaa {
+            msaa.resolve(display, &self.target_color, &self.target_depth);
+        }
+
+        let bloom = match &mut self.bloom {
+            Some(bloom) => {
+                let intensity = bloom.get_intensity();
+                bloom.render(display, &self.postpr_vertex_buffer, &self.target_color)
+                    .map(|texture| (texture, intensity))
+            },
+            None => None
+        };
+
+        let tone_mapped = self.tone_mapper.render(display,
+                                                  &self.postpr_vertex_buffer,
+                                                  &self.target_color,
+                                                  bloom,
+                                                  time);
+        let anti_aliased = match &mut self.fxaa {
+            Some(fxaa) => fxaa.render(display, &self.postpr_vertex_buffer, tone_mapped),
+            None => tone_mapped
+        };
+
+        self.postpr_chain.render(display,
+                                 &mut target,
+                                 &self.postpr_vertex_buffer,
+                                 anti_aliased,
+                                 &self.target_depth,
+                                 viewport,
+                                 time);
+
+        target.finish().unwrap();
+    }
+
+    fn draw_mesh<S: Surface>(&self,
+                             framebuffer: &mut S,
+                             mesh: &mesh::Mesh,
+                             camera: &Camera,
+                             program: &Program,
+                             textures: &[&Texture],
+                             params: &glium::DrawParameters) {
+        //hardware depth comparison with linear filtering already gives 2x2 pcf per lookup
+        let shadow_sampler = Sampler::new(self.shadow_maps.get_maps())
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .depth_texture_comparison(Some(DepthTextureComparison::LessOrEqual));
+        let cascade_splits = self.shadow_maps.get_cascade_splits();
+
+        let current_texture = mesh_texture(textures, mesh);
+        let material = self.materials.bind(mesh.material_id);
+        let occlusion = match &self.ssao {
+            Some(ssao) if samples_occlusion(material.config.blend_mode, self.deferred.is_some()) => ssao.get_occlusion(),
+            _ => None
+        }.unwrap_or(self.materials.get_white());
+        let render_state = mesh.render_state.apply(material.config.render_state);
+        let (fog_color_mode, fog_params, fog_height) = self.fog.pack();
+        let mut params = render_state.draw_parameters(params, self.wireframe);
+        params.depth.test = camera.depth_test(params.depth.test);
+    
+        match (mesh.current_vertex_buf(), &mesh.instance_buf) {
+            (Some(buf), Some(instances)) => { framebuffer.draw((buf, instances.per_instance().unwrap()),
                                             NoIndices(PrimitiveType::TrianglesList),
-                                            &current_shader.prog,
+                                            program,
                                             &uniform! {
                                                 camera_pos: camera.position,
-                                                camera_right: camera.right,
-                                                camera_up: camera.up,
                                                 camera_front: camera.front,
-                                                camera_fov: camera.fov,
-                                                aspect_ratio: camera.view_aspect_ratio,
+                                                view_matrix: camera.view_matrix(),
+                                                projection_matrix: camera.projection_matrix(),
                                                 trans_mat: mesh.transform_mat,
                                                 offset: mesh.offset,
                                                 scale: mesh.scale,
                                                 tex: current_texture.get_texture(),
+                                                LightBlock: self.light_set.get_buffer(),
+                                                light_count: self.light_set.count(),
+                                                shadow_maps: shadow_sampler,
+                                                ShadowMatrixBlock: self.shadow_maps.get_matrix_buffer(),
+                                                ShadowInfoBlock: self.shadow_maps.get_info_buffer(),
+                                                cascade_splits: cascade_splits,
+                                                receive_shadows: mesh.receive_shadows,
+                                                base_color: material.config.base_color,
+                                                metallic: material.config.metallic,
+                                                roughness: material.config.roughness,
+                                                emissive: material.config.emissive,
+                                                albedo_map: material.albedo_map,
+                                                metallic_roughness_map: material.metallic_roughness_map,
+                                                normal_map: material.normal_map,
+                                                ao_map: material.ao_map,
+                                                has_normal_map: material.has_normal_map,
+                                                irradiance_map: self.environment.irradiance_sampler(),
+                                                prefiltered_map: self.environment.prefiltered_sampler(),
+                                                prefiltered_levels: PREFILTERED_LEVELS as f32,
+                                                brdf_lut: self.environment.brdf_lut_sampler(),
+                                                environment_map: self.environment.cubemap_sampler(),
+                                                alpha_test: material.config.blend_mode == BlendMode::Cutout,
+                                                alpha_cutoff: material.config.alpha_cutoff,
+                                                occlusion_sampler: occlusion.sampled()
+                                                    .magnify_filter(MagnifySamplerFilter::Nearest)
+                                                    .minify_filter(MinifySamplerFilter::Nearest),
+                                                fog_color_mode: fog_color_mode,
+                                                fog_params: fog_params,
+                                                fog_height: fog_height,
                                             },
                                             &params).unwrap();
             },
-            None => {}
+            _ => {}
         }
     }
+}
 
-    let postpr_vertex_buffer = VertexBuffer::new(display,
-                                                 &[
-                                                     SpriteVertex{ position: [-1.0, -1.0] },
-                                                     SpriteVertex{ position: [-1.0,  1.0] },
-                                                     SpriteVertex{ position: [ 1.0,  1.0] },
-                                                     SpriteVertex{ position: [ 1.0, -1.0] },
-                                                     SpriteVertex{ position: [-1.0, -1.0] },
-                                                     SpriteVertex{ position: [ 1.0,  1.0] }
-                                                 ]).unwrap();
-   
-    match postpr_shader {
-        Some(prog) => {
-            let uniforms = uniform! {
-                color_sampler: target_color,
-                time: time,
-                resolution: [target_dimensions.0 as f32, target_dimensions.1 as f32]
-            };
-            target.draw(&postpr_vertex_buffer,
-                        NoIndices(PrimitiveType::TrianglesList),
-                        prog.get_prog(),
-                        &uniforms,
-                        &Default::default()).unwrap();
-        },
-        None => {}
+/* whether the forward shaders darken the ambient light of a mesh by the ssao result. only opaque meshes
+ * shaded forward were in the depth the occlusion was computed from, with deferred shading the ambient
+ * pass occludes the g-buffer instead */
+fn samples_occlusion(blend_mode: BlendMode, deferred: bool) -> bool {
+    match blend_mode {
+        BlendMode::Opaque | BlendMode::Cutout => !deferred,
+        BlendMode::Blended => false
     }
-    
-    target.finish().unwrap();
+}
+
+//the texture of the texture id a mesh asks for, textures are sorted by id
+fn mesh_texture<'a>(textures: &[&'a Texture], mesh: &mesh::Mesh) -> &'a Texture {
+    let current_texture_index = textures.binary_search_by( |tex| {
+        let tex_id = mesh.texture_id;
+        if tex.get_id() < tex_id {
+            Ordering::Less
+        } else if tex.get_id() > tex_id {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }).unwrap();
+    textures[current_texture_index]
+}
+
+//the program of the shader id a mesh asks for, shaders are sorted by id
+fn mesh_program<'a>(shaders: &[&'a ShaderProg], mesh: &mesh::Mesh) -> &'a Program {
+    let shader_id = mesh.shader_id;
+    let current_shader_index = shaders.binary_search_by(|prog| {
+        if prog.id < shader_id {
+            Ordering::Less
+        } else if prog.id > shader_id {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }).unwrap();
+    &shaders[current_shader_index].prog
+}
+
+#[test]
+fn samples_occlusion_test() {
+    assert!(samples_occlusion(BlendMode::Opaque, false));
+    assert!(samples_occlusion(BlendMode::Cutout, false));
+    //transparent surfaces weren't in the depth prepass
+    assert!(!samples_occlusion(BlendMode::Blended, false));
+    assert!(!samples_occlusion(BlendMode::Opaque, true));
 }
@@ -0,0 +1,202 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{DepthFormat, DepthTexture2d, DepthTexture2dMultisample, MipmapsOption,
+                     Texture2dMultisample, TextureFormat, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{BlitMask, BlitTarget, CapabilitiesSource, Display, Program, Rect, Surface, VertexBuffer};
+use glium::uniform;
+
+use super::SpriteVertex;
+use super::deferred::RenderPath;
+use super::shader_compilation;
+
+//the only sample count gl promises for multisampled textures
+const GUARANTEED_SAMPLES: u32 = 4;
+//formats of the multisampled targets
+const COLOR_FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F16F16F16F16;
+const DEPTH_FORMAT: DepthFormat = DepthFormat::I24;
+
+/* the most samples the driver takes for both target formats. drivers that can't be asked are
+ * assumed to take the guaranteed count */
+pub fn max_samples(display: &Display) -> u32 {
+    let formats = display.get_capabilities();
+    [TextureFormat::UncompressedFloat(COLOR_FORMAT), TextureFormat::DepthFormat(DEPTH_FORMAT)].iter()
+        .map(|format| {
+            formats.internal_formats_textures.get(format)
+                .and_then(|infos| infos.multisamples.as_ref())
+                .and_then(|samples| samples.iter().max().copied())
+                .map(|max| max.max(0) as u32)
+                .unwrap_or(GUARANTEED_SAMPLES)
+        })
+        .min()
+        .unwrap_or(GUARANTEED_SAMPLES)
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub enum AntiAliasing {
+    #[default]
+    Off,
+    //the scene is rendered into multisampled targets with this many samples and resolved afterwards
+    Msaa(u32),
+    //edges are smoothed in a single pass over the tone mapped image
+    Fxaa
+}
+
+impl AntiAliasing {
+    //sample counts that can be asked for, whether the driver takes them is checked with supported
+    pub fn valid_samples(samples: u32) -> bool {
+        matches!(samples, 2 | 4 | 8 | 16)
+    }
+
+    //msaa with as many samples as the driver takes, up to the requested ones, or none if it takes less than 2
+    pub fn supported(self, max_samples: u32) -> AntiAliasing {
+        match self {
+            AntiAliasing::Msaa(samples) if samples > max_samples => {
+                match [16, 8, 4, 2].into_iter().find(|count| *count <= max_samples) {
+                    Some(count) => AntiAliasing::Msaa(count),
+                    None => AntiAliasing::Off
+                }
+            },
+            _ => self
+        }
+    }
+
+    //the g-buffer would need multisampled targets and a light pass per sample, so deferred shading goes without msaa
+    pub fn for_render_path(self, render_path: RenderPath) -> AntiAliasing {
+        match (render_path, self) {
+            (RenderPath::Deferred, AntiAliasing::Msaa(_)) => AntiAliasing::Off,
+            _ => self
+        }
+    }
+
+    //the command line form: off, fxaa, msaa or msaa:<samples>
+    pub fn parse(text: &str) -> Option<AntiAliasing> {
+        match text.split_once(':') {
+            Some(("msaa", samples)) => {
+                let samples = samples.parse::<u32>().ok()?;
+                if Self::valid_samples(samples) { Some(AntiAliasing::Msaa(samples)) } else { None }
+            },
+            Some(_) => None,
+            None => match text {
+                "off" => Some(AntiAliasing::Off),
+                "fxaa" => Some(AntiAliasing::Fxaa),
+                "msaa" => Some(AntiAliasing::Msaa(4)),
+                _ => None
+            }
+        }
+    }
+}
+
+//multisampled versions of the hdr color and depth targets the scene is drawn into
+pub struct MsaaTargets {
+    pub color: Texture2dMultisample,
+    pub depth: DepthTexture2dMultisample
+}
+
+impl MsaaTargets {
+    //samples have to be supported by the driver, see AntiAliasing::supported
+    pub fn new(display: &Display, dimensions: (u32, u32), samples: u32) -> MsaaTargets {
+        MsaaTargets {
+            color: Texture2dMultisample::empty_with_format(display,
+                                                          COLOR_FORMAT,
+                                                          MipmapsOption::NoMipmap,
+                                                          dimensions.0,
+                                                          dimensions.1,
+                                                          samples).unwrap(),
+            depth: DepthTexture2dMultisample::empty_with_format(display,
+                                                                DEPTH_FORMAT,
+                                                                MipmapsOption::NoMipmap,
+                                                                dimensions.0,
+                                                                dimensions.1,
+                                                                samples).unwrap()
+        }
+    }
+
+    //averages the color samples and keeps one depth sample, so bloom and the postprocessing
+    //chain can read the usual single sampled textures
+    pub fn resolve(&self, display: &Display, color: &Texture2d, depth: &DepthTexture2d) {
+        let source = SimpleFrameBuffer::with_depth_buffer(display, &self.color, &self.depth).unwrap();
+        let target = SimpleFrameBuffer::with_depth_buffer(display, color, depth).unwrap();
+        let (width, height) = color.dimensions();
+
+        //depth can only be blitted with nearest filtering
+        target.blit_buffers_from_simple_framebuffer(&source,
+                                                    &Rect { left: 0, bottom: 0, width, height },
+                                                    &BlitTarget { left: 0, bottom: 0, width: width as i32, height: height as i32 },
+                                                    MagnifySamplerFilter::Nearest,
+                                                    BlitMask::color_and_depth());
+    }
+}
+
+//fast approximate anti-aliasing on the gamma encoded image, before the postprocessing chain
+pub struct Fxaa {
+    prog: Program,
+    output: Option<Texture2d>
+}
+
+impl Fxaa {
+    pub fn new(display: &Display) -> Fxaa {
+        Fxaa {
+            prog: shader_compilation::load_program("shaderpp.vert", "fxaa.frag", display),
+            output: None
+        }
+    }
+
+    pub fn render(&mut self,
+                  display: &Display,
+                  quad: &VertexBuffer<SpriteVertex>,
+                  source: &Texture2d) -> &Texture2d {
+        let dimensions = source.dimensions();
+        if self.output.as_ref().map(|output| output.dimensions()) != Some(dimensions) {
+            self.output = Some(Texture2d::empty(display, dimensions.0, dimensions.1).unwrap());
+        }
+        let output = self.output.as_ref().unwrap();
+
+        let mut framebuffer = SimpleFrameBuffer::new(display, output).unwrap();
+        framebuffer.draw(quad,
+                         NoIndices(PrimitiveType::TrianglesList),
+                         &self.prog,
+                         &uniform! {
+                             source_sampler: source.sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Linear)
+                                 .minify_filter(MinifySamplerFilter::Linear)
+                                 .wrap_function(SamplerWrapFunction::Clamp),
+                             source_texel: [1.0 / dimensions.0 as f32, 1.0 / dimensions.1 as f32]
+                         },
+                         &Default::default()).unwrap();
+
+        output
+    }
+}
+
+#[test]
+fn anti_aliasing_parse_test() {
+    assert_eq!(AntiAliasing::parse("fxaa"), Some(AntiAliasing::Fxaa));
+    assert_eq!(AntiAliasing::parse("msaa"), Some(AntiAliasing::Msaa(4)));
+    assert_eq!(AntiAliasing::parse("msaa:8"), Some(AntiAliasing::Msaa(8)));
+    assert_eq!(AntiAliasing::parse("msaa:3"), None);
+    assert_eq!(AntiAliasing::parse("fxaa:2"), None);
+    assert_eq!(AntiAliasing::parse("smaa"), None);
+}
+
+#[test]
+fn supported_samples_test() {
+    assert_eq!(AntiAliasing::Msaa(16).supported(8), AntiAliasing::Msaa(8));
+    assert_eq!(AntiAliasing::Msaa(16).supported(6), AntiAliasing::Msaa(4));
+    assert_eq!(AntiAliasing::Msaa(4).supported(32), AntiAliasing::Msaa(4));
+    assert_eq!(AntiAliasing::Msaa(4).supported(1), AntiAliasing::Off);
+    assert_eq!(AntiAliasing::Fxaa.supported(0), AntiAliasing::Fxaa);
+}
+
+#[test]
+fn render_path_anti_aliasing_test() {
+    assert_eq!(AntiAliasing::Msaa(4).for_render_path(RenderPath::Deferred), AntiAliasing::Off);
+    assert_eq!(AntiAliasing::Msaa(4).for_render_path(RenderPath::Forward), AntiAliasing::Msaa(4));
+    //fxaa only needs the finished image
+    assert_eq!(AntiAliasing::Fxaa.for_render_path(RenderPath::Deferred), AntiAliasing::Fxaa);
+}
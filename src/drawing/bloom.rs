@@ -0,0 +1,172 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{MipmapsOption, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::uniform;
+
+use super::SpriteVertex;
+use super::shader_compilation;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct BloomSettings {
+    //luminance above which pixels start to glow
+    pub threshold: f32,
+    //width of the soft transition around the threshold, relative to it
+    pub knee: f32,
+    //how strongly the blurred highlights are added back to the scene
+    pub intensity: f32,
+    //number of downsampled levels, each half the size of the one before
+    pub levels: u32,
+    //radius of the upsampling tent filter in texels
+    pub radius: f32
+}
+
+impl Default for BloomSettings {
+    fn default() -> BloomSettings {
+        BloomSettings {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.05,
+            levels: 6,
+            radius: 1.0
+        }
+    }
+}
+
+//sizes of the mip chain, starting at half the target size and stopping before a level gets too small
+fn chain_dimensions(dimensions: (u32, u32), levels: u32) -> Vec<(u32, u32)> {
+    let mut res : Vec<(u32, u32)> = Vec::new();
+    let (mut width, mut height) = (dimensions.0 / 2, dimensions.1 / 2);
+    while (res.len() as u32) < levels && width >= 2 && height >= 2 {
+        res.push((width, height));
+        width /= 2;
+        height /= 2;
+    }
+    res
+}
+
+//bright parts of the hdr scene, blurred by going down a chain of half sized targets and back up again
+pub struct Bloom {
+    settings: BloomSettings,
+    threshold_prog: Program,
+    downsample_prog: Program,
+    upsample_prog: Program,
+    dimensions: (u32, u32),
+    chain: Vec<Texture2d>
+}
+
+impl Bloom {
+    pub fn new(display: &Display, settings: BloomSettings) -> Bloom {
+        Bloom {
+            settings,
+            threshold_prog: shader_compilation::load_program("shaderpp.vert", "bloom_threshold.frag", display),
+            downsample_prog: shader_compilation::load_program("shaderpp.vert", "bloom_downsample.frag", display),
+            upsample_prog: shader_compilation::load_program("shaderpp.vert", "bloom_upsample.frag", display),
+            dimensions: (0, 0),
+            chain: Vec::new()
+        }
+    }
+
+    pub fn get_intensity(&self) -> f32 {
+        self.settings.intensity
+    }
+
+    fn update_chain(&mut self, display: &Display, dimensions: (u32, u32)) {
+        if dimensions != self.dimensions {
+            self.chain = chain_dimensions(dimensions, self.settings.levels).into_iter()
+                .map(|(w, h)| Texture2d::empty_with_format(display,
+                                                           UncompressedFloatFormat::F16F16F16F16,
+                                                           MipmapsOption::NoMipmap,
+                                                           w,
+                                                           h).unwrap())
+                .collect();
+            self.dimensions = dimensions;
+        }
+    }
+
+    //returns the blurred highlights at half resolution, None if the target is too small for a chain
+    pub fn render(&mut self,
+                  display: &Display,
+                  quad: &VertexBuffer<SpriteVertex>,
+                  scene_color: &Texture2d) -> Option<&Texture2d> {
+        self.update_chain(display, scene_color.dimensions());
+        if self.chain.is_empty() {
+            return None;
+        }
+
+        let texel = |texture: &Texture2d| [1.0 / texture.width() as f32, 1.0 / texture.height() as f32];
+
+        let mut framebuffer = SimpleFrameBuffer::new(display, &self.chain[0]).unwrap();
+        framebuffer.draw(quad,
+                         NoIndices(PrimitiveType::TrianglesList),
+                         &self.threshold_prog,
+                         &uniform! {
+                             source_sampler: scene_color.sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Linear)
+                                 .minify_filter(MinifySamplerFilter::Linear)
+                                 .wrap_function(SamplerWrapFunction::Clamp),
+                             source_texel: texel(scene_color),
+                             threshold: self.settings.threshold,
+                             knee: self.settings.knee
+                         },
+                         &Default::default()).unwrap();
+
+        for i in 1..self.chain.len() {
+            let mut framebuffer = SimpleFrameBuffer::new(display, &self.chain[i]).unwrap();
+            framebuffer.draw(quad,
+                             NoIndices(PrimitiveType::TrianglesList),
+                             &self.downsample_prog,
+                             &uniform! {
+                                 source_sampler: self.chain[i - 1].sampled()
+                                     .magnify_filter(MagnifySamplerFilter::Linear)
+                                     .minify_filter(MinifySamplerFilter::Linear)
+                                     .wrap_function(SamplerWrapFunction::Clamp),
+                                 source_texel: texel(&self.chain[i - 1])
+                             },
+                             &Default::default()).unwrap();
+        }
+
+        //every level gets the blurred level below it added on top
+        let additive = glium::DrawParameters {
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::One
+                },
+                alpha: glium::BlendingFunction::AlwaysReplace,
+                constant_value: (0.0, 0.0, 0.0, 0.0)
+            },
+            .. Default::default()
+        };
+
+        for i in (1..self.chain.len()).rev() {
+            let mut framebuffer = SimpleFrameBuffer::new(display, &self.chain[i - 1]).unwrap();
+            framebuffer.draw(quad,
+                             NoIndices(PrimitiveType::TrianglesList),
+                             &self.upsample_prog,
+                             &uniform! {
+                                 source_sampler: self.chain[i].sampled()
+                                     .magnify_filter(MagnifySamplerFilter::Linear)
+                                     .minify_filter(MinifySamplerFilter::Linear)
+                                     .wrap_function(SamplerWrapFunction::Clamp),
+                                 source_texel: texel(&self.chain[i]),
+                                 radius: self.settings.radius
+                             },
+                             &additive).unwrap();
+        }
+
+        Some(&self.chain[0])
+    }
+}
+
+#[test]
+fn chain_dimensions_test() {
+    assert_eq!(chain_dimensions((800, 600), 3), vec![(400, 300), (200, 150), (100, 75)]);
+    //stops once a level would get smaller than two texels
+    assert_eq!(chain_dimensions((16, 8), 6), vec![(8, 4), (4, 2)]);
+    assert!(chain_dimensions((2, 2), 6).is_empty());
+}
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+
+use crate::event_handling::camera_transformations::{Camera, Projection};
+use crate::event_handling::camera_transformations::linalg;
+use super::mesh::Mesh;
+use super::mesh::model_loading::Position;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3]
+}
+
+impl BoundingBox {
+    fn from_points(points: &[[f32; 3]]) -> Option<BoundingBox> {
+        let first = *points.first()?;
+        let mut res = BoundingBox { min: first, max: first };
+        for point in points.iter() {
+            for (axis, value) in point.iter().enumerate() {
+                res.min[axis] = res.min[axis].min(*value);
+                res.max[axis] = res.max[axis].max(*value);
+            }
+        }
+        Some(res)
+    }
+
+    fn corners(&self) -> [[f32; 3]; 8] {
+        let mut res = [[0.0; 3]; 8];
+        for (i, corner) in res.iter_mut().enumerate() {
+            *corner = [
+                if i & 1 == 0 { self.min[0] } else { self.max[0] },
+                if i & 2 == 0 { self.min[1] } else { self.max[1] },
+                if i & 4 == 0 { self.min[2] } else { self.max[2] }
+            ];
+        }
+        res
+    }
+
+    fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: [self.min[0].min(other.min[0]), self.min[1].min(other.min[1]), self.min[2].min(other.min[2])],
+            max: [self.max[0].max(other.max[0]), self.max[1].max(other.max[1]), self.max[2].max(other.max[2])]
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32
+}
+
+//both volumes are kept, the sphere is cheaper to test and the box is tighter
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Bounds {
+    pub aabb: BoundingBox,
+    pub sphere: BoundingSphere
+}
+
+impl Bounds {
+    //the sphere is centered on the box, which is close enough to the optimum for culling
+    fn from_points(points: &[[f32; 3]]) -> Option<Bounds> {
+        let aabb = BoundingBox::from_points(points)?;
+        let center = linalg::scale(linalg::add(aabb.min, aabb.max), 0.5);
+        let radius = points.iter()
+            .map(|point| linalg::mag(linalg::sub(*point, center)))
+            .fold(0.0, f32::max);
+
+        Some(Bounds {
+            aabb,
+            sphere: BoundingSphere { center, radius }
+        })
+    }
+
+    pub fn from_positions(positions: &[Position]) -> Option<Bounds> {
+        let points : Vec<[f32; 3]> = positions.iter()
+            .map(|pos| [pos.position.0, pos.position.1, pos.position.2])
+            .collect();
+        Self::from_points(&points)
+    }
+
+    /* moves the bounds into world space the same way the vertex shaders place a mesh:
+     * transform_mat with perspective divide, uniform scale, offset and finally every instance transform */
+    pub fn transform(&self,
+                     transform_mat: [[f32; 4]; 4],
+                     offset: (f32, f32, f32),
+                     scale: f32,
+                     instances: &[[[f32; 4]; 4]]) -> Bounds {
+        let placed : Vec<[f32; 3]> = self.aabb.corners().iter()
+            .map(|corner| {
+                let p = linalg::mat4mulvec4(transform_mat, [corner[0], corner[1], corner[2], 1.0]);
+                [scale * p[0] / p[3] + offset.0, scale * p[1] / p[3] + offset.1, scale * p[2] / p[3] + offset.2]
+            })
+            .collect();
+
+        if instances.is_empty() {
+            return Self::from_points(&placed).unwrap();
+        }
+
+        let mut aabb : Option<BoundingBox> = None;
+        for instance in instances {
+            let moved : Vec<[f32; 3]> = placed.iter()
+                .map(|point| {
+                    let p = linalg::mat4mulvec4(*instance, [point[0], point[1], point[2], 1.0]);
+                    [p[0], p[1], p[2]]
+                })
+                .collect();
+            let instance_box = BoundingBox::from_points(&moved).unwrap();
+            aabb = Some(match aabb {
+                Some(aabb) => aabb.union(&instance_box),
+                None => instance_box
+            });
+        }
+        Self::from_points(&aabb.unwrap().corners()).unwrap()
+    }
+}
+
+//planes as (normal, distance) with dot(normal, p) + distance >= 0 on the inside
+pub struct Frustum {
+    planes: Vec<([f32; 3], f32)>
+}
+
+impl Frustum {
+    //the near plane, the four sides and the far plane unless it is at infinity
+    pub fn from_camera(camera: &Camera) -> Frustum {
+        let front = linalg::norm(camera.front);
+        let half_height = camera.half_view_height(1.0);
+        let half_width = half_height * camera.view_aspect_ratio;
+
+        let near_point = linalg::add(camera.position, linalg::scale(front, camera.near));
+        let mut planes = vec![(front, -linalg::dot(front, near_point))];
+        let far = match camera.projection {
+            Projection::Perspective => camera.far,
+            Projection::Orthographic => Some(camera.ortho_far())
+        };
+        if let Some(far) = far {
+            let far_point = linalg::add(camera.position, linalg::scale(front, far));
+            let back = linalg::scale(front, -1.0);
+            planes.push((back, -linalg::dot(back, far_point)));
+        }
+
+        //the sides of an orthographic view are parallel to the viewing direction
+        if camera.projection == Projection::Orthographic {
+            for (axis, half_size) in [(camera.right, half_width), (camera.up, half_height)] {
+                for sign in [1.0, -1.0] {
+                    let normal = linalg::scale(axis, sign);
+                    let edge_point = linalg::sub(camera.position, linalg::scale(normal, half_size));
+                    planes.push((normal, -linalg::dot(normal, edge_point)));
+                }
+            }
+            return Frustum { planes };
+        }
+
+        let edges = [
+            (linalg::sub(front, linalg::scale(camera.right, half_width)), camera.up),
+            (linalg::add(front, linalg::scale(camera.right, half_width)), camera.up),
+            (linalg::sub(front, linalg::scale(camera.up, half_height)), camera.right),
+            (linalg::add(front, linalg::scale(camera.up, half_height)), camera.right)
+        ];
+        for (edge, axis) in edges {
+            let mut normal = linalg::norm(linalg::cross(edge, axis));
+            //the plane contains the edge, the inside is wherever the viewing direction points
+            if linalg::dot(normal, front) < 0.0 {
+                normal = linalg::scale(normal, -1.0);
+            }
+            planes.push((normal, -linalg::dot(normal, camera.position)));
+        }
+
+        Frustum { planes }
+    }
+
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        for (normal, distance) in self.planes.iter() {
+            if linalg::dot(*normal, bounds.sphere.center) + distance < -bounds.sphere.radius {
+                return false;
+            }
+
+            //the corner of the box furthest along the normal
+            let furthest = [
+                if normal[0] >= 0.0 { bounds.aabb.max[0] } else { bounds.aabb.min[0] },
+                if normal[1] >= 0.0 { bounds.aabb.max[1] } else { bounds.aabb.min[1] },
+                if normal[2] >= 0.0 { bounds.aabb.max[2] } else { bounds.aabb.min[2] }
+            ];
+            if linalg::dot(*normal, furthest) + distance < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/* distance along the ray to where it enters the box, zero if it starts inside and None if it
+ * misses. the direction has to be normalized */
+pub fn ray_distance(bounds: &Bounds, origin: [f32; 3], direction: [f32; 3]) -> Option<f32> {
+    let to_center = linalg::sub(bounds.sphere.center, origin);
+    let along = linalg::dot(to_center, direction);
+    if linalg::dot(to_center, to_center) - along * along > bounds.sphere.radius * bounds.sphere.radius {
+        return None;
+    }
+
+    let (mut enter, mut exit) = (0.0f32, f32::INFINITY);
+    for axis in 0..3 {
+        if direction[axis].abs() < f32::EPSILON {
+            if origin[axis] < bounds.aabb.min[axis] || origin[axis] > bounds.aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (bounds.aabb.min[axis] - origin[axis]) / direction[axis];
+        let t1 = (bounds.aabb.max[axis] - origin[axis]) / direction[axis];
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+    }
+
+    if enter <= exit { Some(enter) } else { None }
+}
+
+//index of the closest mesh whose bounds the ray hits, instanced meshes are hit through the box around all instances
+pub fn pick(meshes: &[Mesh], origin: [f32; 3], direction: [f32; 3]) -> Option<usize> {
+    meshes.iter()
+        .enumerate()
+        .filter_map(|(i, mesh)| Some((i, ray_distance(&mesh.world_bounds()?, origin, direction)?)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+#[test]
+fn frustum_culling_test() {
+    let camera = Camera::default();
+    let frustum = Frustum::from_camera(&camera);
+    let unit_cube = Bounds::from_points(&[[-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]]).unwrap();
+    let identity = linalg::identity4();
+
+    //the default camera sits at z = -1 and looks along +z
+    assert!(frustum.intersects(&unit_cube.transform(identity, (0.0, 0.0, 5.0), 1.0, &[])));
+    assert!(!frustum.intersects(&unit_cube.transform(identity, (0.0, 0.0, -5.0), 1.0, &[])));
+    assert!(!frustum.intersects(&unit_cube.transform(identity, (50.0, 0.0, 5.0), 1.0, &[])));
+    assert!(!frustum.intersects(&unit_cube.transform(identity, (0.0, -50.0, 5.0), 1.0, &[])));
+
+    //instanced meshes are visible as long as one instance is
+    let instances = [linalg::model_matrix([50.0, 0.0, 0.0], [0.0; 3], 1.0),
+                     linalg::model_matrix([0.0, 0.0, 0.0], [0.0; 3], 1.0)];
+    assert!(frustum.intersects(&unit_cube.transform(identity, (0.0, 0.0, 5.0), 1.0, &instances)));
+}
+
+#[test]
+fn orthographic_culling_test() {
+    let mut camera = Camera::default();
+    camera.projection = Projection::Orthographic;
+    camera.ortho_height = 4.0;
+    let frustum = Frustum::from_camera(&camera);
+    let unit_cube = Bounds::from_points(&[[-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]]).unwrap();
+    let identity = linalg::identity4();
+
+    //the view doesn't widen with distance like the perspective one does
+    assert!(frustum.intersects(&unit_cube.transform(identity, (0.0, 2.0, 100.0), 1.0, &[])));
+    assert!(!frustum.intersects(&unit_cube.transform(identity, (0.0, 3.0, 100.0), 1.0, &[])));
+    assert!(!frustum.intersects(&unit_cube.transform(identity, (0.0, 0.0, 2000.0), 1.0, &[])));
+}
+
+#[test]
+fn ray_distance_test() {
+    let unit_cube = Bounds::from_points(&[[-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]]).unwrap();
+    assert_eq!(ray_distance(&unit_cube, [0.0, 0.0, -5.0], [0.0, 0.0, 1.0]), Some(4.5));
+    assert_eq!(ray_distance(&unit_cube, [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]), Some(0.0));
+    assert_eq!(ray_distance(&unit_cube, [0.0, 2.0, -5.0], [0.0, 0.0, 1.0]), None);
+    //pointing away from the box
+    assert_eq!(ray_distance(&unit_cube, [0.0, 0.0, -5.0], [0.0, 0.0, -1.0]), None);
+}
+
+#[test]
+fn bounds_transform_test() {
+    let bounds = Bounds::from_points(&[[-1.0, 0.0, 0.0], [1.0, 2.0, 0.0]]).unwrap();
+    assert_eq!(bounds.sphere.center, [0.0, 1.0, 0.0]);
+
+    let moved = bounds.transform(linalg::identity4(), (1.0, 0.0, 0.0), 2.0, &[]);
+    assert_eq!(moved.aabb, BoundingBox { min: [-1.0, 0.0, 0.0], max: [3.0, 4.0, 0.0] });
+}
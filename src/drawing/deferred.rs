@@ -0,0 +1,312 @@
+use glium::framebuffer::{MultiOutputFrameBuffer, SimpleFrameBuffer};
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{DepthTexture2d, MipmapsOption, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{DepthTextureComparison, MagnifySamplerFilter, MinifySamplerFilter, Sampler};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::implement_vertex;
+use glium::uniform;
+
+use crate::event_handling::camera_transformations::Camera;
+use crate::event_handling::camera_transformations::linalg;
+use super::SpriteVertex;
+use super::environment::{Environment, PREFILTERED_LEVELS};
+use super::fog::FogSettings;
+use super::lighting::{LightSet, MAX_LIGHTS};
+use super::shader_compilation;
+use super::shadows::ShadowMaps;
+
+//the g-buffer pass reproduces this shader, meshes with any other shader are lit forward after the light pass
+pub const GBUFFER_SHADER: u16 = 2;
+
+//rings and segments of the sphere drawn around point and spot lights
+const VOLUME_RINGS: u32 = 8;
+const VOLUME_SEGMENTS: u32 = 16;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub enum RenderPath {
+    //every mesh is lit by all lights while it is drawn
+    #[default]
+    Forward,
+    //meshes only fill the g-buffer, lights are added afterwards in screen space
+    Deferred
+}
+
+#[derive(Copy, Clone)]
+pub struct VolumeVertex {
+    position: [f32; 3]
+}
+
+implement_vertex!(VolumeVertex, position);
+
+/* unit sphere made of triangles wound counter clockwise when seen from outside. the vertices are
+ * pushed out so the flat faces still enclose the round sphere of radius one */
+fn volume_sphere() -> Vec<VolumeVertex> {
+    let grow = 1.0 / ((std::f32::consts::PI / VOLUME_SEGMENTS as f32).cos()
+                      * (std::f32::consts::PI / (2.0 * VOLUME_RINGS as f32)).cos());
+    let point = |ring: u32, segment: u32| {
+        let theta = std::f32::consts::PI * ring as f32 / VOLUME_RINGS as f32;
+        let phi = 2.0 * std::f32::consts::PI * segment as f32 / VOLUME_SEGMENTS as f32;
+        VolumeVertex {
+            position: [grow * theta.sin() * phi.cos(), grow * theta.cos(), grow * theta.sin() * phi.sin()]
+        }
+    };
+
+    let mut res : Vec<VolumeVertex> = Vec::new();
+    for ring in 0..VOLUME_RINGS {
+        for segment in 0..VOLUME_SEGMENTS {
+            let (a, b) = (point(ring, segment), point(ring, segment + 1));
+            let (c, d) = (point(ring + 1, segment), point(ring + 1, segment + 1));
+            res.extend([a, b, d, a, d, c]);
+        }
+    }
+    res
+}
+
+//the surface attributes of every visible pixel, the scene color and depth are attached next to them
+struct GBuffer {
+    //linear albedo and alpha
+    albedo: Texture2d,
+    //world space normal
+    normal: Texture2d,
+    //metallic, roughness, ambient occlusion and whether shadows are received
+    material: Texture2d
+}
+
+impl GBuffer {
+    fn new(display: &Display, dimensions: (u32, u32)) -> GBuffer {
+        let target = |format| Texture2d::empty_with_format(display,
+                                                            format,
+                                                            MipmapsOption::NoMipmap,
+                                                            dimensions.0,
+                                                            dimensions.1).unwrap();
+        GBuffer {
+            albedo: target(UncompressedFloatFormat::F16F16F16F16),
+            normal: target(UncompressedFloatFormat::F16F16F16F16),
+            material: target(UncompressedFloatFormat::U8U8U8U8)
+        }
+    }
+}
+
+//the screen space passes add their light onto color, the alpha written by the geometry pass is kept
+fn additive() -> glium::DrawParameters<'static> {
+    glium::DrawParameters {
+        blend: glium::Blend {
+            color: glium::BlendingFunction::Addition {
+                source: glium::LinearBlendingFactor::One,
+                destination: glium::LinearBlendingFactor::One
+            },
+            alpha: glium::BlendingFunction::Addition {
+                source: glium::LinearBlendingFactor::Zero,
+                destination: glium::LinearBlendingFactor::One
+            },
+            constant_value: (0.0, 0.0, 0.0, 0.0)
+        },
+        .. Default::default()
+    }
+}
+
+//the g-buffer is read pixel by pixel, filtering would blend normals across edges
+fn nearest(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled()
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(MinifySamplerFilter::Nearest)
+}
+
+pub struct DeferredRenderer {
+    gbuffer_prog: Program,
+    ambient_prog: Program,
+    //directional lights cover the whole screen, the others only their volume
+    fullscreen_light_prog: Program,
+    volume_light_prog: Program,
+    volume: VertexBuffer<VolumeVertex>,
+    dimensions: (u32, u32),
+    gbuffer: GBuffer
+}
+
+impl DeferredRenderer {
+    pub fn new(display: &Display, dimensions: (u32, u32)) -> DeferredRenderer {
+        DeferredRenderer {
+            gbuffer_prog: shader_compilation::load_program("shader2.vert", "gbuffer.frag", display),
+            ambient_prog: shader_compilation::load_program("shaderpp.vert", "deferred_ambient.frag", display),
+            fullscreen_light_prog: shader_compilation::load_program("shaderpp.vert", "deferred_light.frag", display),
+            volume_light_prog: shader_compilation::load_program("light_volume.vert", "deferred_light.frag", display),
+            volume: VertexBuffer::new(display, &volume_sphere()).unwrap(),
+            dimensions,
+            gbuffer: GBuffer::new(display, dimensions)
+        }
+    }
+
+    pub fn get_gbuffer_prog(&self) -> &Program {
+        &self.gbuffer_prog
+    }
+
+    pub fn get_normals(&self) -> &Texture2d {
+        &self.gbuffer.normal
+    }
+
+    pub fn update_targets(&mut self, display: &Display, dimensions: (u32, u32)) {
+        if dimensions != self.dimensions {
+            self.gbuffer = GBuffer::new(display, dimensions);
+            self.dimensions = dimensions;
+        }
+    }
+
+    //the geometry pass writes emissive light and fog into color, the ambient light and the lights are added to it later
+    pub fn geometry_framebuffer<'a>(&'a self,
+                                    display: &Display,
+                                    color: &'a Texture2d,
+                                    depth: &'a DepthTexture2d) -> MultiOutputFrameBuffer<'a> {
+        let outputs = [("color", color),
+                       ("gbuffer_albedo", &self.gbuffer.albedo),
+                       ("gbuffer_normal", &self.gbuffer.normal),
+                       ("gbuffer_material", &self.gbuffer.material)];
+        MultiOutputFrameBuffer::with_depth_buffer(display, outputs.iter().cloned(), depth).unwrap()
+    }
+
+    /* adds the image based ambient light to color. it is darkened by the occlusion, the emissive light
+     * and fog of the geometry pass aren't */
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_ambient(&self,
+                          display: &Display,
+                          quad: &VertexBuffer<SpriteVertex>,
+                          color: &Texture2d,
+                          depth: &DepthTexture2d,
+                          camera: &Camera,
+                          environment: &Environment,
+                          occlusion: &Texture2d,
+                          fog: &FogSettings) {
+        let mut framebuffer = SimpleFrameBuffer::new(display, color).unwrap();
+        let (fog_color_mode, fog_params, fog_height) = fog.pack();
+        framebuffer.draw(quad,
+                         NoIndices(PrimitiveType::TrianglesList),
+                         &self.ambient_prog,
+                         &uniform! {
+                             albedo_sampler: nearest(&self.gbuffer.albedo),
+                             normal_sampler: nearest(&self.gbuffer.normal),
+                             material_sampler: nearest(&self.gbuffer.material),
+                             depth_sampler: depth.sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Nearest)
+                                 .minify_filter(MinifySamplerFilter::Nearest),
+                             resolution: [self.dimensions.0 as f32, self.dimensions.1 as f32],
+                             camera_pos: camera.position,
+                             inverse_view_projection: linalg::inverse4(camera.view_projection_matrix()),
+                             clear_depth: camera.clear_depth(),
+                             occlusion_sampler: nearest(occlusion),
+                             irradiance_map: environment.irradiance_sampler(),
+                             prefiltered_map: environment.prefiltered_sampler(),
+                             prefiltered_levels: PREFILTERED_LEVELS as f32,
+                             brdf_lut: environment.brdf_lut_sampler(),
+                             fog_color_mode: fog_color_mode,
+                             fog_params: fog_params,
+                             fog_height: fog_height,
+                         },
+                         &additive()).unwrap();
+    }
+
+    //adds every light to color, one additive draw per light
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_lights(&self,
+                         display: &Display,
+                         quad: &VertexBuffer<SpriteVertex>,
+                         color: &Texture2d,
+                         depth: &DepthTexture2d,
+                         camera: &Camera,
+                         light_set: &LightSet,
+                         shadow_maps: &ShadowMaps,
+                         fog: &FogSettings) {
+        let mut framebuffer = SimpleFrameBuffer::new(display, color).unwrap();
+        let shadow_sampler = Sampler::new(shadow_maps.get_maps())
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .depth_texture_comparison(Some(DepthTextureComparison::LessOrEqual));
+
+        //depth is only sampled, the volumes are clipped against the stored positions in the shader
+        let additive = additive();
+        //only the far side of a volume is drawn, so it still covers the screen with the camera inside
+        let volume_params = glium::DrawParameters {
+            backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
+            .. additive.clone()
+        };
+
+        let (fog_color_mode, fog_params, fog_height) = fog.pack();
+        let view_matrix = camera.view_matrix();
+        let projection_matrix = camera.projection_matrix();
+        let inverse_view_projection = linalg::inverse4(camera.view_projection_matrix());
+
+        for (i, light) in light_set.get_lights().iter().enumerate() {
+            let radius = light.influence_radius();
+            if radius == Some(0.0) {
+                continue;
+            }
+
+            let packed = light.pack();
+            let uniforms = uniform! {
+                albedo_sampler: nearest(&self.gbuffer.albedo),
+                normal_sampler: nearest(&self.gbuffer.normal),
+                material_sampler: nearest(&self.gbuffer.material),
+                depth_sampler: depth.sampled()
+                    .magnify_filter(MagnifySamplerFilter::Nearest)
+                    .minify_filter(MinifySamplerFilter::Nearest),
+                resolution: [self.dimensions.0 as f32, self.dimensions.1 as f32],
+                camera_pos: camera.position,
+                camera_front: camera.front,
+                view_matrix: view_matrix,
+                projection_matrix: projection_matrix,
+                inverse_view_projection: inverse_view_projection,
+                clear_depth: camera.clear_depth(),
+                light_position_kind: packed[0],
+                light_direction_intensity: packed[1],
+                light_color_outer: packed[2],
+                light_attenuation_inner: packed[3],
+                light_center: light.position,
+                light_radius: radius.unwrap_or(0.0),
+                //only the lights in the uniform block have shadow maps assigned
+                shadow_index: if i < MAX_LIGHTS { i as i32 } else { -1 },
+                shadow_maps: shadow_sampler,
+                ShadowMatrixBlock: shadow_maps.get_matrix_buffer(),
+                ShadowInfoBlock: shadow_maps.get_info_buffer(),
+                cascade_splits: shadow_maps.get_cascade_splits(),
+                fog_color_mode: fog_color_mode,
+                fog_params: fog_params,
+                fog_height: fog_height,
+            };
+
+            match radius {
+                Some(_) => framebuffer.draw(&self.volume,
+                                            NoIndices(PrimitiveType::TrianglesList),
+                                            &self.volume_light_prog,
+                                            &uniforms,
+                                            &volume_params).unwrap(),
+                None => framebuffer.draw(quad,
+                                         NoIndices(PrimitiveType::TrianglesList),
+                                         &self.fullscreen_light_prog,
+                                         &uniforms,
+                                         &additive).unwrap()
+            }
+        }
+    }
+}
+
+#[test]
+fn volume_sphere_test() {
+    use crate::event_handling::camera_transformations::linalg;
+
+    let sphere = volume_sphere();
+    assert_eq!(sphere.len() as u32, 6 * VOLUME_RINGS * VOLUME_SEGMENTS);
+
+    //every face points outwards and its plane stays outside the unit sphere, so the volume covers it
+    for face in sphere.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| face[i].position);
+        let normal = linalg::cross(linalg::sub(b, a), linalg::sub(c, a));
+        if linalg::mag(normal) < 1e-6 {
+            //the triangles touching the poles collapse into lines
+            continue;
+        }
+        assert!(linalg::dot(linalg::norm(normal), a) >= 1.0);
+    }
+}
@@ -0,0 +1,286 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{CubeLayer, Cubemap, MipmapsOption, RawImage2d, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::uniform;
+
+use super::SpriteVertex;
+use super::shader_compilation;
+
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 128;
+//number of mipmap levels of the prefiltered map, the last one is used for full roughness
+pub const PREFILTERED_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+const DEFAULT_ENVIRONMENT_SIZE: u32 = 16;
+
+pub const CUBE_LAYERS: [CubeLayer; 6] = [
+    CubeLayer::PositiveX,
+    CubeLayer::NegativeX,
+    CubeLayer::PositiveY,
+    CubeLayer::NegativeY,
+    CubeLayer::PositiveZ,
+    CubeLayer::NegativeZ
+];
+
+/* the direction through the center of a face and the directions its
+ * s and t texture coordinates point along, following the opengl cubemap layout */
+pub fn face_basis(layer: CubeLayer) -> ([f32; 3], [f32; 3], [f32; 3]) {
+    match layer {
+        CubeLayer::PositiveX => ([ 1.0,  0.0,  0.0], [ 0.0,  0.0, -1.0], [ 0.0, -1.0,  0.0]),
+        CubeLayer::NegativeX => ([-1.0,  0.0,  0.0], [ 0.0,  0.0,  1.0], [ 0.0, -1.0,  0.0]),
+        CubeLayer::PositiveY => ([ 0.0,  1.0,  0.0], [ 1.0,  0.0,  0.0], [ 0.0,  0.0,  1.0]),
+        CubeLayer::NegativeY => ([ 0.0, -1.0,  0.0], [ 1.0,  0.0,  0.0], [ 0.0,  0.0, -1.0]),
+        CubeLayer::PositiveZ => ([ 0.0,  0.0,  1.0], [ 1.0,  0.0,  0.0], [ 0.0, -1.0,  0.0]),
+        CubeLayer::NegativeZ => ([ 0.0,  0.0, -1.0], [-1.0,  0.0,  0.0], [ 0.0, -1.0,  0.0])
+    }
+}
+
+//where the environment cubemap comes from
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum EnvironmentSource {
+    //six images in the order +x, -x, +y, -y, +z, -z
+    Faces([String; 6]),
+    //a single equirectangular image, usually hdr
+    Equirect(String)
+}
+
+//loads an image from the texture folder as linear floating point rgba, top row first.
+//8 bit images are assumed to be gamma encoded, floating point ones (hdr) to be linear already
+fn load_linear_image(name: &str) -> (Vec<f32>, (u32, u32)) {
+    let mut pathstr = std::string::String::new();
+    pathstr.push_str("./resources/textures/");
+    pathstr.push_str(name);
+    let path = std::path::Path::new(&pathstr);
+
+    let image = image::open(path).unwrap();
+    let is_linear = matches!(image, image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_));
+    let image = image.to_rgba32f();
+    let dimensions = image.dimensions();
+    let mut data = image.into_raw();
+
+    if !is_linear {
+        for pixel in data.chunks_mut(4) {
+            for channel in pixel.iter_mut().take(3) {
+                *channel = channel.powf(2.2);
+            }
+        }
+    }
+
+    (data, dimensions)
+}
+
+pub struct Environment {
+    cubemap: Cubemap,
+    irradiance: Cubemap,
+    prefiltered: Cubemap,
+    brdf_lut: Texture2d
+}
+
+impl Environment {
+    pub fn from_source(display: &Display, source: &EnvironmentSource) -> Environment {
+        match source {
+            EnvironmentSource::Faces(faces) => Self::from_faces(display, faces),
+            EnvironmentSource::Equirect(name) => Self::from_equirect(display, name)
+        }
+    }
+
+    //loads the six faces in the order +x, -x, +y, -y, +z, -z from the texture folder
+    pub fn from_faces(display: &Display, faces: &[String; 6]) -> Environment {
+        let mut cubemap : Option<Cubemap> = None;
+
+        for (face, layer) in faces.iter().zip(CUBE_LAYERS) {
+            //cubemap faces are stored top row first, so the image is not flipped like regular textures
+            let (data, image_dimensions) = load_linear_image(face);
+            let image = RawImage2d::from_raw_rgba(data, image_dimensions);
+            let face_texture = Texture2d::with_format(display,
+                                                      image,
+                                                      UncompressedFloatFormat::F16F16F16F16,
+                                                      MipmapsOption::NoMipmap).unwrap();
+
+            let target_cubemap = cubemap.get_or_insert_with(|| {
+                Cubemap::empty_with_format(display,
+                                           UncompressedFloatFormat::F16F16F16F16,
+                                           MipmapsOption::EmptyMipmaps,
+                                           image_dimensions.0).unwrap()
+            });
+            let target = SimpleFrameBuffer::new(display, target_cubemap.main_level().image(layer)).unwrap();
+            face_texture.as_surface().fill(&target, MagnifySamplerFilter::Linear);
+        }
+
+        Self::from_cubemap(display, cubemap.unwrap())
+    }
+
+    //projects an equirectangular image onto the faces of a cubemap
+    pub fn from_equirect(display: &Display, name: &str) -> Environment {
+        let (data, image_dimensions) = load_linear_image(name);
+        let image = RawImage2d::from_raw_rgba(data, image_dimensions);
+        let equirect = Texture2d::with_format(display,
+                                              image,
+                                              UncompressedFloatFormat::F32F32F32F32,
+                                              MipmapsOption::NoMipmap).unwrap();
+
+        //a quarter of the width keeps roughly the resolution of the image around the horizon
+        let face_size = (image_dimensions.0 / 4).max(DEFAULT_ENVIRONMENT_SIZE);
+        let cubemap = Cubemap::empty_with_format(display,
+                                                 UncompressedFloatFormat::F16F16F16F16,
+                                                 MipmapsOption::EmptyMipmaps,
+                                                 face_size).unwrap();
+
+        let quad = super::fullscreen_quad(display);
+        let prog = shader_compilation::load_program("cubeface.vert", "equirect_to_cube.frag", display);
+        let equirect_sampler = Sampler::new(&equirect)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .wrap_function(SamplerWrapFunction::Repeat);
+
+        for layer in CUBE_LAYERS {
+            let (face_dir, face_s, face_t) = face_basis(layer);
+            let mut target = SimpleFrameBuffer::new(display, cubemap.main_level().image(layer)).unwrap();
+            draw_face(&mut target, &quad, &prog, &uniform! {
+                equirect: equirect_sampler,
+                face_dir: face_dir,
+                face_s: face_s,
+                face_t: face_t
+            });
+        }
+
+        Self::from_cubemap(display, cubemap)
+    }
+
+    //a dim uniform environment for scenes that don't declare one
+    pub fn uniform(display: &Display, color: [f32; 3]) -> Environment {
+        let cubemap = Cubemap::empty_with_format(display,
+                                                 UncompressedFloatFormat::F16F16F16F16,
+                                                 MipmapsOption::EmptyMipmaps,
+                                                 DEFAULT_ENVIRONMENT_SIZE).unwrap();
+        for layer in CUBE_LAYERS {
+            let mut target = SimpleFrameBuffer::new(display, cubemap.main_level().image(layer)).unwrap();
+            target.clear_color(color[0], color[1], color[2], 1.0);
+        }
+
+        Self::from_cubemap(display, cubemap)
+    }
+
+    /* precomputes everything image based lighting needs from the environment,
+     * the cubemap needs storage for a full mipmap chain with the first level filled */
+    pub fn from_cubemap(display: &Display, cubemap: Cubemap) -> Environment {
+        //safe because the cubemap has been created with storage for all mipmap levels
+        unsafe { cubemap.generate_mipmaps(); }
+
+        let quad = super::fullscreen_quad(display);
+        let quad = &quad;
+
+        let irradiance_prog = shader_compilation::load_program("cubeface.vert", "irradiance.frag", display);
+        let prefilter_prog = shader_compilation::load_program("cubeface.vert", "prefilter.frag", display);
+        let brdf_prog = shader_compilation::load_program("shaderpp.vert", "brdf_lut.frag", display);
+
+        let irradiance = Cubemap::empty_with_format(display,
+                                                    UncompressedFloatFormat::F16F16F16F16,
+                                                    MipmapsOption::NoMipmap,
+                                                    IRRADIANCE_SIZE).unwrap();
+        let prefiltered = Cubemap::empty_with_format(display,
+                                                     UncompressedFloatFormat::F16F16F16F16,
+                                                     MipmapsOption::EmptyMipmapsMax(PREFILTERED_LEVELS - 1),
+                                                     PREFILTERED_SIZE).unwrap();
+
+        let env_sampler = Sampler::new(&cubemap)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+            .wrap_function(SamplerWrapFunction::Clamp);
+
+        for layer in CUBE_LAYERS {
+            let (face_dir, face_s, face_t) = face_basis(layer);
+            let mut target = SimpleFrameBuffer::new(display, irradiance.main_level().image(layer)).unwrap();
+            draw_face(&mut target, quad, &irradiance_prog, &uniform! {
+                environment: env_sampler,
+                face_dir: face_dir,
+                face_s: face_s,
+                face_t: face_t
+            });
+
+            for level in 0..PREFILTERED_LEVELS {
+                let roughness = level as f32 / (PREFILTERED_LEVELS - 1) as f32;
+                let mipmap = prefiltered.mipmap(level).unwrap();
+                let mut target = SimpleFrameBuffer::new(display, mipmap.image(layer)).unwrap();
+                draw_face(&mut target, quad, &prefilter_prog, &uniform! {
+                    environment: env_sampler,
+                    face_dir: face_dir,
+                    face_s: face_s,
+                    face_t: face_t,
+                    roughness: roughness,
+                    environment_size: cubemap.width() as f32
+                });
+            }
+        }
+
+        let brdf_lut = Texture2d::empty_with_format(display,
+                                                    UncompressedFloatFormat::F16F16,
+                                                    MipmapsOption::NoMipmap,
+                                                    BRDF_LUT_SIZE,
+                                                    BRDF_LUT_SIZE).unwrap();
+        let mut target = SimpleFrameBuffer::new(display, &brdf_lut).unwrap();
+        draw_face(&mut target, quad, &brdf_prog, &glium::uniforms::EmptyUniforms);
+
+        Environment {
+            cubemap,
+            irradiance,
+            prefiltered,
+            brdf_lut
+        }
+    }
+
+    //the unfiltered environment, for the skybox and mirror like reflections
+    pub fn cubemap_sampler(&self) -> Sampler<'_, Cubemap> {
+        Sampler::new(&self.cubemap)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+            .wrap_function(SamplerWrapFunction::Clamp)
+    }
+
+    pub fn irradiance_sampler(&self) -> Sampler<'_, Cubemap> {
+        Sampler::new(&self.irradiance)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .wrap_function(SamplerWrapFunction::Clamp)
+    }
+
+    pub fn prefiltered_sampler(&self) -> Sampler<'_, Cubemap> {
+        Sampler::new(&self.prefiltered)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+            .wrap_function(SamplerWrapFunction::Clamp)
+    }
+
+    pub fn brdf_lut_sampler(&self) -> Sampler<'_, Texture2d> {
+        Sampler::new(&self.brdf_lut)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .wrap_function(SamplerWrapFunction::Clamp)
+    }
+}
+
+fn draw_face<U: glium::uniforms::Uniforms>(target: &mut SimpleFrameBuffer,
+                                           quad: &VertexBuffer<SpriteVertex>,
+                                           prog: &Program,
+                                           uniforms: &U) {
+    target.draw(quad,
+                NoIndices(PrimitiveType::TrianglesList),
+                prog,
+                uniforms,
+                &Default::default()).unwrap();
+}
+
+#[test]
+fn face_basis_test() {
+    use crate::event_handling::camera_transformations::linalg;
+    //s cross t has to point back out of the face for every face of a cubemap
+    for layer in CUBE_LAYERS {
+        let (dir, s, t) = face_basis(layer);
+        assert_eq!(linalg::cross(s, t), linalg::scale(dir, -1.0));
+    }
+}
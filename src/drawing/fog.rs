@@ -0,0 +1,85 @@
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub enum FogMode {
+    #[default]
+    Off,
+    //no fog before start, fully fogged after end
+    Linear,
+    //visibility falls off with exp(-density * distance)
+    Exponential,
+    //exponential fog that thins out above height, thickest in valleys and towards the horizon
+    Height
+}
+
+impl FogMode {
+    //the mode as it is compared in the shaders
+    fn id(&self) -> f32 {
+        match self {
+            FogMode::Off => 0.0,
+            FogMode::Linear => 1.0,
+            FogMode::Exponential => 2.0,
+            FogMode::Height => 3.0
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct FogSettings {
+    pub mode: FogMode,
+    //linear color the scene fades into
+    pub color: [f32; 3],
+    pub start: f32,
+    pub end: f32,
+    pub density: f32,
+    //world space height where height fog has its full density
+    pub height: f32,
+    //how quickly height fog thins out above its height
+    pub height_falloff: f32,
+    //height of the band above the horizon in which the skybox fades into the fog, as the y of the view direction
+    pub sky_blend: f32
+}
+
+impl Default for FogSettings {
+    fn default() -> FogSettings {
+        FogSettings {
+            mode: FogMode::Off,
+            color: [0.5, 0.6, 0.7],
+            start: 10.0,
+            end: 100.0,
+            density: 0.02,
+            height: 0.0,
+            height_falloff: 0.2,
+            sky_blend: 0.2
+        }
+    }
+}
+
+impl FogSettings {
+    /* uniforms of the lit shaders and the skybox:
+     *   fog_color_mode: color.rgb, mode
+     *   fog_params: start, end, density, sky_blend
+     *   fog_height: height, height_falloff */
+    pub fn pack(&self) -> ([f32; 4], [f32; 4], [f32; 2]) {
+        (
+            [self.color[0], self.color[1], self.color[2], self.mode.id()],
+            [self.start, self.end, self.density, self.sky_blend],
+            [self.height, self.height_falloff]
+        )
+    }
+}
+
+#[test]
+fn fog_pack_test() {
+    let fog = FogSettings { mode: FogMode::Height, color: [0.1, 0.2, 0.3], height: 5.0, .. Default::default() };
+    let (color_mode, params, height) = fog.pack();
+    assert_eq!(color_mode, [0.1, 0.2, 0.3, 3.0]);
+    assert_eq!(params, [10.0, 100.0, 0.02, 0.2]);
+    assert_eq!(height, [5.0, 0.2]);
+    assert_eq!(FogSettings::default().pack().0[3], 0.0);
+}
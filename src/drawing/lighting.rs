@@ -0,0 +1,170 @@
+use glium::Display;
+use glium::uniforms::UniformBuffer;
+
+use crate::event_handling::camera_transformations::linalg;
+
+//has to match the array size of the light block in the shaders
+pub const MAX_LIGHTS: usize = 16;
+const VEC4S_PER_LIGHT: usize = 4;
+//radiance below this is not worth drawing, defines how far light volumes reach
+const LIGHT_CUTOFF: f32 = 1.0 / 256.0;
+
+pub type LightData = [[f32; 4]; MAX_LIGHTS * VEC4S_PER_LIGHT];
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    //constant, linear and quadratic falloff
+    pub attenuation: [f32; 3],
+    //cone angles of spot lights in degrees
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub cast_shadows: bool
+}
+
+impl Default for Light {
+    //the light that used to be hardcoded in the standard shader
+    fn default() -> Light {
+        Light {
+            kind: LightKind::Point,
+            position: [10.0, 15.0, -10.0],
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            attenuation: [1.0, 0.0, 0.0],
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            cast_shadows: false
+        }
+    }
+}
+
+impl Light {
+    fn kind_id(&self) -> f32 {
+        match self.kind {
+            LightKind::Directional => 0.0,
+            LightKind::Point => 1.0,
+            LightKind::Spot => 2.0
+        }
+    }
+
+    /* layout of one light in the block:
+     *   position.xyz, kind
+     *   direction.xyz, intensity
+     *   color.rgb, cos(outer_angle)
+     *   attenuation.xyz, cos(inner_angle) */
+    pub fn pack(&self) -> [[f32; 4]; VEC4S_PER_LIGHT] {
+        let dir = linalg::norm(self.direction);
+        [
+            [self.position[0], self.position[1], self.position[2], self.kind_id()],
+            [dir[0], dir[1], dir[2], self.intensity],
+            [self.color[0], self.color[1], self.color[2], self.outer_angle.to_radians().cos()],
+            [self.attenuation[0], self.attenuation[1], self.attenuation[2], self.inner_angle.to_radians().cos()]
+        ]
+    }
+
+    /* distance at which the attenuated light falls below the cutoff, solving
+     * c + l * d + q * d^2 = brightness / cutoff. None for lights that reach everything */
+    pub fn influence_radius(&self) -> Option<f32> {
+        if self.kind == LightKind::Directional {
+            return None;
+        }
+
+        let brightness = self.intensity * self.color[0].max(self.color[1]).max(self.color[2]);
+        let [constant, linear, quadratic] = self.attenuation;
+        let rest = constant - brightness / LIGHT_CUTOFF;
+
+        if quadratic > 0.0 {
+            Some(((-linear + (linear * linear - 4.0 * quadratic * rest).max(0.0).sqrt()) / (2.0 * quadratic)).max(0.0))
+        } else if linear > 0.0 {
+            Some((-rest / linear).max(0.0))
+        } else {
+            None
+        }
+    }
+}
+
+pub fn pack_lights(lights: &[Light]) -> LightData {
+    let mut res : LightData = [[0.0; 4]; MAX_LIGHTS * VEC4S_PER_LIGHT];
+    for (i, light) in lights.iter().take(MAX_LIGHTS).enumerate() {
+        let packed = light.pack();
+        res[i * VEC4S_PER_LIGHT..(i + 1) * VEC4S_PER_LIGHT].copy_from_slice(&packed);
+    }
+    res
+}
+
+pub struct LightSet {
+    lights: Vec<Light>,
+    buffer: UniformBuffer<LightData>
+}
+
+impl LightSet {
+    pub fn new(display: &Display, lights: Vec<Light>) -> LightSet {
+        if lights.len() > MAX_LIGHTS {
+            println!("only the first {} of {} lights are used!", MAX_LIGHTS, lights.len());
+        }
+
+        let buffer = UniformBuffer::new(display, pack_lights(&lights)).unwrap();
+        LightSet { lights, buffer }
+    }
+
+    pub fn get_lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn count(&self) -> i32 {
+        self.lights.len().min(MAX_LIGHTS) as i32
+    }
+
+    pub fn get_buffer(&self) -> &UniformBuffer<LightData> {
+        &self.buffer
+    }
+}
+
+#[test]
+fn pack_lights_test() {
+    let spot = Light {
+        kind: LightKind::Spot,
+        direction: [0.0, -2.0, 0.0],
+        inner_angle: 0.0,
+        outer_angle: 90.0,
+        ..Default::default()
+    };
+    let packed = pack_lights(&[Light::default(), spot]);
+
+    assert_eq!(packed[0], [10.0, 15.0, -10.0, 1.0]);
+    assert_eq!(packed[4][3], 2.0);
+    assert_eq!(packed[5], [0.0, -1.0, 0.0, 1.0]);
+    assert!(packed[6][3].abs() < 1e-6);
+    assert_eq!(packed[7][3], 1.0);
+    assert_eq!(packed[8], [0.0; 4]);
+}
+
+#[test]
+fn influence_radius_test() {
+    let light = Light { attenuation: [1.0, 0.0, 1.0], ..Default::default() };
+    let radius = light.influence_radius().unwrap();
+    assert!((radius * radius - 255.0).abs() < 1e-3);
+
+    let linear = Light { attenuation: [0.0, 2.0, 0.0], intensity: 0.5, ..Default::default() };
+    assert_eq!(linear.influence_radius(), Some(64.0));
+
+    //constant attenuation never fades out, directional lights have no position
+    assert_eq!(Light::default().influence_radius(), None);
+    assert_eq!(Light { kind: LightKind::Directional, ..linear }.influence_radius(), None);
+}
@@ -0,0 +1,103 @@
+use glium::vertex::VertexBuffer;
+
+use super::mesh::model_loading::Vertex;
+
+//when a level of detail takes over from the one before it
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum LodSwitch {
+    //camera distance to the center of the bounds
+    Distance(f32),
+    //radius of the bounds on screen relative to half the screen height
+    ScreenSize(f32)
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum LodSource {
+    //another model from the collada folder, placed like the base mesh
+    Model(u16),
+    //the base mesh simplified to this fraction of its triangles
+    Simplified(f32)
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct LodConfig {
+    pub source: LodSource,
+    pub switch: LodSwitch
+}
+
+pub struct Lod {
+    pub config: LodConfig,
+    pub vertex_buf: Option<VertexBuffer<Vertex>>
+}
+
+//what the camera sees of a mesh this frame
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct LodMetrics {
+    pub distance: f32,
+    pub screen_size: f32
+}
+
+impl LodMetrics {
+    //half_view_height is half the height the camera sees at that distance
+    pub fn new(distance: f32, radius: f32, half_view_height: f32) -> LodMetrics {
+        LodMetrics {
+            distance,
+            screen_size: radius / half_view_height.max(0.0001)
+        }
+    }
+}
+
+//factor widens the threshold, above one makes a level harder to reach and below one harder to leave
+fn wanted(switch: LodSwitch, metrics: LodMetrics, factor: f32) -> bool {
+    match switch {
+        LodSwitch::Distance(distance) => metrics.distance > distance * factor,
+        LodSwitch::ScreenSize(size) => metrics.screen_size < size / factor
+    }
+}
+
+/* picks the level of detail starting from the current one, 0 is the base mesh and i the lod at
+ * switches[i - 1]. a level is only entered once its threshold is passed by the hysteresis margin
+ * and only left once it is missed by the same margin, so a mesh at a threshold doesn't flicker */
+pub fn select_lod(current: usize, switches: &[LodSwitch], metrics: LodMetrics, hysteresis: f32) -> usize {
+    let mut res = current.min(switches.len());
+
+    while res < switches.len() && wanted(switches[res], metrics, 1.0 + hysteresis) {
+        res += 1;
+    }
+    while res > 0 && !wanted(switches[res - 1], metrics, 1.0 - hysteresis) {
+        res -= 1;
+    }
+
+    res
+}
+
+#[test]
+fn select_lod_test() {
+    let switches = [LodSwitch::Distance(10.0), LodSwitch::Distance(20.0)];
+    let at = |distance: f32| LodMetrics { distance, screen_size: 1.0 };
+
+    assert_eq!(select_lod(0, &switches, at(5.0), 0.1), 0);
+    assert_eq!(select_lod(0, &switches, at(25.0), 0.1), 2);
+    //inside the margin around a threshold the current level is kept in both directions
+    assert_eq!(select_lod(0, &switches, at(10.5), 0.1), 0);
+    assert_eq!(select_lod(1, &switches, at(10.5), 0.1), 1);
+    assert_eq!(select_lod(1, &switches, at(9.5), 0.1), 1);
+    assert_eq!(select_lod(1, &switches, at(8.5), 0.1), 0);
+
+    let switches = [LodSwitch::ScreenSize(0.5)];
+    let sized = |screen_size: f32| LodMetrics { distance: 1.0, screen_size };
+    assert_eq!(select_lod(0, &switches, sized(0.25), 0.1), 1);
+    assert_eq!(select_lod(1, &switches, sized(0.5), 0.1), 1);
+    assert_eq!(select_lod(1, &switches, sized(1.0), 0.1), 0);
+}
@@ -0,0 +1,175 @@
+use glium::Display;
+use glium::texture::RawImage2d;
+use glium::texture::texture2d::Texture2d;
+
+use std::cmp::Ordering;
+
+use super::render_state::RenderState;
+use super::texture::Texture;
+
+//how the alpha of a material is used
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum BlendMode {
+    Opaque,
+    //fragments below the alpha cutoff are discarded, everything else stays opaque
+    Cutout,
+    //drawn after all opaque meshes, sorted back to front and blended over them
+    Blended
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct MaterialConfig {
+    pub id: u16,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    //texture ids of the optional maps
+    pub albedo_map: Option<u16>,
+    //roughness in the green and metallic in the blue channel
+    pub metallic_roughness_map: Option<u16>,
+    pub normal_map: Option<u16>,
+    pub ao_map: Option<u16>,
+    pub blend_mode: BlendMode,
+    pub alpha_cutoff: f32,
+    pub render_state: RenderState
+}
+
+impl Default for MaterialConfig {
+    fn default() -> MaterialConfig {
+        MaterialConfig {
+            id: 0,
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0, 0.0, 0.0],
+            albedo_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            ao_map: None,
+            blend_mode: BlendMode::Opaque,
+            alpha_cutoff: 0.5,
+            render_state: RenderState::default()
+        }
+    }
+}
+
+pub struct Material {
+    pub config: MaterialConfig,
+    albedo_map: Option<Texture>,
+    metallic_roughness_map: Option<Texture>,
+    normal_map: Option<Texture>,
+    ao_map: Option<Texture>
+}
+
+impl Material {
+    pub fn load(config: MaterialConfig, display: &Display) -> Material {
+        let load = |id: Option<u16>| id.map(|id| Texture::from_file(id, display));
+        Material {
+            albedo_map: load(config.albedo_map),
+            metallic_roughness_map: load(config.metallic_roughness_map),
+            normal_map: load(config.normal_map),
+            ao_map: load(config.ao_map),
+            config
+        }
+    }
+}
+
+//1x1 textures that stand in for maps a material doesn't have
+struct FallbackTextures {
+    white: Texture2d,
+    flat_normal: Texture2d
+}
+
+impl FallbackTextures {
+    fn new(display: &Display) -> FallbackTextures {
+        let pixel = |color: [u8; 4]| RawImage2d::from_raw_rgba(color.to_vec(), (1, 1));
+        FallbackTextures {
+            white: Texture2d::new(display, pixel([255, 255, 255, 255])).unwrap(),
+            flat_normal: Texture2d::new(display, pixel([128, 128, 255, 255])).unwrap()
+        }
+    }
+}
+
+pub struct MaterialLibrary {
+    //sorted by id
+    materials: Vec<Material>,
+    default_material: Material,
+    fallback: FallbackTextures
+}
+
+//the textures and factors of one material, ready to be handed to a shader
+pub struct MaterialBinding<'a> {
+    pub config: &'a MaterialConfig,
+    pub albedo_map: &'a Texture2d,
+    pub metallic_roughness_map: &'a Texture2d,
+    pub normal_map: &'a Texture2d,
+    pub ao_map: &'a Texture2d,
+    pub has_albedo_map: bool,
+    pub has_normal_map: bool
+}
+
+impl MaterialLibrary {
+    pub fn new(display: &Display, configs: Vec<MaterialConfig>) -> MaterialLibrary {
+        let mut materials : Vec<Material> = configs.into_iter()
+            .map(|config| Material::load(config, display))
+            .collect();
+        materials.sort_by_key(|material| material.config.id);
+
+        MaterialLibrary {
+            materials,
+            default_material: Material::load(MaterialConfig::default(), display),
+            fallback: FallbackTextures::new(display)
+        }
+    }
+
+    pub fn get(&self, id: Option<u16>) -> &Material {
+        let index = id.and_then(|id| {
+            self.materials.binary_search_by(|material| {
+                if material.config.id < id {
+                    Ordering::Less
+                } else if material.config.id > id {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            }).ok()
+        });
+
+        match index {
+            Some(i) => &self.materials[i],
+            None => &self.default_material
+        }
+    }
+
+    pub fn bind(&self, id: Option<u16>) -> MaterialBinding<'_> {
+        let material = self.get(id);
+
+        MaterialBinding {
+            config: &material.config,
+            albedo_map: self.map_or(&material.albedo_map, &self.fallback.white),
+            metallic_roughness_map: self.map_or(&material.metallic_roughness_map, &self.fallback.white),
+            normal_map: self.map_or(&material.normal_map, &self.fallback.flat_normal),
+            ao_map: self.map_or(&material.ao_map, &self.fallback.white),
+            has_albedo_map: material.albedo_map.is_some(),
+            has_normal_map: material.normal_map.is_some()
+        }
+    }
+
+    //bound wherever a map is needed that changes nothing
+    pub fn get_white(&self) -> &Texture2d {
+        &self.fallback.white
+    }
+
+    fn map_or<'a>(&'a self, map: &'a Option<Texture>, fallback: &'a Texture2d) -> &'a Texture2d {
+        match map {
+            Some(tex) => tex.get_texture(),
+            None => fallback
+        }
+    }
+}
@@ -1,26 +1,65 @@
 use glium::backend::glutin::Display;
+use glium::implement_vertex;
 use glium::vertex::VertexBuffer;
 
 use serde::{Deserialize, Serialize};
 
-use model_loading::{ Position, Normal, TextureCoordinates , Vertex };
+use std::cell::Cell;
+
+use model_loading::{ Position, Normal, TextureCoordinates, Tangent, Vertex };
 use model_loading::parsing::Parser;
-use model_loading::parsing;
+use crate::event_handling::camera_transformations::linalg;
+use super::culling::Bounds;
+use super::lod::{self, Lod, LodConfig, LodMetrics, LodSource, LodSwitch};
+use super::render_state::RenderStateOverride;
 
 pub mod model_loading;
+pub mod simplification;
+
+//written in front of every cached mesh, files without it are ignored
+const CACHE_MAGIC: [u8; 4] = *b"RMSH";
+//has to go up whenever MeshData changes, older files are rebuilt from the collada file instead of misread
+const CACHE_VERSION: u32 = 1;
+
+//per instance attributes for instanced drawing
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct Instance {
+    pub instance_transform: [[f32; 4]; 4]
+}
+
+implement_vertex!(Instance, instance_transform);
 
 pub struct Mesh {
     pub id: u16,
     pub positions: Option<Box<[Position]>>,
     pub normals: Option<Box<[Normal]>>,
     pub tex_coords: Option<Box<[TextureCoordinates]>>,
+    //one tangent per index triplet
+    pub tangents: Option<Box<[Tangent]>>,
     pub indices: Option<Box<[u16]>>,
+    //in model space, before transform_mat is applied
+    pub bounds: Option<Bounds>,
     pub vertex_buf: Option<VertexBuffer<Vertex>>,
+    //applied after the mesh's own transform, offset and scale. empty means a single untransformed copy
+    pub instances: Vec<[[f32; 4]; 4]>,
+    pub instance_buf: Option<VertexBuffer<Instance>>,
     pub transform_mat: [[f32; 4]; 4],
     pub offset: (f32, f32, f32),
     pub scale: f32,
     pub shader_id: u16,
-    pub texture_id: u16
+    pub texture_id: u16,
+    pub material_id: Option<u16>,
+    pub cast_shadows: bool,
+    pub receive_shadows: bool,
+    //applied on top of the render state of the material
+    pub render_state: RenderStateOverride,
+    //cheaper versions of the mesh, ordered from the finest to the coarsest
+    pub lods: Vec<Lod>,
+    pub lod_hysteresis: f32,
+    //0 is the mesh itself, i the lod at i - 1. a cell since the choice changes while drawing
+    lod_index: Cell<usize>
 }
 
 impl Mesh {
@@ -38,13 +77,24 @@ impl Mesh {
             positions: None,
             normals: None,
             tex_coords: None,
+            tangents: None,
             indices: None,
+            bounds: None,
             vertex_buf: None,
+            instances: Vec::new(),
+            instance_buf: None,
             transform_mat: mat,
             offset: (0.0, 0.0, 0.0),
             scale: 1.0,
             shader_id: 0,
-            texture_id: 0
+            texture_id: 0,
+            material_id: None,
+            cast_shadows: true,
+            receive_shadows: true,
+            render_state: RenderStateOverride::default(),
+            lods: Vec::new(),
+            lod_hysteresis: 0.0,
+            lod_index: Cell::new(0)
         }
     }
 
@@ -62,13 +112,24 @@ impl Mesh {
             positions: None,
             normals: None,
             tex_coords: None,
+            tangents: None,
             indices: None,
+            bounds: None,
             vertex_buf: None,
+            instances: Vec::new(),
+            instance_buf: None,
             transform_mat: mat,
             offset: (0.0, 0.0, 0.0),
             scale: 1.0,
             shader_id: 0,
-            texture_id: 0
+            texture_id: 0,
+            material_id: None,
+            cast_shadows: true,
+            receive_shadows: true,
+            render_state: RenderStateOverride::default(),
+            lods: Vec::new(),
+            lod_hysteresis: 0.0,
+            lod_index: Cell::new(0)
         }
     }
 
@@ -86,13 +147,24 @@ impl Mesh {
             positions: None,
             normals: None,
             tex_coords: None,
+            tangents: None,
             indices: None,
+            bounds: None,
             vertex_buf: None,
+            instances: Vec::new(),
+            instance_buf: None,
             transform_mat: mat,
             offset: (0.0, 0.0, 0.0),
             scale: 1.0,
             shader_id: shader_id,
-            texture_id: 0
+            texture_id: 0,
+            material_id: None,
+            cast_shadows: true,
+            receive_shadows: true,
+            render_state: RenderStateOverride::default(),
+            lods: Vec::new(),
+            lod_hysteresis: 0.0,
+            lod_index: Cell::new(0)
         }
     }
 
@@ -109,13 +181,24 @@ impl Mesh {
             positions: None,
             normals: None,
             tex_coords: None,
+            tangents: None,
             indices: None,
+            bounds: None,
             vertex_buf: None,
+            instances: Vec::new(),
+            instance_buf: None,
             transform_mat: mat,
             offset: (0.0, 0.0, 0.0),
             scale: 1.0,
             shader_id: shader_id,
-            texture_id: texture_id
+            texture_id: texture_id,
+            material_id: None,
+            cast_shadows: true,
+            receive_shadows: true,
+            render_state: RenderStateOverride::default(),
+            lods: Vec::new(),
+            lod_hysteresis: 0.0,
+            lod_index: Cell::new(0)
         }
     }
 
@@ -133,6 +216,23 @@ impl Mesh {
         self.scale = scale;
     }
 
+    pub fn set_instances(&mut self, instances: Vec<[[f32; 4]; 4]>) {
+        self.instances = instances;
+    }
+
+    pub fn set_material(&mut self, material_id: Option<u16>) {
+        self.material_id = material_id;
+    }
+
+    pub fn set_shadows(&mut self, cast_shadows: bool, receive_shadows: bool) {
+        self.cast_shadows = cast_shadows;
+        self.receive_shadows = receive_shadows;
+    }
+
+    pub fn set_render_state(&mut self, render_state: RenderStateOverride) {
+        self.render_state = render_state;
+    }
+
     pub fn load_geometry(&mut self) {
         //first try to load the model form a binary file
         match retrieve_from_bin(self.id) {
@@ -140,10 +240,15 @@ impl Mesh {
                 self.positions = mesh_data.positions;
                 self.normals = mesh_data.normals;
                 self.tex_coords = mesh_data.tex_coords;
+                self.tangents = mesh_data.tangents;
                 self.indices = mesh_data.indices;
+                self.bounds = mesh_data.bounds;
                 self.transform_mat = mesh_data.transform_mat;
             },
-            Err(_) => {
+            Err(err) => {
+                if let MeshStorageError::OutdatedCache = err {
+                    println!("the binary file of model {} is outdated, run with -b to write it again", self.id);
+                }
                 println!("loading model with id {} from collada file!", self.id);
                 //load the mesh from the collada file if the binary file doesn't work
                 let mut pathstr = std::string::String::new();
@@ -172,11 +277,30 @@ impl Mesh {
                 }
             }
         }
+
+        if self.tangents.is_none() {
+            self.generate_tangents();
+        }
+
+        if self.bounds.is_none() {
+            self.bounds = self.positions.as_ref().and_then(|positions| Bounds::from_positions(positions));
+        }
     }
 
-    pub fn buffer_unindexed(&mut self, display: &Display) { 
-        let mut res : Vec<Vertex> = Vec::new();
+    //bounds of the mesh and all of its instances in world space
+    pub fn world_bounds(&self) -> Option<Bounds> {
+        self.bounds.map(|bounds| bounds.transform(self.transform_mat, self.offset, self.scale, &self.instances))
+    }
+
+    fn generate_tangents(&mut self) {
+        if let (Some(positions), Some(normals), Some(tex_coords), Some(indices)) =
+            (&self.positions, &self.normals, &self.tex_coords, &self.indices) {
+                self.tangents = model_loading::tangents::generate_tangents(positions, normals, tex_coords, indices)
+                    .map(|vec| vec.into_boxed_slice());
+            }
+    }
 
+    pub fn buffer_unindexed(&mut self, display: &Display) { 
         match self.positions {
             Some(_) => {},
             None => { return; }
@@ -202,36 +326,82 @@ impl Mesh {
         let tex_coords = self.tex_coords.clone().unwrap();
         let indices = self.indices.clone().unwrap();
 
-        let indx_len = indices.len();
-
-        if indx_len % 3 != 0 {
+        if indices.len() % 3 != 0 {
             return;
         }
-        
-        let mut curr_indx : usize = 0;
 
-        while curr_indx < indx_len {
-            let mut vert : Vertex = Vertex::default();
-            
-            let pos_index : usize = indices[curr_indx] as usize;
-            vert.position = positions[pos_index].position;
+        let res = unindexed_vertices(&positions, &normals, &tex_coords, self.tangents.as_deref(), &indices);
 
-            curr_indx += 1;
+        self.vertex_buf = Some(VertexBuffer::new(display, res.as_slice()).unwrap());
+        self.buffer_instances(display);
+    }
 
-            let norm_index : usize = indices[curr_indx] as usize;
-            vert.normal = normals[norm_index].normal;
+    pub fn set_lods(&mut self, lods: Vec<LodConfig>, hysteresis: f32) {
+        self.lods = lods.into_iter().map(|config| Lod { config, vertex_buf: None }).collect();
+        self.lod_hysteresis = hysteresis;
+    }
 
-            curr_indx += 1;
+    //loads authored lod models and simplifies the geometry for generated ones, needs load_geometry first
+    pub fn buffer_lods(&mut self, display: &Display) {
+        for i in 0..self.lods.len() {
+            let vertices = match self.lods[i].config.source {
+                LodSource::Model(id) => {
+                    let mut lod_mesh = Mesh::new_with_id(id);
+                    lod_mesh.load_geometry();
+                    lod_mesh.unindexed()
+                },
+                LodSource::Simplified(ratio) => self.simplified(ratio)
+            };
 
-            let tex_coord_index : usize = indices[curr_indx] as usize;
-            vert.tex_coords = tex_coords[tex_coord_index].coordinates;
+            self.lods[i].vertex_buf = vertices.map(|vertices| VertexBuffer::new(display, vertices.as_slice()).unwrap());
+        }
+    }
 
-            curr_indx += 1;
+    fn unindexed(&self) -> Option<Vec<Vertex>> {
+        match (&self.positions, &self.normals, &self.tex_coords, &self.indices) {
+            (Some(positions), Some(normals), Some(tex_coords), Some(indices)) => {
+                Some(unindexed_vertices(positions, normals, tex_coords, self.tangents.as_deref(), indices))
+            },
+            _ => None
+        }
+    }
 
-            res.push(vert);
+    fn simplified(&self, ratio: f32) -> Option<Vec<Vertex>> {
+        match (&self.positions, &self.normals, &self.tex_coords, &self.indices) {
+            (Some(positions), Some(normals), Some(tex_coords), Some(indices)) => {
+                let (lod_indices, source_corners) = simplification::simplify(positions, indices, ratio);
+                //every corner keeps the tangent of the corner its normal and uv come from
+                let lod_tangents : Option<Vec<Tangent>> = self.tangents.as_ref()
+                    .map(|tangents| source_corners.iter().map(|corner| tangents[*corner]).collect());
+                Some(unindexed_vertices(positions, normals, tex_coords, lod_tangents.as_deref(), &lod_indices))
+            },
+            _ => None
         }
+    }
 
-        self.vertex_buf = Some(VertexBuffer::new(display, res.as_slice()).unwrap());
+    //picks the level of detail for this frame, keeping the previous choice in mind
+    pub fn update_lod(&self, metrics: LodMetrics) {
+        let switches : Vec<LodSwitch> = self.lods.iter().map(|lod| lod.config.switch).collect();
+        self.lod_index.set(lod::select_lod(self.lod_index.get(), &switches, metrics, self.lod_hysteresis));
+    }
+
+    //the vertex buffer of the current level of detail, falling back to the base mesh
+    pub fn current_vertex_buf(&self) -> Option<&VertexBuffer<Vertex>> {
+        match self.lod_index.get() {
+            0 => self.vertex_buf.as_ref(),
+            i => self.lods[i - 1].vertex_buf.as_ref().or(self.vertex_buf.as_ref())
+        }
+    }
+
+    //every mesh is drawn instanced, meshes without instances get a single identity transform
+    fn buffer_instances(&mut self, display: &Display) {
+        let instances : Vec<Instance> = if self.instances.is_empty() {
+            vec![Instance { instance_transform: linalg::identity4() }]
+        } else {
+            self.instances.iter().map(|transform| Instance { instance_transform: *transform }).collect()
+        };
+
+        self.instance_buf = Some(VertexBuffer::new(display, instances.as_slice()).unwrap());
     }
 
     fn to_mesh_data(self) -> MeshData{
@@ -240,7 +410,9 @@ impl Mesh {
             positions: self.positions,
             normals: self.normals,
             tex_coords: self.tex_coords,
+            tangents: self.tangents,
             indices: self.indices,
+            bounds: self.bounds,
             transform_mat: self.transform_mat,
             offset: self.offset,
             scale: self.scale,
@@ -254,13 +426,70 @@ impl Mesh {
     }
 }
 
+//one vertex per index triplet, triangles are not shared
+fn unindexed_vertices(positions: &[Position],
+                      normals: &[Normal],
+                      tex_coords: &[TextureCoordinates],
+                      tangents: Option<&[Tangent]>,
+                      indices: &[u16]) -> Vec<Vertex> {
+    let mut res : Vec<Vertex> = Vec::new();
+    let indx_len = indices.len();
+    let mut curr_indx : usize = 0;
+
+    while curr_indx + 2 < indx_len {
+        let mut vert : Vertex = Vertex::default();
+        
+        let pos_index : usize = indices[curr_indx] as usize;
+        vert.position = positions[pos_index].position;
+
+        curr_indx += 1;
+
+        let norm_index : usize = indices[curr_indx] as usize;
+        vert.normal = normals[norm_index].normal;
+
+        curr_indx += 1;
+
+        let tex_coord_index : usize = indices[curr_indx] as usize;
+        vert.tex_coords = tex_coords[tex_coord_index].coordinates;
+
+        if let Some(tangents) = tangents {
+            vert.tangent = tangents[curr_indx / 3].tangent;
+        }
+
+        curr_indx += 1;
+
+        res.push(vert);
+    }
+
+    res
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+struct CacheHeader {
+    magic: [u8; 4],
+    version: u32
+}
+
+impl CacheHeader {
+    fn current() -> CacheHeader {
+        CacheHeader {
+            magic: CACHE_MAGIC,
+            version: CACHE_VERSION
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct MeshData {
    pub id: u16,
     pub positions: Option<Box<[Position]>>,
     pub normals: Option<Box<[Normal]>>,
     pub tex_coords: Option<Box<[TextureCoordinates]>>,
+    pub tangents: Option<Box<[Tangent]>>,
     pub indices: Option<Box<[u16]>>,
+    pub bounds: Option<Bounds>,
     pub transform_mat: [[f32; 4]; 4],
     pub offset: (f32, f32, f32),
     pub scale: f32,
@@ -279,7 +508,8 @@ impl MeshData {
 
         match std::fs::File::create(path) {
             Ok(mut bin_file) => {
-                match bincode::serialize_into(&mut bin_file, &self) {
+                match bincode::serialize_into(&mut bin_file, &CacheHeader::current())
+                    .and_then(|()| bincode::serialize_into(&mut bin_file, &self)) {
                     Ok(()) => {
                         Ok(())
                     },
@@ -305,26 +535,35 @@ fn retrieve_from_bin(id: u16) -> Result<MeshData, MeshStorageError> {
     let path = std::path::Path::new(&pathstr);
     
     match std::fs::File::open(path) {
-        Ok(bin_file) => {
-            let maybe_mesh_data : bincode::Result<MeshData> = bincode::deserialize_from(bin_file);
-            match maybe_mesh_data {
-                Ok(mesh_data) => {
-                    Ok(mesh_data)
-                },
-                Err(err) => {
-                    Err(MeshStorageError::SerdeError(err))
-                }
-            }
-        },
+        Ok(bin_file) => read_mesh_data(bin_file),
         Err(err) => {
             Err(MeshStorageError::IOError(err))
         }
     }  
 }
 
+fn read_mesh_data<R: std::io::Read>(mut reader: R) -> Result<MeshData, MeshStorageError> {
+    //a file from before the header existed starts with the mesh id, which won't match the magic
+    match bincode::deserialize_from::<_, CacheHeader>(&mut reader) {
+        Ok(header) if header == CacheHeader::current() => {},
+        _ => { return Err(MeshStorageError::OutdatedCache); }
+    }
+    let maybe_mesh_data : bincode::Result<MeshData> = bincode::deserialize_from(reader);
+    match maybe_mesh_data {
+        Ok(mesh_data) => {
+            Ok(mesh_data)
+        },
+        Err(err) => {
+            Err(MeshStorageError::SerdeError(err))
+        }
+    }
+}
+
 pub enum MeshStorageError {
     IOError(std::io::Error),
-    SerdeError(bincode::Error)
+    SerdeError(bincode::Error),
+    //written by another version of the program
+    OutdatedCache
 }
 
 impl MeshStorageError {
@@ -337,142 +576,26 @@ impl MeshStorageError {
             MeshStorageError::IOError(err) => {
                 println!("There has been an input or output error:");
                 println!("{:#?}", err);
-            }
-        }
-    }
-}
-
-struct MeshConfig {
-    id: u16,
-    shader: u16,
-    texture: u16,
-    offset: (f32, f32, f32),
-    scale: f32
-}
-
-impl MeshConfig {
-    fn mesh_config_parser<'a>() -> impl Parser<Self, &'a str> {
-        move |input: &'a str| {
-            parsing::parse_token("{").and(
-                parsing::parse_ws().maybe()
-            ).and(
-                parsing::parse_token("id: ")
-                    .and(parsing::parse_u16())
-
-                    .and(parsing::parse_ws().maybe())
-                    .and(parsing::parse_token(","))
-                    .and(parsing::parse_ws().maybe())
-                    .map( |((((_id_str, id), _ws0), _comma), _ws1)| id )
-            ).and(
-                parsing::parse_token("shader: ")
-                    .and(parsing::parse_u16())
-                    .and(parsing::parse_ws().maybe())
-                    .and(parsing::parse_token(","))
-                    .and(parsing::parse_ws().maybe())
-                    .map( |((((_shader_str, shader), _ws0), _comma), _ws1)| shader )
-            ).and(
-                parsing::parse_token("texture: ")
-                    .and(parsing::parse_u16())
-                    .and(parsing::parse_ws().maybe())
-                    .and(parsing::parse_token(","))
-                    .and(parsing::parse_ws().maybe())
-                    .map( |((((_texture_str, texture), _ws0), _comma), _ws1)| texture )
-            ).and(
-                parsing::parse_token("offset: (")
-                    .and(parsing::parse_scientific().many_delim(
-                        parsing::parse_ws().maybe().and(
-                            parsing::parse_token(",")
-                        ).and(
-                            parsing::parse_ws().maybe()
-                        )
-                    ))
-                    .and(parsing::parse_token(")"))
-                    .and(parsing::parse_ws().maybe())
-                    .and(parsing::parse_token(","))
-                    .and(parsing::parse_ws().maybe())
-                    .map( |(((((_offset_str, offset), _par), _ws0), _comma), _ws1)| offset )
-            ).and(
-                parsing::parse_token("scale: ")
-                    .and(parsing::parse_scientific())
-                    .and(parsing::parse_ws().maybe())
-                    .map( |((_scale_str, scale), _ws)| scale )
-            ).and(
-                parsing::parse_token("}")
-            ).map( |(((((((_br0, _ws), id), shader), tex), offset), scale),_br1)| {
-                MeshConfig {
-                    id: id,
-                    shader: shader,
-                    texture: tex,
-                    offset: (offset[0], offset[1], offset[2]),
-                    scale: scale
-                }
-            }).parse(input)
-        }
-    }
-}
-
-pub struct SceneConfig { meshes: Vec<MeshConfig> }
-
-impl SceneConfig {
-    pub fn load_scene_config(source: &str) -> Result<Self, SceneLoadError> {
-        let mut pathstr = std::string::String::new();
-        pathstr.push_str("./resources/scenes/");
-        pathstr.push_str(source.clone());
-        let path = std::path::Path::new(&pathstr);
-
-        match std::fs::read_to_string(path) {
-            Ok(source) => {
-                match Self::scene_config_parser().parse(&source) {
-                    Some(conf) => {
-                        Ok(conf.1)
-                    },
-                    None => {
-                        Err(SceneLoadError::ParseError)
-                    }
-                }
             },
-            Err(err) => {
-                Err(SceneLoadError::IOError(err))
+            MeshStorageError::OutdatedCache => {
+                println!("The binary file was written by another version of the program.");
             }
         }
     }
+}
 
-    pub fn construct_meshes(&self) -> Vec<Mesh> {
-        let mut res : Vec<Mesh> = Vec::new();
-
-        for mesh_config in &self.meshes {
-            let mut mesh = Mesh::new_with_id_shader_tex(
-                mesh_config.id,
-                mesh_config.shader,
-                mesh_config.texture
-            );
-            mesh.set_offset(mesh_config.offset);
-            mesh.set_scale(mesh_config.scale);
+#[test]
+fn mesh_cache_header_test() {
+    let mesh_data = || Mesh::new_with_id_shader_tex(7, 1, 1).to_mesh_data();
 
-            res.push(mesh);
-        }
+    let mut current = bincode::serialize(&CacheHeader::current()).unwrap();
+    current.extend(bincode::serialize(&mesh_data()).unwrap());
+    assert_eq!(read_mesh_data(current.as_slice()).ok().map(|mesh_data| mesh_data.id), Some(7));
 
-        res
-    }
-    
-    fn scene_config_parser<'a>() -> impl Parser<Self, &'a str> {
-        move |input: &'a str| {
-            MeshConfig::mesh_config_parser().many_delim(
-                parsing::parse_ws().maybe().and(
-                    parsing::parse_token(",")
-                ).and(
-                    parsing::parse_ws().maybe()
-                )
-            ).map(
-                |mesh_configs| SceneConfig{ meshes: mesh_configs }
-            ).parse(input)
-        }
-    }
-}
-
-#[derive(Debug)]
-pub enum SceneLoadError {
-    IOError(std::io::Error),
-    ParseError
+    //files from before the header and from other versions are rebuilt instead of misread
+    let headerless = bincode::serialize(&mesh_data()).unwrap();
+    assert!(matches!(read_mesh_data(headerless.as_slice()), Err(MeshStorageError::OutdatedCache)));
+    let mut older = bincode::serialize(&CacheHeader { version: CACHE_VERSION - 1, .. CacheHeader::current() }).unwrap();
+    older.extend(bincode::serialize(&mesh_data()).unwrap());
+    assert!(matches!(read_mesh_data(older.as_slice()), Err(MeshStorageError::OutdatedCache)));
 }
-
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use crate::drawing::mesh::model_loading::parsing::{Collada, TagParameter};
 
 pub mod parsing;
+pub mod tangents;
 
 #[derive(Clone)]
 #[derive(Copy)]
@@ -12,7 +13,9 @@ pub mod parsing;
 pub struct Vertex {
     pub position: (f32, f32, f32),
     pub normal: (f32, f32, f32),
-    pub tex_coords: (f32, f32)
+    pub tex_coords: (f32, f32),
+    //xyz is the tangent, w the sign of the bitangent
+    pub tangent: (f32, f32, f32, f32)
 }
 
 impl Default for Vertex {
@@ -21,6 +24,7 @@ impl Default for Vertex {
             position: (0.0, 0.0, 0.0),
             normal: (0.0, 0.0, 0.0),
             tex_coords: (0.0, 0.0),
+            tangent: (1.0, 0.0, 0.0, 1.0)
         }
     }
 }
@@ -73,10 +77,27 @@ impl Default for TextureCoordinates {
     }
 }
 
-implement_vertex!(Vertex, position, normal, tex_coords);
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Tangent {
+    pub tangent: (f32, f32, f32, f32)
+}
+
+impl Default for Tangent {
+    fn default() -> Tangent {
+        Tangent{
+            tangent: (1.0, 0.0, 0.0, 1.0)
+        }
+    }
+}
+
+implement_vertex!(Vertex, position, normal, tex_coords, tangent);
 implement_vertex!(Position, position);
 implement_vertex!(Normal, normal);
 implement_vertex!(TextureCoordinates, coordinates);
+implement_vertex!(Tangent, tangent);
 
 fn find_tag_name<'a>(source: &'a Collada<'a>, name: &'a str) -> Option<&'a Collada<'a>> {
     match source {
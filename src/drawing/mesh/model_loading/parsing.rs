@@ -287,23 +287,6 @@ fn parse_digit<'a: 'b, 'b>() -> impl Parser<u32 , &'a str> + 'b{
     }
 }
 
-pub fn parse_u16<'a>() -> impl Parser<u16, &'a str> {
-    move |input: &'a str| {       
-        (parse_digit().many()).map(
-            |t: Vec<u32>| {
-                let mut res: u16 = 0;
-                let mut digits = t;
-                let mut i: u32 = 0;
-                while let Some(k) = digits.pop() {
-                    res += (k as u16)*(u16::pow(10, i));
-                    i += 1;
-                }
-                res
-            }
-        ).parse(input)
-    }
-}
-
 fn parse_integer<'a>() -> impl Parser<i32, &'a str> {
     move |input: &'a str| {
         (parse_token("-").and(
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::event_handling::camera_transformations::linalg;
+use super::{ Position, Normal, TextureCoordinates, Tangent };
+
+/* tangent frames follow the MikkTSpace conventions: the tangent points along +u,
+ * the bitangent is reconstructed as sign * cross(normal, tangent) and the frame is
+ * interpolated per vertex without normalization in the fragment shader.
+ * contributions of the faces around a vertex are weighted by the corner angle and
+ * only merged if the vertex is identical and the uv mapping has the same orientation */
+
+//corners are grouped by their index triplet and the orientation of the uv mapping
+type CornerKey = (u16, u16, u16, bool);
+
+fn to_array(vec: (f32, f32, f32)) -> [f32; 3] {
+    [vec.0, vec.1, vec.2]
+}
+
+//removes the part of vec along the normal
+fn project(vec: [f32; 3], normal: [f32; 3]) -> [f32; 3] {
+    linalg::sub(vec, linalg::scale(normal, linalg::dot(normal, vec)))
+}
+
+//any unit vector perpendicular to the normal, used when the uv mapping is degenerate
+fn perpendicular(normal: [f32; 3]) -> [f32; 3] {
+    if normal[0].abs() < 0.9 {
+        linalg::norm(project([1.0, 0.0, 0.0], normal))
+    } else {
+        linalg::norm(project([0.0, 1.0, 0.0], normal))
+    }
+}
+
+fn corner_angle(corner: [f32; 3], next: [f32; 3], prev: [f32; 3]) -> f32 {
+    let edge0 = linalg::norm(linalg::sub(next, corner));
+    let edge1 = linalg::norm(linalg::sub(prev, corner));
+    linalg::dot(edge0, edge1).clamp(-1.0, 1.0).acos()
+}
+
+/* indices come in (position, normal, texture coordinate) triplets like in the collada
+ * file, the result holds one tangent per triplet */
+pub fn generate_tangents(positions: &[Position],
+                         normals: &[Normal],
+                         tex_coords: &[TextureCoordinates],
+                         indices: &[u16]) -> Option<Vec<Tangent>> {
+    if !indices.len().is_multiple_of(9) {
+        return None;
+    }
+
+    let corner_count = indices.len() / 3;
+    let mut corner_tangents : Vec<[f32; 3]> = Vec::with_capacity(corner_count);
+    let mut corner_bitangents : Vec<[f32; 3]> = Vec::with_capacity(corner_count);
+    let mut corner_orientations : Vec<bool> = Vec::with_capacity(corner_count);
+
+    for triangle in indices.chunks(9) {
+        let mut pos = [[0.0; 3]; 3];
+        let mut norm = [[0.0; 3]; 3];
+        let mut uv = [(0.0, 0.0); 3];
+        for i in 0..3 {
+            pos[i] = to_array(positions.get(triangle[3 * i] as usize)?.position);
+            norm[i] = linalg::norm(to_array(normals.get(triangle[3 * i + 1] as usize)?.normal));
+            uv[i] = tex_coords.get(triangle[3 * i + 2] as usize)?.coordinates;
+        }
+
+        let edge0 = linalg::sub(pos[1], pos[0]);
+        let edge1 = linalg::sub(pos[2], pos[0]);
+        let (du0, dv0) = (uv[1].0 - uv[0].0, uv[1].1 - uv[0].1);
+        let (du1, dv1) = (uv[2].0 - uv[0].0, uv[2].1 - uv[0].1);
+        let signed_area = du0 * dv1 - dv0 * du1;
+
+        //like mikktspace the magnitude of the uv area is dropped, only its sign is kept
+        let orientation = signed_area >= 0.0;
+        let flip = if orientation { 1.0 } else { -1.0 };
+        let face_tangent = linalg::scale(linalg::sub(linalg::scale(edge0, dv1), linalg::scale(edge1, dv0)), flip);
+        let face_bitangent = linalg::scale(linalg::sub(linalg::scale(edge1, du0), linalg::scale(edge0, du1)), flip);
+
+        for i in 0..3 {
+            let angle = corner_angle(pos[i], pos[(i + 1) % 3], pos[(i + 2) % 3]);
+            let tangent = linalg::norm(project(face_tangent, norm[i]));
+            let bitangent = linalg::norm(project(face_bitangent, norm[i]));
+            corner_tangents.push(linalg::scale(tangent, angle));
+            corner_bitangents.push(linalg::scale(bitangent, angle));
+            corner_orientations.push(orientation);
+        }
+    }
+
+    //accumulate the corners sharing a vertex
+    let mut groups : HashMap<CornerKey, ([f32; 3], [f32; 3])> = HashMap::new();
+    for corner in 0..corner_count {
+        let key = (indices[3 * corner], indices[3 * corner + 1], indices[3 * corner + 2],
+                   corner_orientations[corner]);
+        let entry = groups.entry(key).or_insert(([0.0; 3], [0.0; 3]));
+        entry.0 = linalg::add(entry.0, corner_tangents[corner]);
+        entry.1 = linalg::add(entry.1, corner_bitangents[corner]);
+    }
+
+    let mut res : Vec<Tangent> = Vec::with_capacity(corner_count);
+    for corner in 0..corner_count {
+        let key = (indices[3 * corner], indices[3 * corner + 1], indices[3 * corner + 2],
+                   corner_orientations[corner]);
+        let (tangent_sum, bitangent_sum) = groups[&key];
+        let normal = linalg::norm(to_array(normals[indices[3 * corner + 1] as usize].normal));
+
+        let mut tangent = linalg::norm(project(tangent_sum, normal));
+        if linalg::mag(tangent) < 0.5 {
+            tangent = perpendicular(normal);
+        }
+        let sign = if linalg::dot(linalg::cross(normal, tangent), bitangent_sum) < 0.0 { -1.0 } else { 1.0 };
+
+        res.push(Tangent{ tangent: (tangent[0], tangent[1], tangent[2], sign) });
+    }
+    Some(res)
+}
+
+#[test]
+fn quad_tangents_test() {
+    //a quad in the xy plane facing +z with u along +x and v along +y
+    let positions = vec![
+        Position{ position: (0.0, 0.0, 0.0) },
+        Position{ position: (1.0, 0.0, 0.0) },
+        Position{ position: (1.0, 1.0, 0.0) },
+        Position{ position: (0.0, 1.0, 0.0) }
+    ];
+    let normals = vec![Normal{ normal: (0.0, 0.0, 1.0) }];
+    let tex_coords = vec![
+        TextureCoordinates{ coordinates: (0.0, 0.0) },
+        TextureCoordinates{ coordinates: (1.0, 0.0) },
+        TextureCoordinates{ coordinates: (1.0, 1.0) },
+        TextureCoordinates{ coordinates: (0.0, 1.0) }
+    ];
+    let indices : Vec<u16> = vec![0, 0, 0, 1, 0, 1, 2, 0, 2,
+                                 0, 0, 0, 2, 0, 2, 3, 0, 3];
+
+    let tangents = generate_tangents(&positions, &normals, &tex_coords, &indices).unwrap();
+    assert_eq!(tangents.len(), 6);
+    for tangent in tangents.iter() {
+        assert_eq!(tangent.tangent, (1.0, 0.0, 0.0, 1.0));
+    }
+
+    //mirroring the u coordinate flips the tangent and keeps the bitangent through the sign
+    let mirrored : Vec<TextureCoordinates> = tex_coords.iter()
+        .map(|coords| TextureCoordinates{ coordinates: (1.0 - coords.coordinates.0, coords.coordinates.1) })
+        .collect();
+    let tangents = generate_tangents(&positions, &normals, &mirrored, &indices).unwrap();
+    for tangent in tangents.iter() {
+        assert_eq!(tangent.tangent, (-1.0, 0.0, 0.0, -1.0));
+    }
+}
@@ -0,0 +1,429 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::event_handling::camera_transformations::linalg;
+use super::model_loading::Position;
+
+//extra weight of the planes that keep open borders in place
+const BOUNDARY_WEIGHT: f64 = 100.0;
+
+//symmetric 4x4 error quadric, stored as its upper triangle
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+struct Quadric {
+    q: [f64; 10]
+}
+
+impl Quadric {
+    fn zero() -> Quadric {
+        Quadric { q: [0.0; 10] }
+    }
+
+    //squared distance to the plane n.x + d = 0, scaled by weight
+    fn from_plane(normal: [f32; 3], d: f32, weight: f64) -> Quadric {
+        let (a, b, c, d) = (normal[0] as f64, normal[1] as f64, normal[2] as f64, d as f64);
+        Quadric {
+            q: [a * a, a * b, a * c, a * d,
+                       b * b, b * c, b * d,
+                              c * c, c * d,
+                                     d * d].map(|x| x * weight)
+        }
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut q = self.q;
+        for (entry, other) in q.iter_mut().zip(other.q.iter()) {
+            *entry += other;
+        }
+        Quadric { q }
+    }
+
+    fn error(&self, p: [f32; 3]) -> f64 {
+        let (x, y, z) = (p[0] as f64, p[1] as f64, p[2] as f64);
+        let q = &self.q;
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+//moving position `from` onto position `to`, ordered so the heap pops the cheapest first
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    //versions of both positions when the cost was computed, outdated entries are skipped
+    from_version: u32,
+    to_version: u32
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+//what a triangle corner has besides its position
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+struct CornerAttributes {
+    normal: u16,
+    tex_coords: u16,
+    //the original corner the normal and texture coordinates were taken from
+    source: usize
+}
+
+impl CornerAttributes {
+    fn same_vertex(&self, other: &CornerAttributes) -> bool {
+        self.normal == other.normal && self.tex_coords == other.tex_coords
+    }
+}
+
+fn to_array(pos: &Position) -> [f32; 3] {
+    [pos.position.0, pos.position.1, pos.position.2]
+}
+
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    linalg::cross(linalg::sub(b, a), linalg::sub(c, a))
+}
+
+struct Simplifier<'a> {
+    positions: &'a [Position],
+    //position indices of every triangle and whether it is still part of the mesh
+    triangles: Vec<[usize; 3]>,
+    corners: Vec<[CornerAttributes; 3]>,
+    alive: Vec<bool>,
+    //triangles around every position
+    adjacent: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    heap: BinaryHeap<Collapse>
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(positions: &'a [Position], triangles: Vec<[usize; 3]>, corners: Vec<[CornerAttributes; 3]>) -> Simplifier<'a> {
+        let mut adjacent : Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+        let mut quadrics = vec![Quadric::zero(); positions.len()];
+
+        for (i, tri) in triangles.iter().enumerate() {
+            let corners = tri.map(|index| to_array(&positions[index]));
+            let normal = face_normal(corners[0], corners[1], corners[2]);
+            //area weighted, the cross product is twice the area
+            let area = 0.5 * linalg::mag(normal) as f64;
+            let unit = linalg::norm(normal);
+            let plane = Quadric::from_plane(unit, -linalg::dot(unit, corners[0]), area);
+            for index in tri.iter() {
+                adjacent[*index].push(i);
+                quadrics[*index] = quadrics[*index].add(&plane);
+            }
+        }
+
+        let mut simplifier = Simplifier {
+            positions,
+            alive: vec![true; triangles.len()],
+            triangles,
+            corners,
+            adjacent,
+            quadrics,
+            versions: vec![0; positions.len()],
+            removed: vec![false; positions.len()],
+            heap: BinaryHeap::new()
+        };
+        simplifier.add_boundary_quadrics();
+
+        for i in 0..simplifier.triangles.len() {
+            let tri = simplifier.triangles[i];
+            for k in 0..3 {
+                simplifier.push_edge(tri[k], tri[(k + 1) % 3]);
+            }
+        }
+        simplifier
+    }
+
+    //edges with only one triangle get a plane perpendicular to that triangle so borders don't shrink
+    fn add_boundary_quadrics(&mut self) {
+        let mut edges : Vec<(usize, usize, usize)> = Vec::new();
+        for (i, tri) in self.triangles.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                edges.push((a.min(b), a.max(b), i));
+            }
+        }
+        edges.sort();
+
+        let mut i = 0;
+        while i < edges.len() {
+            let mut j = i + 1;
+            while j < edges.len() && edges[j].0 == edges[i].0 && edges[j].1 == edges[i].1 {
+                j += 1;
+            }
+            if j - i == 1 {
+                let (a, b, tri) = edges[i];
+                let tri = self.triangles[tri].map(|index| to_array(&self.positions[index]));
+                let normal = face_normal(tri[0], tri[1], tri[2]);
+                let (pa, pb) = (to_array(&self.positions[a]), to_array(&self.positions[b]));
+                let edge = linalg::sub(pb, pa);
+                let border_normal = linalg::norm(linalg::cross(edge, normal));
+                let weight = BOUNDARY_WEIGHT * linalg::dot(edge, edge) as f64;
+                let plane = Quadric::from_plane(border_normal, -linalg::dot(border_normal, pa), weight);
+                self.quadrics[a] = self.quadrics[a].add(&plane);
+                self.quadrics[b] = self.quadrics[b].add(&plane);
+            }
+            i = j;
+        }
+    }
+
+    //both directions of the edge are considered, the collapse keeps one of the two existing positions
+    fn push_edge(&mut self, a: usize, b: usize) {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+        for (from, to) in [(a, b), (b, a)] {
+            self.heap.push(Collapse {
+                cost: quadric.error(to_array(&self.positions[to])),
+                from,
+                to,
+                from_version: self.versions[from],
+                to_version: self.versions[to]
+            });
+        }
+    }
+
+    //a collapse must not turn any remaining triangle around
+    fn flips_triangles(&self, from: usize, to: usize) -> bool {
+        for tri in self.adjacent[from].iter().filter(|tri| self.alive[**tri]) {
+            let tri = self.triangles[*tri];
+            if tri.contains(&to) {
+                continue;
+            }
+            let before = tri.map(|index| to_array(&self.positions[index]));
+            let after = tri.map(|index| to_array(&self.positions[if index == from { to } else { index }]));
+            let normal_before = face_normal(before[0], before[1], before[2]);
+            let normal_after = face_normal(after[0], after[1], after[2]);
+            if linalg::dot(normal_before, normal_after) <= 0.0 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /* the corners of from take over the normal and texture coordinates that to has in the triangles
+     * which disappear with the collapse. a corner of from without exactly one such replacement sits on
+     * a seam the collapse would tear open, so the collapse isn't possible */
+    fn attribute_remap(&self, from: usize, to: usize) -> Option<Vec<(CornerAttributes, CornerAttributes)>> {
+        let mut remap : Vec<(CornerAttributes, CornerAttributes)> = Vec::new();
+        for tri in self.adjacent[from].iter().filter(|tri| self.alive[**tri]) {
+            let (indices, corners) = (self.triangles[*tri], self.corners[*tri]);
+            let from_corner = indices.iter().position(|index| *index == from);
+            let to_corner = indices.iter().position(|index| *index == to);
+            if let (Some(from_corner), Some(to_corner)) = (from_corner, to_corner) {
+                let (old, new) = (corners[from_corner], corners[to_corner]);
+                match remap.iter().find(|(known, _)| known.same_vertex(&old)) {
+                    Some((_, known_new)) => {
+                        if !known_new.same_vertex(&new) {
+                            return None;
+                        }
+                    },
+                    None => remap.push((old, new))
+                }
+            }
+        }
+
+        for tri in self.adjacent[from].iter().filter(|tri| self.alive[**tri] && !self.triangles[**tri].contains(&to)) {
+            for (index, corner) in self.triangles[*tri].iter().zip(self.corners[*tri].iter()) {
+                if *index == from && !remap.iter().any(|(old, _)| old.same_vertex(corner)) {
+                    return None;
+                }
+            }
+        }
+        Some(remap)
+    }
+
+    //returns the number of triangles that disappeared
+    fn collapse(&mut self, from: usize, to: usize, remap: &[(CornerAttributes, CornerAttributes)]) -> usize {
+        let mut removed_triangles = 0;
+        let adjacent = std::mem::take(&mut self.adjacent[from]);
+
+        for tri in adjacent {
+            if !self.alive[tri] {
+                continue;
+            }
+            if self.triangles[tri].contains(&to) {
+                self.alive[tri] = false;
+                removed_triangles += 1;
+            } else {
+                for (index, corner) in self.triangles[tri].iter_mut().zip(self.corners[tri].iter_mut()) {
+                    if *index == from {
+                        *index = to;
+                        if let Some((_, new)) = remap.iter().find(|(old, _)| old.same_vertex(corner)) {
+                            *corner = *new;
+                        }
+                    }
+                }
+                self.adjacent[to].push(tri);
+            }
+        }
+
+        self.removed[from] = true;
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+        self.versions[to] += 1;
+
+        let mut neighbours : Vec<usize> = Vec::new();
+        for tri in self.adjacent[to].iter().filter(|tri| self.alive[**tri]) {
+            for index in self.triangles[*tri].iter() {
+                if *index != to && !neighbours.contains(index) {
+                    neighbours.push(*index);
+                }
+            }
+        }
+        for neighbour in neighbours {
+            self.push_edge(to, neighbour);
+        }
+
+        removed_triangles
+    }
+
+    fn run(&mut self, target_triangles: usize) {
+        let mut triangle_count = self.triangles.len();
+
+        while triangle_count > target_triangles {
+            let collapse = match self.heap.pop() {
+                Some(collapse) => collapse,
+                None => { break; }
+            };
+
+            if self.removed[collapse.from] || self.removed[collapse.to]
+                || collapse.from_version != self.versions[collapse.from]
+                || collapse.to_version != self.versions[collapse.to] {
+                continue;
+            }
+            if self.flips_triangles(collapse.from, collapse.to) {
+                continue;
+            }
+            let remap = match self.attribute_remap(collapse.from, collapse.to) {
+                Some(remap) => remap,
+                None => { continue; }
+            };
+
+            triangle_count -= self.collapse(collapse.from, collapse.to, &remap);
+        }
+    }
+}
+
+/* quadric error edge collapse after garland and heckbert, restricted to collapsing onto one of the
+ * two existing positions. the moved corners take the normal and texture coordinates of the position
+ * they moved onto. indices are (position, normal, texture coordinate) triplets, the result uses the
+ * same layout together with the original corner the normal and texture coordinates of every new
+ * corner came from */
+pub fn simplify(positions: &[Position], indices: &[u16], ratio: f32) -> (Vec<u16>, Vec<usize>) {
+    let triangle_count = indices.len() / 9;
+    let triangles : Vec<[usize; 3]> = (0..triangle_count)
+        .map(|tri| [0, 1, 2].map(|k| indices[9 * tri + 3 * k] as usize))
+        .collect();
+    let corners : Vec<[CornerAttributes; 3]> = (0..triangle_count)
+        .map(|tri| [0, 1, 2].map(|k| {
+            let corner = 3 * tri + k;
+            CornerAttributes { normal: indices[3 * corner + 1], tex_coords: indices[3 * corner + 2], source: corner }
+        }))
+        .collect();
+
+    let mut simplifier = Simplifier::new(positions, triangles, corners);
+    simplifier.run((triangle_count as f32 * ratio.clamp(0.0, 1.0)) as usize);
+
+    let mut res_indices : Vec<u16> = Vec::new();
+    let mut source_corners : Vec<usize> = Vec::new();
+    for tri in (0..triangle_count).filter(|tri| simplifier.alive[*tri]) {
+        for (index, corner) in simplifier.triangles[tri].iter().zip(simplifier.corners[tri].iter()) {
+            res_indices.push(*index as u16);
+            res_indices.push(corner.normal);
+            res_indices.push(corner.tex_coords);
+            source_corners.push(corner.source);
+        }
+    }
+
+    (res_indices, source_corners)
+}
+
+#[test]
+fn simplify_grid_test() {
+    //a flat 5x5 grid of positions, 32 triangles that can all be merged without any error
+    let mut positions : Vec<Position> = Vec::new();
+    for y in 0..5 {
+        for x in 0..5 {
+            positions.push(Position{ position: (x as f32, y as f32, 0.0) });
+        }
+    }
+    let mut indices : Vec<u16> = Vec::new();
+    for y in 0..4 {
+        for x in 0..4 {
+            let i = (5 * y + x) as u16;
+            for pos in [i, i + 1, i + 6, i, i + 6, i + 5] {
+                indices.extend([pos, 0, 0]);
+            }
+        }
+    }
+
+    let (simplified, source_corners) = simplify(&positions, &indices, 0.25);
+    let triangles = simplified.len() / 9;
+    assert!(triangles <= 8 && triangles > 0);
+    assert_eq!(source_corners.len(), simplified.len() / 3);
+
+    //every remaining triangle still faces +z and the border corners are kept
+    for tri in simplified.chunks(9) {
+        let corners = [tri[0], tri[3], tri[6]].map(|index| to_array(&positions[index as usize]));
+        assert!(face_normal(corners[0], corners[1], corners[2])[2] > 0.0);
+    }
+    for corner in [0, 4, 20, 24] {
+        assert!(simplified.chunks(3).any(|triplet| triplet[0] == corner));
+    }
+}
+
+#[test]
+fn simplify_attributes_test() {
+    //the same grid, every position has its own normal and the right half has other uvs than the left
+    let mut positions : Vec<Position> = Vec::new();
+    for y in 0..5 {
+        for x in 0..5 {
+            positions.push(Position{ position: (x as f32, y as f32, 0.0) });
+        }
+    }
+    let mut indices : Vec<u16> = Vec::new();
+    for y in 0..4 {
+        for x in 0..4 {
+            let i = (5 * y + x) as u16;
+            let uv_offset = if x < 2 { 0 } else { 100 };
+            for pos in [i, i + 1, i + 6, i, i + 6, i + 5] {
+                indices.extend([pos, pos, pos + uv_offset]);
+            }
+        }
+    }
+
+    let (simplified, source_corners) = simplify(&positions, &indices, 0.25);
+    assert!(simplified.len() < indices.len());
+    for (corner, source) in simplified.chunks(3).zip(source_corners.iter()) {
+        //the attributes belong to the position and come from a corner that had both
+        assert_eq!(corner[1], corner[0]);
+        assert_eq!(&indices[3 * source..3 * source + 3], corner);
+    }
+    //no triangle mixes the uvs of both sides of the seam down the middle
+    for tri in simplified.chunks(9) {
+        let right_side = [tri[2], tri[5], tri[8]].map(|uv| uv >= 100);
+        assert!(right_side.iter().all(|side| *side == right_side[0]));
+    }
+}
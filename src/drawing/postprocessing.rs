@@ -0,0 +1,211 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::DepthTexture2d;
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{Uniforms, UniformValue};
+use glium::{Display, Frame, Surface, VertexBuffer};
+
+use crate::scene::PostProcessConfig;
+use super::SpriteVertex;
+use super::shader_compilation::ShaderProg;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum PassUniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4])
+}
+
+pub struct PostProcessPass {
+    shader: ShaderProg,
+    scale: f32,
+    uniforms: Vec<(String, PassUniformValue)>
+}
+
+impl PostProcessPass {
+    pub fn from_config(config: &PostProcessConfig, display: &Display) -> PostProcessPass {
+        PostProcessPass {
+            shader: ShaderProg::load_from_file_pp(config.shader, display),
+            scale: config.scale,
+            uniforms: config.uniforms.clone()
+        }
+    }
+
+    fn output_dimensions(&self, dimensions: (u32, u32)) -> (u32, u32) {
+        (((dimensions.0 as f32 * self.scale) as u32).max(1),
+         ((dimensions.1 as f32 * self.scale) as u32).max(1))
+    }
+}
+
+//two textures of the same size that passes alternate between
+struct PingPongTargets {
+    dimensions: (u32, u32),
+    textures: [Texture2d; 2]
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+enum PassInput {
+    Scene,
+    Intermediate(usize, usize)
+}
+
+struct PassUniforms<'a> {
+    color: &'a Texture2d,
+    depth: &'a DepthTexture2d,
+    time: f32,
+    resolution: [f32; 2],
+    source_resolution: [f32; 2],
+    custom: &'a [(String, PassUniformValue)]
+}
+
+impl<'b> Uniforms for PassUniforms<'b> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        visit("color_sampler", UniformValue::Texture2d(self.color, None));
+        visit("depth_sampler", UniformValue::DepthTexture2d(self.depth, None));
+        visit("time", UniformValue::Float(self.time));
+        visit("resolution", UniformValue::Vec2(self.resolution));
+        visit("source_resolution", UniformValue::Vec2(self.source_resolution));
+
+        for (name, value) in self.custom {
+            match value {
+                PassUniformValue::Float(x) => visit(name, UniformValue::Float(*x)),
+                PassUniformValue::Vec2(v) => visit(name, UniformValue::Vec2(*v)),
+                PassUniformValue::Vec3(v) => visit(name, UniformValue::Vec3(*v)),
+                PassUniformValue::Vec4(v) => visit(name, UniformValue::Vec4(*v))
+            }
+        }
+    }
+}
+
+pub struct PostProcessChain {
+    passes: Vec<PostProcessPass>,
+    targets: Vec<PingPongTargets>
+}
+
+impl PostProcessChain {
+    pub fn new(passes: Vec<PostProcessPass>) -> PostProcessChain {
+        PostProcessChain {
+            passes,
+            targets: Vec::new()
+        }
+    }
+
+    pub fn from_configs(configs: &[PostProcessConfig], display: &Display) -> PostProcessChain {
+        Self::new(configs.iter().map(|config| PostProcessPass::from_config(config, display)).collect())
+    }
+
+    //makes sure there is a pair of targets for every intermediate resolution in the chain
+    fn update_targets(&mut self, display: &Display, dimensions: (u32, u32)) {
+        let mut required : Vec<(u32, u32)> = Vec::new();
+        if self.passes.len() > 1 {
+            for pass in &self.passes[..self.passes.len() - 1] {
+                let pass_dimensions = pass.output_dimensions(dimensions);
+                if !required.contains(&pass_dimensions) {
+                    required.push(pass_dimensions);
+                }
+            }
+        }
+
+        self.targets.retain(|targets| required.contains(&targets.dimensions));
+
+        for pass_dimensions in required {
+            if !self.targets.iter().any(|targets| targets.dimensions == pass_dimensions) {
+                let (w, h) = pass_dimensions;
+                self.targets.push(PingPongTargets {
+                    dimensions: pass_dimensions,
+                    textures: [Texture2d::empty(display, w, h).unwrap(),
+                               Texture2d::empty(display, w, h).unwrap()]
+                });
+            }
+        }
+    }
+
+    fn input_texture<'a>(&'a self, input: PassInput, scene_color: &'a Texture2d) -> &'a Texture2d {
+        match input {
+            PassInput::Scene => scene_color,
+            PassInput::Intermediate(target, slot) => &self.targets[target].textures[slot]
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(&mut self,
+                  display: &Display,
+                  frame: &mut Frame,
+                  quad: &VertexBuffer<SpriteVertex>,
+                  scene_color: &Texture2d,
+                  scene_depth: &DepthTexture2d,
+                  viewport: glium::Rect,
+                  time: f32) {
+        //the scene targets have the size of the part of the frame they end up in
+        let dimensions = (viewport.width, viewport.height);
+        if self.passes.is_empty() {
+            let source = SimpleFrameBuffer::new(display, scene_color).unwrap();
+            frame.blit_from_simple_framebuffer(&source,
+                                               &full_rect(dimensions),
+                                               &blit_target(viewport),
+                                               glium::uniforms::MagnifySamplerFilter::Nearest);
+            return;
+        }
+
+        self.update_targets(display, dimensions);
+
+        let mut input = PassInput::Scene;
+        let mut input_dimensions = dimensions;
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            let color = self.input_texture(input, scene_color);
+            let is_last = i + 1 == self.passes.len();
+            let output_dimensions = if is_last { dimensions } else { pass.output_dimensions(dimensions) };
+
+            let uniforms = PassUniforms {
+                color,
+                depth: scene_depth,
+                time,
+                resolution: [output_dimensions.0 as f32, output_dimensions.1 as f32],
+                source_resolution: [input_dimensions.0 as f32, input_dimensions.1 as f32],
+                custom: &pass.uniforms
+            };
+
+            if is_last {
+                frame.draw(quad,
+                           NoIndices(PrimitiveType::TrianglesList),
+                           pass.shader.get_prog(),
+                           &uniforms,
+                           &glium::DrawParameters { viewport: Some(viewport), .. Default::default() }).unwrap();
+            } else {
+                let target = self.targets.iter()
+                    .position(|targets| targets.dimensions == output_dimensions)
+                    .unwrap();
+                //never write into the texture the pass is reading from
+                let slot = match input {
+                    PassInput::Intermediate(t, 0) if t == target => 1,
+                    _ => 0
+                };
+
+                let mut framebuffer = SimpleFrameBuffer::new(display,
+                                                             &self.targets[target].textures[slot]).unwrap();
+                framebuffer.draw(quad,
+                                 NoIndices(PrimitiveType::TrianglesList),
+                                 pass.shader.get_prog(),
+                                 &uniforms,
+                                 &Default::default()).unwrap();
+
+                input = PassInput::Intermediate(target, slot);
+                input_dimensions = output_dimensions;
+            }
+        }
+    }
+}
+
+fn full_rect(dimensions: (u32, u32)) -> glium::Rect {
+    glium::Rect { left: 0, bottom: 0, width: dimensions.0, height: dimensions.1 }
+}
+
+fn blit_target(rect: glium::Rect) -> glium::BlitTarget {
+    glium::BlitTarget { left: rect.left, bottom: rect.bottom, width: rect.width as i32, height: rect.height as i32 }
+}
@@ -0,0 +1,100 @@
+use glium::draw_parameters::{BackfaceCullingMode, DepthTest};
+
+//faces are wound counter clockwise when seen from the front
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum CullMode {
+    Back,
+    Front,
+    //thin geometry like leaves or flags that has to be visible from both sides
+    TwoSided
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point
+}
+
+//the fixed function state a mesh is drawn with, blending is handled by the render queues
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct RenderState {
+    pub cull: CullMode,
+    pub polygon_mode: PolygonMode,
+    pub depth_test: DepthTest
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState {
+            cull: CullMode::Back,
+            polygon_mode: PolygonMode::Fill,
+            depth_test: DepthTest::IfLess
+        }
+    }
+}
+
+impl RenderState {
+    //wireframe replaces filled polygons for the whole scene but keeps points as they are
+    pub fn draw_parameters<'a>(&self, base: &glium::DrawParameters<'a>, wireframe: bool) -> glium::DrawParameters<'a> {
+        let mut params = base.clone();
+        params.depth.test = self.depth_test;
+        params.backface_culling = match self.cull {
+            CullMode::Back => BackfaceCullingMode::CullCounterClockwise,
+            CullMode::Front => BackfaceCullingMode::CullClockwise,
+            CullMode::TwoSided => BackfaceCullingMode::CullingDisabled
+        };
+        params.polygon_mode = match (self.polygon_mode, wireframe) {
+            (PolygonMode::Point, _) => glium::draw_parameters::PolygonMode::Point,
+            (PolygonMode::Line, _) | (PolygonMode::Fill, true) => glium::draw_parameters::PolygonMode::Line,
+            (PolygonMode::Fill, false) => glium::draw_parameters::PolygonMode::Fill
+        };
+        params
+    }
+}
+
+//lets a mesh change single parts of the state its material asks for
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct RenderStateOverride {
+    pub cull: Option<CullMode>,
+    pub polygon_mode: Option<PolygonMode>,
+    pub depth_test: Option<DepthTest>
+}
+
+impl RenderStateOverride {
+    pub fn apply(&self, state: RenderState) -> RenderState {
+        RenderState {
+            cull: self.cull.unwrap_or(state.cull),
+            polygon_mode: self.polygon_mode.unwrap_or(state.polygon_mode),
+            depth_test: self.depth_test.unwrap_or(state.depth_test)
+        }
+    }
+}
+
+#[test]
+fn render_state_test() {
+    let material_state = RenderState { cull: CullMode::TwoSided, .. Default::default() };
+    let mesh_override = RenderStateOverride { polygon_mode: Some(PolygonMode::Point), .. Default::default() };
+    let state = mesh_override.apply(material_state);
+    assert_eq!(state, RenderState { cull: CullMode::TwoSided, polygon_mode: PolygonMode::Point, depth_test: DepthTest::IfLess });
+
+    let params = RenderState::default().draw_parameters(&Default::default(), true);
+    assert_eq!(params.polygon_mode, glium::draw_parameters::PolygonMode::Line);
+    assert_eq!(params.backface_culling, BackfaceCullingMode::CullCounterClockwise);
+    let params = state.draw_parameters(&Default::default(), true);
+    assert_eq!(params.polygon_mode, glium::draw_parameters::PolygonMode::Point);
+    assert_eq!(params.backface_culling, BackfaceCullingMode::CullingDisabled);
+}
@@ -19,8 +19,8 @@ impl ShaderProg {
         pathstr_frag.push_str(".frag");
         let path_frag = std::path::Path::new(&pathstr_frag);
 
-        let vert_src : String = std::fs::read_to_string(path_vert).unwrap();
-        let frag_src : String = std::fs::read_to_string(path_frag).unwrap();
+        let vert_src : String = read_source(path_vert);
+        let frag_src : String = read_source(path_frag);
 
         let program = Program::from_source(display, &vert_src, &frag_src, None).unwrap();
         
@@ -41,8 +41,8 @@ impl ShaderProg {
         pathstr_frag.push_str(".frag");
         let path_frag = std::path::Path::new(&pathstr_frag);
         
-        let vert_src : String = std::fs::read_to_string(path_vert).unwrap();
-        let frag_src : String = std::fs::read_to_string(path_frag).unwrap();
+        let vert_src : String = read_source(path_vert);
+        let frag_src : String = read_source(path_frag);
 
         let program = Program::from_source(display, &vert_src, &frag_src, None).unwrap();
         
@@ -56,3 +56,70 @@ impl ShaderProg {
         &self.prog
     }
 }
+
+//loads a program that is used internally by the renderer and not selected by id
+pub fn load_program(vert_name: &str, frag_name: &str, display: &Display) -> Program {
+    let mut pathstr_vert = std::string::String::new();
+    pathstr_vert.push_str("./resources/shaders/");
+    pathstr_vert.push_str(vert_name);
+    let path_vert = std::path::Path::new(&pathstr_vert);
+
+    let mut pathstr_frag = std::string::String::new();
+    pathstr_frag.push_str("./resources/shaders/");
+    pathstr_frag.push_str(frag_name);
+    let path_frag = std::path::Path::new(&pathstr_frag);
+
+    let vert_src : String = read_source(path_vert);
+    let frag_src : String = read_source(path_frag);
+
+    Program::from_source(display, &vert_src, &frag_src, None).unwrap()
+}
+
+/* reads a shader and pastes the files named by its #include "name" lines in their place. the
+ * names are relative to the shader folder and the included files can't include anything themselves */
+fn read_source(path: &std::path::Path) -> String {
+    let source = std::fs::read_to_string(path).unwrap();
+    let mut res = String::new();
+    for line in source.lines() {
+        match include_name(line) {
+            Some(name) => {
+                let mut pathstr = std::string::String::new();
+                pathstr.push_str("./resources/shaders/");
+                pathstr.push_str(name);
+                res.push_str(&std::fs::read_to_string(std::path::Path::new(&pathstr)).unwrap());
+            }
+            None => res.push_str(line)
+        }
+        res.push('\n');
+    }
+    res
+}
+
+fn include_name(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("#include")
+        .map(|rest| rest.trim())
+        .and_then(|rest| rest.strip_prefix('"'))
+        .and_then(|rest| rest.strip_suffix('"'))
+}
+
+#[test]
+fn include_name_test() {
+    assert_eq!(include_name("#include \"fog.glsl\""), Some("fog.glsl"));
+    assert_eq!(include_name("  #include  \"fog.glsl\"  "), Some("fog.glsl"));
+    assert_eq!(include_name("#include fog.glsl"), None);
+    assert_eq!(include_name("uniform vec3 camera_pos;"), None);
+}
+
+#[test]
+fn read_source_test() {
+    //every shader that uses the fog gets its function pasted in
+    let source = read_source(std::path::Path::new("./resources/shaders/shader1.frag"));
+    assert!(!source.contains("#include"));
+    assert!(source.contains("float fog_visibility(float dist, float rise)"));
+
+    //the mesh shaders share their vertex stage
+    let source = read_source(std::path::Path::new("./resources/shaders/shader2.vert"));
+    assert!(source.starts_with("#version 140"));
+    assert!(source.contains("mat3 cofactors(mat3 m)"));
+}
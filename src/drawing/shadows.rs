@@ -0,0 +1,327 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{DepthFormat, DepthTexture2dArray, MipmapsOption};
+use glium::uniforms::UniformBuffer;
+use glium::{Display, Program, Surface};
+use glium::uniform;
+
+use crate::event_handling::camera_transformations::Camera;
+use crate::event_handling::camera_transformations::linalg;
+use super::lighting::{Light, LightKind, MAX_LIGHTS};
+use super::material::{BlendMode, MaterialConfig, MaterialLibrary};
+use super::mesh::Mesh;
+use super::shader_compilation;
+use super::texture::Texture;
+
+//has to match the array size of the shadow matrix block in the shaders
+pub const MAX_SHADOW_MAPS: usize = 8;
+pub const CASCADE_COUNT: usize = 3;
+
+//how far behind a cascade casters are still caught
+const CASTER_MARGIN: f32 = 50.0;
+const SPOT_NEAR: f32 = 0.1;
+
+pub type ShadowMatrices = [[[f32; 4]; 4]; MAX_SHADOW_MAPS];
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct ShadowSettings {
+    //distance from the camera up to which directional lights cast shadows
+    pub distance: f32,
+    pub resolution: u32,
+    //blend between uniform (0.0) and logarithmic (1.0) cascade splits
+    pub cascade_lambda: f32
+}
+
+impl Default for ShadowSettings {
+    fn default() -> ShadowSettings {
+        ShadowSettings {
+            distance: 50.0,
+            resolution: 2048,
+            cascade_lambda: 0.5
+        }
+    }
+}
+
+//which layers of the shadow map array belong to which light
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+struct ShadowAssignment {
+    light: usize,
+    first_layer: usize,
+    layer_count: usize
+}
+
+fn assign_layers(lights: &[Light]) -> Vec<ShadowAssignment> {
+    let mut res : Vec<ShadowAssignment> = Vec::new();
+    let mut next_layer : usize = 0;
+
+    for (i, light) in lights.iter().enumerate().take(MAX_LIGHTS) {
+        if !light.cast_shadows {
+            continue;
+        }
+
+        let layer_count = match light.kind {
+            LightKind::Directional => CASCADE_COUNT,
+            LightKind::Spot => 1,
+            LightKind::Point => {
+                println!("point light {} can not cast shadows yet!", i);
+                continue;
+            }
+        };
+
+        if next_layer + layer_count > MAX_SHADOW_MAPS {
+            println!("no shadow maps left for light {}!", i);
+            continue;
+        }
+
+        res.push(ShadowAssignment { light: i, first_layer: next_layer, layer_count });
+        next_layer += layer_count;
+    }
+
+    res
+}
+
+pub fn cascade_splits(near: f32, far: f32, lambda: f32) -> [f32; CASCADE_COUNT] {
+    let mut res : [f32; CASCADE_COUNT] = [far; CASCADE_COUNT];
+    for (i, split) in res.iter_mut().enumerate() {
+        let p = (i + 1) as f32 / CASCADE_COUNT as f32;
+        let log_split = near * (far / near).powf(p);
+        let uniform_split = near + (far - near) * p;
+        *split = lambda * log_split + (1.0 - lambda) * uniform_split;
+    }
+    res
+}
+
+fn light_up(direction: [f32; 3]) -> [f32; 3] {
+    if linalg::norm(direction)[1].abs() > 0.99 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    }
+}
+
+//fits an orthographic projection around the bounding sphere of a slice of the camera frustum
+fn cascade_matrix(camera: &Camera, light: &Light, near: f32, far: f32, resolution: u32) -> [[f32; 4]; 4] {
+    let center = linalg::add(camera.position, linalg::scale(camera.front, 0.5 * (near + far)));
+
+    let mut radius : f32 = 0.0;
+    for depth in [near, far] {
+        let half_height = camera.half_view_height(depth);
+        let half_width = half_height * camera.view_aspect_ratio;
+        let slice_center = linalg::add(camera.position, linalg::scale(camera.front, depth));
+        for (sx, sy) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+            let corner = linalg::add(slice_center,
+                                     linalg::add(linalg::scale(camera.right, sx * half_width),
+                                                 linalg::scale(camera.up, sy * half_height)));
+            radius = radius.max(linalg::mag(linalg::sub(corner, center)));
+        }
+    }
+
+    //snapping the center to whole texels keeps the shadow edges from shimmering while moving
+    let rotation = linalg::view_matrix([0.0; 3], light.direction, light_up(light.direction));
+    let light_center = linalg::mat4mulvec4(rotation, [center[0], center[1], center[2], 1.0]);
+    let texel = 2.0 * radius / resolution as f32;
+    let cx = (light_center[0] / texel).floor() * texel;
+    let cy = (light_center[1] / texel).floor() * texel;
+
+    let projection = linalg::orthographic(cx - radius, cx + radius,
+                                          cy - radius, cy + radius,
+                                          light_center[2] - radius - CASTER_MARGIN,
+                                          light_center[2] + radius);
+    linalg::mat4mul(projection, rotation)
+}
+
+fn spot_matrix(light: &Light, far: f32) -> [[f32; 4]; 4] {
+    let view = linalg::view_matrix(light.position, light.direction, light_up(light.direction));
+    let fov = (2.0 * light.outer_angle).to_radians().min(3.0);
+    linalg::mat4mul(linalg::perspective(fov, 1.0, SPOT_NEAR, far), view)
+}
+
+pub struct ShadowMaps {
+    settings: ShadowSettings,
+    assignments: Vec<ShadowAssignment>,
+    maps: DepthTexture2dArray,
+    matrix_buffer: UniformBuffer<ShadowMatrices>,
+    info_buffer: UniformBuffer<[[f32; 4]; MAX_LIGHTS]>,
+    cascade_splits: [f32; 4],
+    depth_prog: Program
+}
+
+impl ShadowMaps {
+    pub fn new(display: &Display, lights: &[Light], settings: ShadowSettings) -> ShadowMaps {
+        let assignments = assign_layers(lights);
+        let layer_count = assignments.iter().map(|a| a.layer_count).sum::<usize>().max(1);
+        //without any shadow casters a tiny map is still needed to have something bound to the sampler
+        let resolution = if assignments.is_empty() { 1 } else { settings.resolution };
+
+        let maps = DepthTexture2dArray::empty_with_format(display,
+                                                          DepthFormat::I24,
+                                                          MipmapsOption::NoMipmap,
+                                                          resolution,
+                                                          resolution,
+                                                          layer_count as u32).unwrap();
+
+        //x: first layer (negative without shadows), y: number of layers
+        let mut info : [[f32; 4]; MAX_LIGHTS] = [[-1.0, 0.0, 0.0, 0.0]; MAX_LIGHTS];
+        for assignment in &assignments {
+            info[assignment.light] = [assignment.first_layer as f32, assignment.layer_count as f32, 0.0, 0.0];
+        }
+
+        let depth_prog = shader_compilation::load_program("shadow.vert", "shadow.frag", display);
+
+        ShadowMaps {
+            settings,
+            assignments,
+            maps,
+            matrix_buffer: UniformBuffer::new(display, [[[0.0; 4]; 4]; MAX_SHADOW_MAPS]).unwrap(),
+            info_buffer: UniformBuffer::new(display, info).unwrap(),
+            cascade_splits: [0.0; 4],
+            depth_prog
+        }
+    }
+
+    fn compute_matrices(&mut self, camera: &Camera, lights: &[Light]) -> ShadowMatrices {
+        let mut matrices : ShadowMatrices = [[[0.0; 4]; 4]; MAX_SHADOW_MAPS];
+        let splits = cascade_splits(camera.near, self.settings.distance, self.settings.cascade_lambda);
+        self.cascade_splits = [splits[0], splits[1], splits[2], self.settings.distance];
+
+        for assignment in &self.assignments {
+            let light = &lights[assignment.light];
+            match light.kind {
+                LightKind::Directional => {
+                    let mut near = camera.near;
+                    for (cascade, far) in splits.iter().enumerate() {
+                        matrices[assignment.first_layer + cascade] =
+                            cascade_matrix(camera, light, near, *far, self.settings.resolution);
+                        near = *far;
+                    }
+                },
+                _ => {
+                    matrices[assignment.first_layer] = spot_matrix(light, self.settings.distance);
+                }
+            }
+        }
+
+        matrices
+    }
+
+    pub fn render(&mut self,
+                  display: &Display,
+                  meshes: &[&Mesh],
+                  camera: &Camera,
+                  lights: &[Light],
+                  materials: &MaterialLibrary,
+                  textures: &[&Texture]) {
+        if self.assignments.is_empty() {
+            return;
+        }
+
+        let matrices = self.compute_matrices(camera, lights);
+        self.matrix_buffer.write(&matrices);
+
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+
+        let layer_count = self.assignments.iter().map(|a| a.layer_count).sum::<usize>();
+        for (layer, light_matrix) in matrices.iter().enumerate().take(layer_count) {
+            let mut framebuffer = SimpleFrameBuffer::depth_only(
+                display,
+                self.maps.main_level().layer(layer as u32).unwrap()
+            ).unwrap();
+            framebuffer.clear_depth(1.0);
+
+            for mesh in meshes.iter().filter(|mesh| mesh.cast_shadows) {
+                let material = materials.bind(mesh.material_id);
+                //cutout meshes throw the shadow of their albedo map, materials without one use the mesh texture
+                let alpha_map = if material.has_albedo_map {
+                    material.albedo_map
+                } else {
+                    super::mesh_texture(textures, mesh).get_texture()
+                };
+                if let (Some(buf), Some(instances)) = (mesh.current_vertex_buf(), &mesh.instance_buf) {
+                    framebuffer.draw((buf, instances.per_instance().unwrap()),
+                                     NoIndices(PrimitiveType::TrianglesList),
+                                     &self.depth_prog,
+                                     &uniform! {
+                                         light_matrix: *light_matrix,
+                                         trans_mat: mesh.transform_mat,
+                                         offset: mesh.offset,
+                                         scale: mesh.scale,
+                                         alpha_map: alpha_map,
+                                         alpha_threshold: shadow_alpha_threshold(material.config)
+                                     },
+                                     &params).unwrap();
+                }
+            }
+        }
+    }
+
+    pub fn get_maps(&self) -> &DepthTexture2dArray {
+        &self.maps
+    }
+
+    pub fn get_matrix_buffer(&self) -> &UniformBuffer<ShadowMatrices> {
+        &self.matrix_buffer
+    }
+
+    pub fn get_info_buffer(&self) -> &UniformBuffer<[[f32; 4]; MAX_LIGHTS]> {
+        &self.info_buffer
+    }
+
+    pub fn get_cascade_splits(&self) -> [f32; 4] {
+        self.cascade_splits
+    }
+}
+
+/* texels of the alpha map below this don't cast a shadow. it's the cutoff of the mesh shaders with
+ * the alpha of the material moved over to the other side, nothing is cut out of meshes that aren't cutouts */
+fn shadow_alpha_threshold(config: &MaterialConfig) -> f32 {
+    match config.blend_mode {
+        BlendMode::Cutout if config.alpha_cutoff > 0.0 => {
+            if config.base_color[3] > 0.0 { config.alpha_cutoff / config.base_color[3] } else { f32::INFINITY }
+        },
+        BlendMode::Cutout | BlendMode::Opaque | BlendMode::Blended => 0.0
+    }
+}
+
+#[test]
+fn shadow_alpha_threshold_test() {
+    let cutout = MaterialConfig { blend_mode: BlendMode::Cutout, alpha_cutoff: 0.5, .. Default::default() };
+    assert_eq!(shadow_alpha_threshold(&cutout), 0.5);
+    //a half transparent material needs fully opaque texels to reach the cutoff
+    assert_eq!(shadow_alpha_threshold(&MaterialConfig { base_color: [1.0, 1.0, 1.0, 0.5], .. cutout.clone() }), 1.0);
+    assert_eq!(shadow_alpha_threshold(&MaterialConfig { base_color: [1.0, 1.0, 1.0, 0.0], .. cutout.clone() }), f32::INFINITY);
+    assert_eq!(shadow_alpha_threshold(&MaterialConfig { blend_mode: BlendMode::Opaque, .. cutout }), 0.0);
+}
+
+#[test]
+fn assign_layers_test() {
+    let sun = Light { kind: LightKind::Directional, cast_shadows: true, ..Default::default() };
+    let spot = Light { kind: LightKind::Spot, cast_shadows: true, ..Default::default() };
+    let point = Light { cast_shadows: true, ..Default::default() };
+
+    assert_eq!(assign_layers(&[Light::default(), sun.clone(), point, spot.clone()]), vec![
+        ShadowAssignment { light: 1, first_layer: 0, layer_count: CASCADE_COUNT },
+        ShadowAssignment { light: 3, first_layer: CASCADE_COUNT, layer_count: 1 }
+    ]);
+    //the third sun doesn't fit anymore but the spot light after it still does
+    assert_eq!(assign_layers(&[sun.clone(), sun.clone(), sun, spot]).len(), 3);
+}
+
+#[test]
+fn cascade_splits_test() {
+    let splits = cascade_splits(0.5, 50.0, 0.5);
+    assert!(splits[0] < splits[1] && splits[1] < splits[2]);
+    assert!((splits[2] - 50.0).abs() < 1e-3);
+    assert!((cascade_splits(1.0, 4.0, 0.0)[0] - 2.0).abs() < 1e-5);
+}
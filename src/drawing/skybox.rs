@@ -0,0 +1,75 @@
+use glium::index::{PrimitiveType, NoIndices};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::uniform;
+
+use crate::event_handling::camera_transformations::Camera;
+use crate::event_handling::camera_transformations::linalg;
+use super::SpriteVertex;
+use super::environment::Environment;
+use super::fog::FogSettings;
+use super::shader_compilation;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct SkyboxSettings {
+    //scales the environment so ldr images can be brightened in the hdr target
+    pub intensity: f32
+}
+
+impl Default for SkyboxSettings {
+    fn default() -> SkyboxSettings {
+        SkyboxSettings {
+            intensity: 1.0
+        }
+    }
+}
+
+//draws the environment cubemap behind everything else
+pub struct Skybox {
+    settings: SkyboxSettings,
+    prog: Program
+}
+
+impl Skybox {
+    pub fn new(display: &Display, settings: SkyboxSettings) -> Skybox {
+        Skybox {
+            settings,
+            prog: shader_compilation::load_program("skybox.vert", "skybox.frag", display)
+        }
+    }
+
+    //has to run after the opaque meshes, only pixels still at the far plane are covered
+    pub fn render<S: Surface>(&self,
+                              target: &mut S,
+                              quad: &VertexBuffer<SpriteVertex>,
+                              camera: &Camera,
+                              environment: &Environment,
+                              fog: &FogSettings) {
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: camera.depth_test(glium::draw_parameters::DepthTest::IfLessOrEqual),
+                write: false,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+        let (fog_color_mode, fog_params, fog_height) = fog.pack();
+
+        target.draw(quad,
+                    NoIndices(PrimitiveType::TrianglesList),
+                    &self.prog,
+                    &uniform! {
+                        view_matrix: camera.view_matrix(),
+                        inverse_projection: linalg::inverse4(camera.projection_matrix()),
+                        far_ndc: 2.0 * camera.clear_depth() - 1.0,
+                        environment_map: environment.cubemap_sampler(),
+                        intensity: self.settings.intensity,
+                        camera_pos: camera.position,
+                        fog_color_mode: fog_color_mode,
+                        fog_params: fog_params,
+                        fog_height: fog_height
+                    },
+                    &params).unwrap();
+    }
+}
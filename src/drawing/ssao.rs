@@ -0,0 +1,190 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{DepthTexture2d, MipmapsOption, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction,
+                       UniformBuffer};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::uniform;
+
+use crate::event_handling::camera_transformations::Camera;
+use crate::event_handling::camera_transformations::linalg;
+use super::SpriteVertex;
+use super::shader_compilation;
+
+//size of the kernel block in the shader
+pub const MAX_SSAO_SAMPLES: usize = 64;
+
+type Kernel = [[f32; 4]; MAX_SSAO_SAMPLES];
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct SsaoSettings {
+    //world space distance around a surface that is searched for occluders
+    pub radius: f32,
+    //depth difference below which a sample doesn't count, against self occlusion on flat surfaces
+    pub bias: f32,
+    //how dark fully occluded spots get, one makes them black
+    pub intensity: f32,
+    //number of kernel samples per pixel, at most MAX_SSAO_SAMPLES
+    pub samples: u32,
+    //texels the bilateral blur reaches to each side
+    pub blur_radius: u32
+}
+
+impl Default for SsaoSettings {
+    fn default() -> SsaoSettings {
+        SsaoSettings {
+            radius: 0.5,
+            bias: 0.025,
+            intensity: 1.0,
+            samples: 16,
+            blur_radius: 4
+        }
+    }
+}
+
+/* points in the unit hemisphere around +z, spread by a golden angle spiral so no random numbers are
+ * needed. they get denser towards the center since close occluders matter the most */
+fn kernel(samples: u32) -> Kernel {
+    let mut res : Kernel = [[0.0; 4]; MAX_SSAO_SAMPLES];
+    let count = (samples as usize).min(MAX_SSAO_SAMPLES);
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+
+    for (i, sample) in res.iter_mut().take(count).enumerate() {
+        let t = (i as f32 + 0.5) / count as f32;
+        let cos_theta = 1.0 - t;
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = golden_angle * i as f32;
+        let length = 0.1 + 0.9 * t * t;
+        *sample = [length * sin_theta * phi.cos(), length * sin_theta * phi.sin(), length * cos_theta, 0.0];
+    }
+    res
+}
+
+fn nearest(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled()
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(MinifySamplerFilter::Nearest)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
+
+/* ambient occlusion from the depth buffer, blurred along both axes. the forward shaders and the ambient
+ * pass of deferred shading sample it for their ambient term */
+pub struct Ssao {
+    settings: SsaoSettings,
+    occlusion_prog: Program,
+    blur_prog: Program,
+    kernel: UniformBuffer<Kernel>,
+    dimensions: (u32, u32),
+    //the raw occlusion and the blurred one, the blur goes back and forth between them
+    targets: Vec<Texture2d>
+}
+
+impl Ssao {
+    pub fn new(display: &Display, settings: SsaoSettings) -> Ssao {
+        Ssao {
+            settings,
+            occlusion_prog: shader_compilation::load_program("shaderpp.vert", "ssao.frag", display),
+            blur_prog: shader_compilation::load_program("shaderpp.vert", "ssao_blur.frag", display),
+            kernel: UniformBuffer::new(display, kernel(settings.samples)).unwrap(),
+            dimensions: (0, 0),
+            targets: Vec::new()
+        }
+    }
+
+    fn update_targets(&mut self, display: &Display, dimensions: (u32, u32)) {
+        if dimensions != self.dimensions {
+            self.targets = (0..2)
+                .map(|_| Texture2d::empty_with_format(display,
+                                                      UncompressedFloatFormat::U8,
+                                                      MipmapsOption::NoMipmap,
+                                                      dimensions.0,
+                                                      dimensions.1).unwrap())
+                .collect();
+            self.dimensions = dimensions;
+        }
+    }
+
+    /* the occlusion around every pixel of depth. normals are the world space normals of the g-buffer
+     * if there is one, otherwise they are reconstructed from neighbouring depths */
+    pub fn compute(&mut self,
+                   display: &Display,
+                   quad: &VertexBuffer<SpriteVertex>,
+                   depth: &DepthTexture2d,
+                   normals: Option<&Texture2d>,
+                   camera: &Camera) {
+        let dimensions = depth.dimensions();
+        self.update_targets(display, dimensions);
+        let texel = [1.0 / dimensions.0 as f32, 1.0 / dimensions.1 as f32];
+        let projection_matrix = camera.projection_matrix();
+        let inverse_projection = linalg::inverse4(projection_matrix);
+        let depth_sampler = depth.sampled()
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .minify_filter(MinifySamplerFilter::Nearest)
+            .wrap_function(SamplerWrapFunction::Clamp);
+
+        let mut framebuffer = SimpleFrameBuffer::new(display, &self.targets[0]).unwrap();
+        framebuffer.draw(quad,
+                         NoIndices(PrimitiveType::TrianglesList),
+                         &self.occlusion_prog,
+                         &uniform! {
+                             depth_sampler: depth_sampler,
+                             //unused without a g-buffer, any texture that isn't drawn to keeps the sampler bound
+                             normal_sampler: nearest(normals.unwrap_or(&self.targets[1])),
+                             has_normals: normals.is_some(),
+                             source_texel: texel,
+                             view_matrix: camera.view_matrix(),
+                             projection_matrix: projection_matrix,
+                             inverse_projection: inverse_projection,
+                             clear_depth: camera.clear_depth(),
+                             SsaoKernelBlock: &self.kernel,
+                             sample_count: self.settings.samples.min(MAX_SSAO_SAMPLES as u32) as i32,
+                             radius: self.settings.radius,
+                             bias: self.settings.bias,
+                             intensity: self.settings.intensity
+                         },
+                         &Default::default()).unwrap();
+
+        //horizontal into the second target, vertical back into the first
+        for (source, target, direction) in [(0, 1, [texel[0], 0.0]), (1, 0, [0.0, texel[1]])] {
+            let mut framebuffer = SimpleFrameBuffer::new(display, &self.targets[target]).unwrap();
+            framebuffer.draw(quad,
+                             NoIndices(PrimitiveType::TrianglesList),
+                             &self.blur_prog,
+                             &uniform! {
+                                 ao_sampler: nearest(&self.targets[source]),
+                                 depth_sampler: depth_sampler,
+                                 direction: direction,
+                                 blur_radius: self.settings.blur_radius as i32,
+                                 inverse_projection: inverse_projection,
+                                 clear_depth: camera.clear_depth()
+                             },
+                             &Default::default()).unwrap();
+        }
+    }
+
+    //the blurred occlusion of the last compute, none before the first one
+    pub fn get_occlusion(&self) -> Option<&Texture2d> {
+        self.targets.first()
+    }
+}
+
+#[test]
+fn kernel_test() {
+    let samples = kernel(16);
+    let length = |sample: [f32; 4]| (sample[0] * sample[0] + sample[1] * sample[1] + sample[2] * sample[2]).sqrt();
+    for (i, sample) in samples.iter().enumerate() {
+        if i < 16 {
+            //inside the hemisphere in front of the surface
+            assert!(sample[2] > 0.0);
+            assert!(length(*sample) > 0.0 && length(*sample) <= 1.0);
+        } else {
+            assert_eq!(length(*sample), 0.0);
+        }
+    }
+    //the later samples reach further out
+    assert!(length(samples[0]) < length(samples[15]));
+    assert_eq!(kernel(1000).iter().filter(|sample| sample[2] > 0.0).count(), MAX_SSAO_SAMPLES);
+}
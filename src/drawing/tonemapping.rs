@@ -0,0 +1,203 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{PrimitiveType, NoIndices};
+use glium::texture::{MipmapsOption, UncompressedFloatFormat};
+use glium::texture::texture2d::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler};
+use glium::{Display, Program, Surface, VertexBuffer};
+use glium::uniform;
+
+use super::SpriteVertex;
+use super::shader_compilation;
+
+//the scene is downsampled to this size before averaging, a power of two so the
+//last mip level is a single texel
+const LUMINANCE_SIZE: u32 = 256;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum ToneMapOperator {
+    Reinhard,
+    Aces
+}
+
+impl ToneMapOperator {
+    //has to match the operator switch in tonemap.frag
+    fn index(&self) -> i32 {
+        match self {
+            ToneMapOperator::Reinhard => 0,
+            ToneMapOperator::Aces => 1
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct ToneMapSettings {
+    pub operator: ToneMapOperator,
+    pub exposure: f32,
+    //scales the exposure so the average luminance of the scene ends up at key
+    pub auto_exposure: bool,
+    pub key: f32,
+    //how fast the eye adapts to a new average luminance, in 1/seconds
+    pub adaptation_speed: f32
+}
+
+impl Default for ToneMapSettings {
+    fn default() -> ToneMapSettings {
+        ToneMapSettings {
+            operator: ToneMapOperator::Reinhard,
+            exposure: 1.0,
+            auto_exposure: false,
+            key: 0.18,
+            adaptation_speed: 1.5
+        }
+    }
+}
+
+//maps the floating point scene color to a displayable image before the postprocessing chain runs
+pub struct ToneMapper {
+    settings: ToneMapSettings,
+    tonemap_prog: Program,
+    luminance_prog: Program,
+    adapt_prog: Program,
+    output: Texture2d,
+    //log luminance of the scene with a full mip chain
+    luminance: Texture2d,
+    //1x1 targets alternating between the previous and the current adapted luminance
+    adapted: [Texture2d; 2],
+    current_adapted: usize,
+    last_time: Option<f32>
+}
+
+impl ToneMapper {
+    pub fn new(display: &Display, settings: ToneMapSettings, dimensions: (u32, u32)) -> ToneMapper {
+        let luminance = Texture2d::empty_with_format(display,
+                                                     UncompressedFloatFormat::F16,
+                                                     MipmapsOption::EmptyMipmaps,
+                                                     LUMINANCE_SIZE,
+                                                     LUMINANCE_SIZE).unwrap();
+        let adapted_texture = || Texture2d::empty_with_format(display,
+                                                              UncompressedFloatFormat::F32,
+                                                              MipmapsOption::NoMipmap,
+                                                              1,
+                                                              1).unwrap();
+
+        let mut tone_mapper = ToneMapper {
+            settings,
+            tonemap_prog: shader_compilation::load_program("shaderpp.vert", "tonemap.frag", display),
+            luminance_prog: shader_compilation::load_program("shaderpp.vert", "luminance.frag", display),
+            adapt_prog: shader_compilation::load_program("shaderpp.vert", "adapt_luminance.frag", display),
+            output: Texture2d::empty(display, dimensions.0, dimensions.1).unwrap(),
+            luminance,
+            adapted: [adapted_texture(), adapted_texture()],
+            current_adapted: 0,
+            last_time: None
+        };
+        tone_mapper.reset_adaptation();
+        tone_mapper
+    }
+
+    fn update_output(&mut self, display: &Display, dimensions: (u32, u32)) {
+        if self.output.dimensions() != dimensions {
+            self.output = Texture2d::empty(display, dimensions.0, dimensions.1).unwrap();
+            self.reset_adaptation();
+        }
+    }
+
+    /* empty textures hold whatever the driver left in the memory, which could be nan and would never
+     * adapt away. both start at a luminance of 1, a log luminance of 0, and the next frame adapts fully */
+    fn reset_adaptation(&mut self) {
+        for adapted in self.adapted.iter() {
+            adapted.as_surface().clear_color(0.0, 0.0, 0.0, 1.0);
+        }
+        self.last_time = None;
+    }
+
+    //averages the log luminance of the scene and moves the adapted value towards it
+    fn adapt(&mut self, display: &Display, quad: &VertexBuffer<SpriteVertex>, scene_color: &Texture2d, time: f32) {
+        let mut luminance_buffer = SimpleFrameBuffer::new(display, &self.luminance).unwrap();
+        luminance_buffer.draw(quad,
+                              NoIndices(PrimitiveType::TrianglesList),
+                              &self.luminance_prog,
+                              &uniform! {
+                                  color_sampler: scene_color.sampled()
+                                      .minify_filter(MinifySamplerFilter::Linear)
+                              },
+                              &Default::default()).unwrap();
+        unsafe {
+            self.luminance.generate_mipmaps();
+        }
+
+        //the first frame starts out fully adapted
+        let rate = match self.last_time {
+            Some(last_time) => 1.0 - (-(time - last_time).max(0.0) * self.settings.adaptation_speed).exp(),
+            None => 1.0
+        };
+        self.last_time = Some(time);
+
+        let previous = self.current_adapted;
+        let next = 1 - previous;
+        let average_level = (self.luminance.get_mipmap_levels() - 1) as f32;
+        let luminance_sampler = Sampler::new(&self.luminance)
+            .minify_filter(MinifySamplerFilter::NearestMipmapNearest)
+            .magnify_filter(MagnifySamplerFilter::Nearest);
+
+        let mut adapted_buffer = SimpleFrameBuffer::new(display, &self.adapted[next]).unwrap();
+        adapted_buffer.draw(quad,
+                            NoIndices(PrimitiveType::TrianglesList),
+                            &self.adapt_prog,
+                            &uniform! {
+                                luminance_sampler: luminance_sampler,
+                                average_level: average_level,
+                                previous_sampler: self.adapted[previous].sampled()
+                                    .magnify_filter(MagnifySamplerFilter::Nearest),
+                                rate: rate
+                            },
+                            &Default::default()).unwrap();
+        self.current_adapted = next;
+    }
+
+    //returns the tone mapped image, gamma encoded for display.
+    //the bloom texture is added to the scene with the given intensity first
+    pub fn render(&mut self,
+                  display: &Display,
+                  quad: &VertexBuffer<SpriteVertex>,
+                  scene_color: &Texture2d,
+                  bloom: Option<(&Texture2d, f32)>,
+                  time: f32) -> &Texture2d {
+        self.update_output(display, scene_color.dimensions());
+
+        if self.settings.auto_exposure {
+            self.adapt(display, quad, scene_color, time);
+        }
+
+        //without bloom the scene itself is bound but not added
+        let (bloom_texture, bloom_intensity) = bloom.unwrap_or((scene_color, 0.0));
+
+        let mut framebuffer = SimpleFrameBuffer::new(display, &self.output).unwrap();
+        framebuffer.draw(quad,
+                         NoIndices(PrimitiveType::TrianglesList),
+                         &self.tonemap_prog,
+                         &uniform! {
+                             color_sampler: scene_color.sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Nearest)
+                                 .minify_filter(MinifySamplerFilter::Nearest),
+                             adapted_sampler: self.adapted[self.current_adapted].sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Nearest),
+                             bloom_sampler: bloom_texture.sampled()
+                                 .magnify_filter(MagnifySamplerFilter::Linear)
+                                 .minify_filter(MinifySamplerFilter::Linear),
+                             bloom_intensity: bloom_intensity,
+                             tone_operator: self.settings.operator.index(),
+                             exposure: self.settings.exposure,
+                             auto_exposure: self.settings.auto_exposure,
+                             key: self.settings.key
+                         },
+                         &Default::default()).unwrap();
+
+        &self.output
+    }
+}
@@ -3,10 +3,37 @@ use glium::glutin::event as ev;
 use std::collections::HashSet;
 use std::collections::hash_map::RandomState;
 
+pub mod camera_path;
 pub mod camera_transformations;
+pub mod fixed_timestep;
+
+//a press and release further apart than this many pixels is a drag and doesn't pick
+const CLICK_TOLERANCE: f64 = 4.0;
 
 pub struct Params {
-    pub quit: bool
+    pub quit: bool,
+    //toggled with F1, draws the whole scene as lines
+    pub wireframe: bool,
+    //window position of the last left click, taken by the main loop to pick a mesh
+    pub pick: Option<(f64, f64)>,
+    //index of the mesh hit by the last pick
+    pub selected: Option<usize>,
+    //set by F, the main loop fits the selected mesh into the view
+    pub frame_selected: bool
+}
+
+//input collected between two frames
+#[derive(Default)]
+struct FrameInput {
+    mouse_move: Option<(f64, f64)>,
+    //scroll wheel steps since the last frame, positive away from the user
+    scroll: f32,
+    //set by F2, switches the camera between perspective and orthographic
+    toggle_projection: bool,
+    //set by F3, switches the camera between flying and orbiting
+    toggle_controller: bool,
+    //set by P, starts the next camera path or stops the one playing
+    toggle_playback: bool
 }
 
 pub enum ModelType {
@@ -14,9 +41,34 @@ pub enum ModelType {
 }
 
 impl ModelType {
+    fn resize(&mut self, dimensions: (u32, u32)) {
+        match self {
+            ModelType::Camera(camera) => camera.set_window_size(dimensions)
+        }
+    }
+
+    fn follow(&mut self, position: [f32; 3], direction: [f32; 3]) {
+        match self {
+            ModelType::Camera(camera) => camera.place(position, direction)
+        }
+    }
+
+    fn frame(&mut self, center: [f32; 3], radius: f32) {
+        match self {
+            ModelType::Camera(camera) => {
+                camera.apply_frame_movement(vec![camera_transformations::CameraMovement::Frame(center, radius)]);
+            }
+        }
+    }
+
+    /* held keys move the model in every simulation step, the mouse, the scroll wheel and toggles are
+     * applied once per frame since they aren't rates */
     fn apply_inputs(&mut self,
                     keypresses: &HashSet<ev::VirtualKeyCode, RandomState>,
-                    mouse_move: &Option<(f64, f64)>) {
+                    mouse_buttons: &HashSet<ev::MouseButton, RandomState>,
+                    input: &FrameInput,
+                    steps: u32,
+                    step: f32) {
         match self {
             ModelType::Camera(camera) => {
                 let mut  movements : Vec<camera_transformations::CameraMovement> = Vec::new();
@@ -56,15 +108,43 @@ impl ModelType {
                     }
                 }
 
-                match *mouse_move {
-                    Some((dx, dy)) => {
-                        movements.push(camera_transformations::CameraMovement::RotateDir(dx, dy));
+                let mut frame_movements : Vec<camera_transformations::CameraMovement> = Vec::new();
+                //flying always looks around with the mouse, orbiting only while a button is dragged
+                match (input.mouse_move, camera.controller) {
+                    (Some((dx, dy)), camera_transformations::Controller::FreeFly) => {
+                        frame_movements.push(camera_transformations::CameraMovement::RotateDir(dx, dy));
+                    },
+                    (Some((dx, dy)), camera_transformations::Controller::Orbit) => {
+                        if mouse_buttons.contains(&ev::MouseButton::Left) {
+                            frame_movements.push(camera_transformations::CameraMovement::RotateDir(dx, dy));
                         }
-                    None => {}
+                        if mouse_buttons.contains(&ev::MouseButton::Middle) {
+                            frame_movements.push(camera_transformations::CameraMovement::Pan(dx, dy));
+                        }
+                    },
+                    (None, _) => {}
+                }
+
+                if input.scroll != 0.0 {
+                    frame_movements.push(camera_transformations::CameraMovement::Zoom(input.scroll));
                 }
-                
-                if !movements.is_empty() {
-                    camera.apply_movement(movements);
+                if input.toggle_projection {
+                    frame_movements.push(camera_transformations::CameraMovement::ToggleProjection);
+                }
+                if input.toggle_controller {
+                    frame_movements.push(camera_transformations::CameraMovement::ToggleController);
+                }
+
+                if !frame_movements.is_empty() {
+                    camera.apply_frame_movement(frame_movements);
+                }
+
+                //every step starts over from where the last one ended, even without movement
+                for _ in 0..steps {
+                    camera.begin_step();
+                    if !movements.is_empty() {
+                        camera.apply_movement(movements.clone(), step);
+                    }
                 }
             }
         }
@@ -75,22 +155,46 @@ pub struct EventHandler {
     models: Vec<ModelType>,
     pub params: Params,
     keypresses: HashSet<ev::VirtualKeyCode, RandomState>,
-    mouse_move: Option<(f64, f64)> 
+    mouse_buttons: HashSet<ev::MouseButton, RandomState>,
+    input: FrameInput,
+    cursor_position: (f64, f64),
+    //where the left button went down, to tell clicks from drags
+    click_start: Option<(f64, f64)>,
+    camera_paths: Vec<camera_path::CameraPath>,
+    //the path being played and the time since it started, manual input is ignored meanwhile
+    playback: Option<(usize, f32)>,
+    //path the next press of P plays
+    next_path: usize,
+    timestep: fixed_timestep::FixedTimestep
 }
 
 impl EventHandler {
     pub fn new() -> EventHandler {
         EventHandler {
             models: Vec::new(),
-            params: Params { quit: false },
+            params: Params { quit: false, wireframe: false, pick: None, selected: None, frame_selected: false },
             keypresses: HashSet::new(),
-            mouse_move: None
+            mouse_buttons: HashSet::new(),
+            input: FrameInput::default(),
+            cursor_position: (0.0, 0.0),
+            click_start: None,
+            camera_paths: Vec::new(),
+            playback: None,
+            next_path: 0,
+            timestep: fixed_timestep::FixedTimestep::new(fixed_timestep::DEFAULT_STEP)
         }
     }
 
     pub fn add_model(&mut self, model: ModelType) {
         self.models.push(model);
     }
+
+    //the first path marked for autoplay starts right away
+    pub fn set_camera_paths(&mut self, paths: Vec<camera_path::CameraPath>) {
+        self.playback = paths.iter().position(|path| path.autoplay).map(|index| (index, 0.0));
+        self.next_path = self.playback.map(|(index, _)| index + 1).unwrap_or(0);
+        self.camera_paths = paths;
+    }
     
     pub fn register_window_event(&mut self, event: ev::WindowEvent) {
         match event {
@@ -107,6 +211,32 @@ impl EventHandler {
                         if keycode == ev::VirtualKeyCode::Escape {
                             self.params.quit = true;
                         }
+                        //only the first press counts, not the repeats while the key is held
+                        if keycode == ev::VirtualKeyCode::F1
+                            && keyboard_input.state == ev::ElementState::Pressed
+                            && !self.keypresses.contains(&keycode) {
+                            self.params.wireframe = !self.params.wireframe;
+                        }
+                        if keycode == ev::VirtualKeyCode::F2
+                            && keyboard_input.state == ev::ElementState::Pressed
+                            && !self.keypresses.contains(&keycode) {
+                            self.input.toggle_projection = !self.input.toggle_projection;
+                        }
+                        if keycode == ev::VirtualKeyCode::F3
+                            && keyboard_input.state == ev::ElementState::Pressed
+                            && !self.keypresses.contains(&keycode) {
+                            self.input.toggle_controller = !self.input.toggle_controller;
+                        }
+                        if keycode == ev::VirtualKeyCode::F
+                            && keyboard_input.state == ev::ElementState::Pressed
+                            && !self.keypresses.contains(&keycode) {
+                            self.params.frame_selected = true;
+                        }
+                        if keycode == ev::VirtualKeyCode::P
+                            && keyboard_input.state == ev::ElementState::Pressed
+                            && !self.keypresses.contains(&keycode) {
+                            self.input.toggle_playback = !self.input.toggle_playback;
+                        }
                         match keyboard_input.state {
                             ev::ElementState::Pressed => self.keypresses.insert(keycode),
                             ev::ElementState::Released => self.keypresses.remove(&keycode),
@@ -115,24 +245,113 @@ impl EventHandler {
                     None => {}
                 }
             },
+            //the scale factor changes the size in pixels as well, if it comes without a resize
+            ev::WindowEvent::Resized(size) | ev::WindowEvent::ScaleFactorChanged { new_inner_size: &mut size, .. } => {
+                for model in &mut self.models {
+                    model.resize((size.width, size.height));
+                }
+            },
+            ev::WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = (position.x, position.y);
+            },
+            ev::WindowEvent::MouseWheel { delta, .. } => {
+                //touchpads scroll in pixels, count roughly one line height as a step
+                self.input.scroll += match delta {
+                    ev::MouseScrollDelta::LineDelta(_, y) => y,
+                    ev::MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0
+                };
+            },
+            ev::WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ev::ElementState::Pressed => {
+                        if button == ev::MouseButton::Left {
+                            self.click_start = Some(self.cursor_position);
+                        }
+                        self.mouse_buttons.insert(button);
+                    },
+                    ev::ElementState::Released => {
+                        //only a click picks, dragging to orbit keeps the selection
+                        if button == ev::MouseButton::Left {
+                            if let Some(start) = self.click_start.take() {
+                                let (dx, dy) = (self.cursor_position.0 - start.0, self.cursor_position.1 - start.1);
+                                if dx * dx + dy * dy <= CLICK_TOLERANCE * CLICK_TOLERANCE {
+                                    self.params.pick = Some(self.cursor_position);
+                                }
+                            }
+                        }
+                        self.mouse_buttons.remove(&button);
+                    }
+                }
+            },
             _ => {}
         }
     }
 
     pub fn register_device_event(&mut self, event: ev::DeviceEvent) {
         match event {
+            //there can be several motion events per frame
             ev::DeviceEvent::MouseMotion{delta: (dx, dy)} => {
-                self.mouse_move = Some((dx, dy));
+                let (x, y) = self.input.mouse_move.unwrap_or((0.0, 0.0));
+                self.input.mouse_move = Some((x + dx, y + dy));
             },
             _ => {}
         }
     }
 
-    pub fn modify_models(&mut self) {
-        for i in 0..self.models.len() {
-            self.models[i].apply_inputs(&self.keypresses, &self.mouse_move);
+    //dt is the time in seconds since the last frame
+    pub fn modify_models(&mut self, dt: f32) {
+        if self.input.toggle_playback {
+            self.playback = match self.playback {
+                Some(_) => None,
+                None if self.camera_paths.is_empty() => None,
+                None => {
+                    let index = self.next_path % self.camera_paths.len();
+                    self.next_path = index + 1;
+                    Some((index, 0.0))
+                }
+            };
+        }
+
+        let steps = self.timestep.advance(dt);
+        match self.playback {
+            //a path is played in real time, the fixed steps only matter for manual movement
+            Some((index, time)) => {
+                let time = time + dt;
+                let path = &self.camera_paths[index];
+                let (position, direction) = path.sample(time);
+                for model in &mut self.models {
+                    model.follow(position, direction);
+                }
+                self.playback = if path.is_over(time) { None } else { Some((index, time)) };
+            },
+            None => {
+                for i in 0..self.models.len() {
+                    self.models[i].apply_inputs(&self.keypresses,
+                                                &self.mouse_buttons,
+                                                &self.input,
+                                                steps,
+                                                self.timestep.get_step());
+                }
+            }
         }
-        self.mouse_move = None;
+        self.input = FrameInput::default();
+    }
+
+    //fits a bounding sphere into the view of the camera
+    pub fn frame(&mut self, center: [f32; 3], radius: f32) {
+        for model in &mut self.models {
+            model.frame(center, radius);
+        }
+    }
+
+    //how far rendering is between the last two simulation steps
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.timestep.alpha()
+    }
+
+    //the camera as it should be drawn this frame, between its last two simulation steps
+    pub fn get_render_camera(&self) -> Option<camera_transformations::Camera> {
+        self.get_camera().map(|camera| camera.interpolated(self.interpolation_alpha()))
     }
 
     pub fn get_camera(&self) -> Option<&camera_transformations::Camera> {
@@ -0,0 +1,157 @@
+use super::camera_transformations::linalg;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Keyframe {
+    //seconds since the start of the path
+    pub time: f32,
+    pub position: [f32; 3],
+    //normalized viewing direction
+    pub direction: [f32; 3]
+}
+
+/* a camera flight through keyframes. positions follow a catmull-rom spline through all of them,
+ * the viewing direction turns between neighbouring keyframes at a constant rate */
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    //starts over after the last keyframe instead of stopping
+    pub looping: bool,
+    //played as soon as the scene is loaded
+    pub autoplay: bool
+}
+
+impl CameraPath {
+    //there has to be at least one keyframe
+    pub fn new(mut keyframes: Vec<Keyframe>, looping: bool, autoplay: bool) -> CameraPath {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        CameraPath {
+            keyframes,
+            looping,
+            autoplay
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map(|last| last.time).unwrap_or(0.0)
+    }
+
+    pub fn is_over(&self, time: f32) -> bool {
+        !self.looping && time >= self.duration()
+    }
+
+    //position and viewing direction at a time since the start of the playback
+    pub fn sample(&self, time: f32) -> ([f32; 3], [f32; 3]) {
+        let first = self.keyframes[0];
+        let span = self.duration() - first.time;
+        let time = if self.looping && span > 0.0 && time > self.duration() {
+            first.time + (time - first.time).rem_euclid(span)
+        } else {
+            time
+        };
+
+        //the keyframe the time has passed last, the spline runs from it to the next one
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return (first.position, first.direction);
+        }
+        if next == self.keyframes.len() {
+            let last = self.keyframes[next - 1];
+            return (last.position, last.direction);
+        }
+
+        let (from, to) = (self.keyframes[next - 1], self.keyframes[next]);
+        let t = (time - from.time) / (to.time - from.time);
+        //the ends repeat their keyframe in place of the missing neighbour
+        let before = self.keyframes[next.saturating_sub(2)].position;
+        let after = self.keyframes[(next + 1).min(self.keyframes.len() - 1)].position;
+
+        (catmull_rom(before, from.position, to.position, after, t), slerp(from.direction, to.direction, t))
+    }
+}
+
+//the spline segment between p1 and p2 that also passes through p0 and p3
+fn catmull_rom(p0: [f32; 3], p1: [f32; 3], p2: [f32; 3], p3: [f32; 3], t: f32) -> [f32; 3] {
+    let (t2, t3) = (t * t, t * t * t);
+    let mut res = [0.0; 3];
+    for (axis, value) in res.iter_mut().enumerate() {
+        *value = 0.5 * (2.0 * p1[axis]
+                        + (p2[axis] - p0[axis]) * t
+                        + (2.0 * p0[axis] - 5.0 * p1[axis] + 4.0 * p2[axis] - p3[axis]) * t2
+                        + (3.0 * p1[axis] - p0[axis] - 3.0 * p2[axis] + p3[axis]) * t3);
+    }
+    res
+}
+
+/* spherical interpolation of two unit vectors, falls back to a normalized lerp when they are almost the same.
+ * opposite vectors have no plane to turn in, they turn around world up or around x when they point along it */
+fn slerp(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+    let cos_angle = linalg::dot(from, to).clamp(-1.0, 1.0);
+    let angle = cos_angle.acos();
+    if angle.sin().abs() < 1e-4 {
+        if cos_angle > 0.0 {
+            return linalg::norm(linalg::add(from, linalg::scale(linalg::sub(to, from), t)));
+        }
+        let axis = if linalg::mag(linalg::cross([0.0, 1.0, 0.0], from)) > 1e-2 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+        //the part of from that is perpendicular to the axis turns around it, the rest stays
+        let along = linalg::scale(axis, linalg::dot(from, axis));
+        let perpendicular = linalg::sub(from, along);
+        let turned = linalg::add(linalg::scale(perpendicular, (std::f32::consts::PI * t).cos()),
+                                 linalg::scale(linalg::cross(axis, perpendicular), (std::f32::consts::PI * t).sin()));
+        return linalg::norm(linalg::add(along, turned));
+    }
+    let weight_from = ((1.0 - t) * angle).sin() / angle.sin();
+    let weight_to = (t * angle).sin() / angle.sin();
+    linalg::norm(linalg::add(linalg::scale(from, weight_from), linalg::scale(to, weight_to)))
+}
+
+#[test]
+fn camera_path_test() {
+    let front = [0.0, 0.0, 1.0];
+    let right = [1.0, 0.0, 0.0];
+    let path = CameraPath::new(vec![Keyframe { time: 2.0, position: [2.0, 0.0, 0.0], direction: right },
+                                    Keyframe { time: 0.0, position: [0.0, 0.0, 0.0], direction: front },
+                                    Keyframe { time: 1.0, position: [1.0, 1.0, 0.0], direction: front }],
+                               false,
+                               false);
+
+    //the spline passes through every keyframe
+    assert_eq!(path.sample(0.0), ([0.0, 0.0, 0.0], front));
+    assert_eq!(path.sample(1.0), ([1.0, 1.0, 0.0], front));
+    assert_eq!(path.sample(5.0), ([2.0, 0.0, 0.0], right));
+    assert!(path.is_over(2.0) && !path.is_over(1.5));
+
+    //halfway through a quarter turn looks diagonally
+    let (_, direction) = path.sample(1.5);
+    let diagonal = 0.5f32.sqrt();
+    assert!(linalg::mag(linalg::sub(direction, [diagonal, 0.0, diagonal])) < 1e-5);
+
+    let looping = CameraPath { looping: true, .. path.clone() };
+    assert!(!looping.is_over(10.0));
+    assert_eq!(looping.sample(3.0), path.sample(1.0));
+}
+
+#[test]
+fn catmull_rom_test() {
+    //evenly spaced points on a line stay on it at an even pace
+    let point = catmull_rom([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [3.0, 0.0, 0.0], 0.25);
+    assert!(linalg::mag(linalg::sub(point, [1.25, 0.0, 0.0])) < 1e-6);
+}
+
+#[test]
+fn slerp_antipodal_test() {
+    //turning around halfway looks to the side instead of collapsing to a zero vector
+    let halfway = slerp([0.0, 0.0, 1.0], [0.0, 0.0, -1.0], 0.5);
+    assert!((linalg::mag(halfway) - 1.0).abs() < 1e-5);
+    assert!(linalg::dot(halfway, [0.0, 0.0, 1.0]).abs() < 1e-5);
+    assert!(linalg::mag(linalg::sub(slerp([0.0, 0.0, 1.0], [0.0, 0.0, -1.0], 1.0), [0.0, 0.0, -1.0])) < 1e-5);
+
+    //looking straight up the turn goes around x instead
+    let halfway = slerp([0.0, 1.0, 0.0], [0.0, -1.0, 0.0], 0.5);
+    assert!((linalg::mag(halfway) - 1.0).abs() < 1e-5);
+    assert!(linalg::dot(halfway, [0.0, 1.0, 0.0]).abs() < 1e-5);
+}
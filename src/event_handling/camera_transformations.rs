@@ -1,18 +1,130 @@
 use std::f32::consts::PI;
 
-mod linalg;
+use glium::draw_parameters::DepthTest;
 
+pub mod linalg;
+
+//an orthographic camera needs a finite depth range, this one is used if the scene sets no far plane
+pub const ORTHO_FAR: f32 = 1000.0;
+//how much one step of the scroll wheel changes the orthographic view height
+const ZOOM_STEP: f32 = 1.1;
+//looking straight up or down would leave no direction to the right of the camera
+const MAX_PITCH: f32 = 89.0 * PI / 180.0;
+//distance to the target when switching to orbiting without anything to look at
+const ORBIT_DISTANCE: f32 = 5.0;
+//part of the view height a panned unit of mouse movement moves the target
+const PAN_SPEED: f32 = 0.002;
+
+//viewing direction for yaw and pitch in radians, yaw turning right from +z and pitch up from the horizon
+pub fn angles_to_direction(yaw: f32, pitch: f32) -> [f32; 3] {
+    [pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos()]
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub enum Controller {
+    //wasd and the mouse move the camera itself
+    #[default]
+    FreeFly,
+    //the camera circles a target point, for looking at a single model
+    Orbit
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    //parallel view rays, for top down views and tools
+    Orthographic
+}
+
+//how the camera projects, read from the camera block of the scene
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct CameraSettings {
+    //vertical field of view in radians
+    pub fov: f32,
+    pub near: f32,
+    //None puts the far plane at infinity
+    pub far: Option<f32>,
+    //depth 1 at the near plane and 0 at the far plane, the depth tests are flipped to match
+    pub reversed_z: bool,
+    pub projection: Projection,
+    //world space height of the orthographic view, the width follows from the aspect ratio
+    pub ortho_height: f32,
+    //fixed width to height ratio of the image, the rest of the window is filled with black bars
+    pub letterbox: Option<f32>,
+    //degrees the camera turns per unit of mouse movement
+    pub sensitivity: f32,
+    //moving the mouse up looks down
+    pub invert_y: bool,
+    pub controller: Controller,
+    //point an orbiting camera starts out looking at
+    pub orbit_target: [f32; 3]
+}
+
+impl Default for CameraSettings {
+    fn default() -> CameraSettings {
+        CameraSettings {
+            fov: 1.0,
+            near: 0.5,
+            far: None,
+            reversed_z: false,
+            projection: Projection::Perspective,
+            ortho_height: 10.0,
+            letterbox: None,
+            sensitivity: 0.5,
+            invert_y: false,
+            controller: Controller::FreeFly,
+            orbit_target: [0.0, 0.0, 0.0]
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub position: [f32; 3],
+    //position before the last simulation step, rendering interpolates from there
+    previous_position: [f32; 3],
+    /* the basis is rebuilt from yaw and pitch after every rotation, so it stays orthonormal and
+     * never rolls. change the orientation with set_orientation, not through these */
     pub front: [f32; 3],
     pub up: [f32; 3],
     pub  right: [f32; 3],
+    //radians, turning right around the world's y axis starting from +z
+    yaw: f32,
+    //radians above the horizon, at most MAX_PITCH either way
+    pitch: f32,
     pub view_aspect_ratio: f32,
     pub fov: f32,
+    pub near: f32,
+    pub far: Option<f32>,
+    pub reversed_z: bool,
+    pub projection: Projection,
+    pub ortho_height: f32,
+    pub letterbox: Option<f32>,
+    pub controller: Controller,
+    orbit_target: [f32; 3],
+    orbit_distance: f32,
+    //units per second
     mov_speed: f32,
-    rot_speed: f32
+    //degrees per unit of mouse movement
+    rot_speed: f32,
+    invert_y: bool,
+    //degrees per second while an arrow key is held
+    key_rot_speed: f32
 }
 
+#[derive(Clone)]
 #[derive(Debug)]
 pub enum CameraMovement {
     MovLeft,
@@ -25,27 +137,239 @@ pub enum CameraMovement {
     RotRight,
     RotUp,
     RotDown,
-    RotateDir(f64, f64)
+    RotateDir(f64, f64),
+    //moves an orbiting camera and its target sideways, by mouse movement
+    Pan(f64, f64),
+    //steps of the scroll wheel, positive zooms in
+    Zoom(f32),
+    ToggleProjection,
+    ToggleController,
+    //center and radius of a bounding sphere to fit into the view
+    Frame([f32; 3], f32)
 }
 
 impl Default for Camera {
     fn default() -> Camera {
-        let asp : f32 = 16.0 / 9.0;
+        Camera::new(CameraSettings::default())
+    }
+}
+
+impl Camera {
+    pub fn new(settings: CameraSettings) -> Camera {
+        //replaced by the window's ratio as soon as the window size is known
+        let asp : f32 = settings.letterbox.unwrap_or(16.0 / 9.0);
+        let position = match settings.controller {
+            Controller::FreeFly => [0.0, 0.0, -1.0],
+            Controller::Orbit => linalg::sub(settings.orbit_target, [0.0, 0.0, ORBIT_DISTANCE])
+        };
         Camera {
-            position: [0.0, 0.0, -1.0],
+            position,
+            previous_position: position,
             front: [0.0, 0.0, 1.0],
             up: [0.0, 1.0, 0.0],
             right: [1.0, 0.0, 0.0],
+            yaw: 0.0,
+            pitch: 0.0,
             view_aspect_ratio: asp,
-            fov: 1.0,
-            mov_speed: 0.02,
-            rot_speed: 0.5
+            fov: settings.fov,
+            near: settings.near,
+            far: settings.far,
+            reversed_z: settings.reversed_z,
+            projection: settings.projection,
+            ortho_height: settings.ortho_height,
+            letterbox: settings.letterbox,
+            controller: settings.controller,
+            orbit_target: settings.orbit_target,
+            orbit_distance: ORBIT_DISTANCE,
+            mov_speed: 1.2,
+            rot_speed: settings.sensitivity,
+            invert_y: settings.invert_y,
+            key_rot_speed: 30.0
         }
     }
-}
 
-impl Camera {
-    pub fn apply_movement(&mut self, actions: Vec<CameraMovement>) {
+    //world space into the camera basis, x to the right, y up and z along front
+    pub fn view_matrix(&self) -> [[f32; 4]; 4] {
+        let (r, u, f) = (self.right, self.up, self.front);
+        [
+            [r[0], u[0], f[0], 0.0],
+            [r[1], u[1], f[1], 0.0],
+            [r[2], u[2], f[2], 0.0],
+            [-linalg::dot(r, self.position), -linalg::dot(u, self.position), -linalg::dot(f, self.position), 1.0]
+        ]
+    }
+
+    /* the usual opengl depth range from -1 at the near plane to 1 at the far plane, turned around
+     * for reversed z. opengl keeps the [-1, 1] clip range, so the flipped range mostly helps
+     * depth comparisons against a far plane at infinity */
+    pub fn projection_matrix(&self) -> [[f32; 4]; 4] {
+        let mut res = match (self.projection, self.far) {
+            (Projection::Perspective, Some(far)) => linalg::perspective(self.fov, self.view_aspect_ratio, self.near, far),
+            (Projection::Perspective, None) => linalg::perspective_infinite(self.fov, self.view_aspect_ratio, self.near),
+            (Projection::Orthographic, _) => {
+                let half_height = 0.5 * self.ortho_height;
+                let half_width = half_height * self.view_aspect_ratio;
+                linalg::orthographic(-half_width, half_width, -half_height, half_height, self.near, self.ortho_far())
+            }
+        };
+        if self.reversed_z {
+            for col in res.iter_mut() {
+                col[2] = -col[2];
+            }
+        }
+        res
+    }
+
+    pub fn ortho_far(&self) -> f32 {
+        self.far.unwrap_or(ORTHO_FAR)
+    }
+
+    //half the height of what the camera sees at this distance in front of it
+    pub fn half_view_height(&self, distance: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective => distance * (0.5 * self.fov).tan(),
+            Projection::Orthographic => 0.5 * self.ortho_height
+        }
+    }
+
+    //follows the window size, minimized windows with a zero size keep the last ratio
+    pub fn set_window_size(&mut self, dimensions: (u32, u32)) {
+        if dimensions.0 > 0 && dimensions.1 > 0 {
+            self.view_aspect_ratio = self.letterbox.unwrap_or(dimensions.0 as f32 / dimensions.1 as f32);
+        }
+    }
+
+    //the part of the window the scene is drawn into, centered between black bars when letterboxed
+    pub fn viewport(&self, dimensions: (u32, u32)) -> glium::Rect {
+        let (width, height) = match self.letterbox {
+            Some(aspect) if (dimensions.0 as f32) > aspect * dimensions.1 as f32 => {
+                ((aspect * dimensions.1 as f32).round() as u32, dimensions.1)
+            },
+            Some(aspect) => (dimensions.0, (dimensions.0 as f32 / aspect).round() as u32),
+            None => dimensions
+        };
+        glium::Rect {
+            left: (dimensions.0 - width) / 2,
+            bottom: (dimensions.1 - height) / 2,
+            width,
+            height
+        }
+    }
+
+    /* a window position in pixels from the top left into normalized device coordinates of the
+     * viewport, None on the black bars around it */
+    pub fn window_to_ndc(&self, position: (f64, f64), dimensions: (u32, u32)) -> Option<[f32; 2]> {
+        let viewport = self.viewport(dimensions);
+        if viewport.width == 0 || viewport.height == 0 {
+            return None;
+        }
+        let x = (position.0 as f32 - viewport.left as f32) / viewport.width as f32;
+        let y = (dimensions.1 as f32 - position.1 as f32 - viewport.bottom as f32) / viewport.height as f32;
+        if (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y) {
+            Some([2.0 * x - 1.0, 2.0 * y - 1.0])
+        } else {
+            None
+        }
+    }
+
+    /* the ray through a point on screen given in normalized device coordinates, as origin and
+     * direction. perspective rays start at the camera, orthographic ones on the camera plane */
+    pub fn screen_ray(&self, ndc: [f32; 2]) -> ([f32; 3], [f32; 3]) {
+        let half_height = self.half_view_height(1.0);
+        let half_width = half_height * self.view_aspect_ratio;
+        let offset = linalg::add(linalg::scale(self.right, ndc[0] * half_width),
+                                 linalg::scale(self.up, ndc[1] * half_height));
+        match self.projection {
+            Projection::Perspective => (self.position, linalg::norm(linalg::add(self.front, offset))),
+            Projection::Orthographic => (linalg::add(self.position, offset), linalg::norm(self.front))
+        }
+    }
+
+    pub fn view_projection_matrix(&self) -> [[f32; 4]; 4] {
+        linalg::mat4mul(self.projection_matrix(), self.view_matrix())
+    }
+
+    //what the depth buffer is cleared to, the depth of the far plane
+    pub fn clear_depth(&self) -> f32 {
+        if self.reversed_z { 0.0 } else { 1.0 }
+    }
+
+    //depth tests are written for the normal depth range and flipped here for reversed z
+    pub fn depth_test(&self, test: DepthTest) -> DepthTest {
+        if !self.reversed_z {
+            return test;
+        }
+        match test {
+            DepthTest::IfLess => DepthTest::IfMore,
+            DepthTest::IfLessOrEqual => DepthTest::IfMoreOrEqual,
+            DepthTest::IfMore => DepthTest::IfLess,
+            DepthTest::IfMoreOrEqual => DepthTest::IfLessOrEqual,
+            DepthTest::Ignore | DepthTest::Overwrite | DepthTest::IfEqual | DepthTest::IfNotEqual => test
+        }
+    }
+
+    //yaw and pitch in radians, the pitch is clamped so the camera can't flip over
+    pub fn set_orientation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw.rem_euclid(2.0 * PI);
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self.front = angles_to_direction(self.yaw, self.pitch);
+        self.right = linalg::norm(linalg::cross([0.0, 1.0, 0.0], self.front));
+        self.up = linalg::cross(self.front, self.right);
+    }
+
+    //called before every simulation step so the step can be interpolated
+    pub fn begin_step(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    //the camera somewhere between the last two simulation steps, alpha 0 is the earlier one
+    pub fn interpolated(&self, alpha: f32) -> Camera {
+        let mut camera = self.clone();
+        camera.position = linalg::add(self.previous_position,
+                                      linalg::scale(linalg::sub(self.position, self.previous_position), alpha));
+        camera
+    }
+
+    /* puts the camera somewhere without gliding there, looking along direction. an orbiting
+     * camera circles the point in front of it afterwards */
+    pub fn place(&mut self, position: [f32; 3], direction: [f32; 3]) {
+        let direction = linalg::norm(direction);
+        self.set_orientation(direction[0].atan2(direction[2]), direction[1].clamp(-1.0, 1.0).asin());
+        self.position = position;
+        self.previous_position = position;
+        self.orbit_target = linalg::add(position, linalg::scale(self.front, self.orbit_distance));
+    }
+
+    /* applies input that happens once per frame instead of once per simulation step. jumps like
+     * framing a mesh move the interpolation along, so they don't glide there over a step */
+    pub fn apply_frame_movement(&mut self, actions: Vec<CameraMovement>) {
+        let before = self.position;
+        self.apply_movement(actions, 0.0);
+        self.previous_position = linalg::add(self.previous_position, linalg::sub(self.position, before));
+    }
+
+    //moves the camera so the sphere fills the view, looking at it from the current direction
+    fn frame(&mut self, center: [f32; 3], radius: f32) {
+        let radius = radius.max(0.01);
+        self.orbit_target = center;
+        self.orbit_distance = match self.projection {
+            Projection::Perspective => {
+                let half_fov = 0.5 * self.fov;
+                let half_fov = half_fov.min((half_fov.tan() * self.view_aspect_ratio).atan());
+                radius / half_fov.sin()
+            },
+            Projection::Orthographic => {
+                self.ortho_height = 2.0 * radius * (1.0 / self.view_aspect_ratio).max(1.0);
+                2.0 * radius
+            }
+        }.max(radius + self.near);
+        self.position = linalg::sub(center, linalg::scale(self.front, self.orbit_distance));
+    }
+
+    /* dt is the time in seconds the movement lasts, it scales held keys but not the mouse, whose
+     * movement is already a distance. an orbiting camera ignores the movement keys and the rotations
+     * turn it around its target */
+    pub fn apply_movement(&mut self, actions: Vec<CameraMovement>, dt: f32) {
         let mut mov_dir : [f32; 3] = [0.0, 0.0, 0.0];
         for action in actions.iter() {
             match action {
@@ -80,56 +404,224 @@ impl Camera {
                                mov_dir[2] - self.up[2]];
                 },
                 CameraMovement::RotLeft => {
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat([0.0, 1.0, 0.0], self.rot_speed * (PI / 180.0));
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
+                    self.set_orientation(self.yaw - self.key_rot_speed * dt * (PI / 180.0), self.pitch);
                 },
                 CameraMovement::RotRight => {
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat([0.0, 1.0, 0.0], (-1.0) * self.rot_speed * (PI / 180.0));
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
+                    self.set_orientation(self.yaw + self.key_rot_speed * dt * (PI / 180.0), self.pitch);
                 },
                 CameraMovement::RotUp => {
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat(self.right, self.rot_speed * (PI / 180.0));
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
+                    self.set_orientation(self.yaw, self.pitch + self.key_rot_speed * dt * (PI / 180.0));
                 },
                 CameraMovement::RotDown => {
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat(self.right, (-1.0) * self.rot_speed * (PI / 180.0));
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
-                }
+                    self.set_orientation(self.yaw, self.pitch - self.key_rot_speed * dt * (PI / 180.0));
+                },
+                //the mouse moving down gives a positive dy
                 CameraMovement::RotateDir(dx, dy) => {
-                    let degree = (-1.0) * self.rot_speed * (PI / 180.0);
-
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat(self.right,  *dy as f32 * degree);
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
-
-                    let rotmat : [[f32; 3]; 3] =
-                        linalg::rotmat([0.0, 1.0, 0.0], *dx as f32 * degree);
-                    self.up = linalg::matmulvec3(rotmat, self.up);
-                    self.right = linalg::matmulvec3(rotmat, self.right);
-                    self.front = linalg::matmulvec3(rotmat, self.front);
+                    let degree = self.rot_speed * (PI / 180.0);
+                    let dy = if self.invert_y { -*dy } else { *dy };
+                    self.set_orientation(self.yaw + *dx as f32 * degree, self.pitch - dy as f32 * degree);
+                },
+                //the mouse moving right drags the scene along to the right
+                CameraMovement::Pan(dx, dy) => {
+                    let step = 2.0 * self.half_view_height(self.orbit_distance) * PAN_SPEED;
+                    let shift = linalg::add(linalg::scale(self.right, -(*dx as f32) * step),
+                                            linalg::scale(self.up, *dy as f32 * step));
+                    self.orbit_target = linalg::add(self.orbit_target, shift);
+                },
+                //an orthographic view only changes its size, a perspective orbit moves closer to the target
+                CameraMovement::Zoom(steps) => {
+                    match (self.projection, self.controller) {
+                        (Projection::Orthographic, _) => {
+                            self.ortho_height = (self.ortho_height * ZOOM_STEP.powf(-steps)).clamp(0.01, 10000.0);
+                        },
+                        (Projection::Perspective, Controller::Orbit) => {
+                            self.orbit_distance = (self.orbit_distance * ZOOM_STEP.powf(-steps)).clamp(0.01, 100000.0);
+                        },
+                        (Projection::Perspective, Controller::FreeFly) => {}
+                    }
+                },
+                CameraMovement::ToggleProjection => {
+                    self.projection = match self.projection {
+                        Projection::Perspective => Projection::Orthographic,
+                        Projection::Orthographic => Projection::Perspective
+                    };
+                },
+                //orbits around whatever is in front of the camera
+                CameraMovement::ToggleController => {
+                    self.controller = match self.controller {
+                        Controller::FreeFly => {
+                            self.orbit_distance = ORBIT_DISTANCE;
+                            self.orbit_target = linalg::add(self.position, linalg::scale(self.front, ORBIT_DISTANCE));
+                            Controller::Orbit
+                        },
+                        Controller::Orbit => Controller::FreeFly
+                    };
+                },
+                CameraMovement::Frame(center, radius) => {
+                    self.frame(*center, *radius);
                 }
             }
         }
 
-        mov_dir = linalg::norm(mov_dir);
-        
-        self.position = [self.position[0] + self.mov_speed * mov_dir[0],
-                         self.position[1] + self.mov_speed * mov_dir[1],
-                         self.position[2] + self.mov_speed * mov_dir[2]];
+        match self.controller {
+            Controller::FreeFly => {
+                mov_dir = linalg::norm(mov_dir);
+
+                let distance = self.mov_speed * dt;
+                self.position = [self.position[0] + distance * mov_dir[0],
+                                 self.position[1] + distance * mov_dir[1],
+                                 self.position[2] + distance * mov_dir[2]];
+            },
+            Controller::Orbit => {
+                self.position = linalg::sub(self.orbit_target, linalg::scale(self.front, self.orbit_distance));
+            }
+        }
+    }
+}
+
+#[test]
+fn camera_projection_test() {
+    let settings = CameraSettings { near: 0.5, far: Some(100.0), .. Default::default() };
+    let depth = |camera: &Camera, z: f32| {
+        let clip = linalg::mat4mulvec4(camera.projection_matrix(), [0.0, 0.0, z, 1.0]);
+        0.5 * clip[2] / clip[3] + 0.5
+    };
+
+    let camera = Camera::new(settings);
+    assert!(depth(&camera, 0.5).abs() < 1e-5);
+    assert!((depth(&camera, 100.0) - 1.0).abs() < 1e-5);
+    assert_eq!(camera.depth_test(DepthTest::IfLess), DepthTest::IfLess);
+
+    let reversed = Camera::new(CameraSettings { reversed_z: true, .. settings });
+    assert!((depth(&reversed, 0.5) - 1.0).abs() < 1e-5);
+    assert!(depth(&reversed, 100.0).abs() < 1e-5);
+    assert_eq!(reversed.depth_test(DepthTest::IfLess), DepthTest::IfMore);
+    assert_eq!(reversed.clear_depth(), 0.0);
+
+    //the camera sits at z = -1 looking along +z
+    assert_eq!(linalg::mat4mulvec4(camera.view_matrix(), [1.0, 2.0, 3.0, 1.0]), [1.0, 2.0, 4.0, 1.0]);
+}
+
+#[test]
+fn camera_orthographic_test() {
+    let mut camera = Camera::new(CameraSettings { projection: Projection::Orthographic, ortho_height: 4.0, .. Default::default() });
+    camera.view_aspect_ratio = 2.0;
+
+    //the corners of the view are ortho_height apart no matter how far away they are
+    for z in [1.0, 50.0] {
+        let clip = linalg::mat4mulvec4(camera.projection_matrix(), [4.0, 2.0, z, 1.0]);
+        assert!((clip[0] - 1.0).abs() < 1e-5 && (clip[1] - 1.0).abs() < 1e-5);
+    }
+
+    //rays through the top right corner are parallel and start on the camera plane
+    let (origin, direction) = camera.screen_ray([1.0, 1.0]);
+    assert_eq!(origin, [4.0, 2.0, -1.0]);
+    assert_eq!(direction, [0.0, 0.0, 1.0]);
+
+    camera.apply_movement(vec![CameraMovement::Zoom(1.0)], 0.0);
+    assert!(camera.ortho_height < 4.0);
+    camera.apply_movement(vec![CameraMovement::ToggleProjection], 0.0);
+    assert_eq!(camera.projection, Projection::Perspective);
+    let (origin, direction) = camera.screen_ray([0.0, 0.0]);
+    assert_eq!((origin, direction), ([0.0, 0.0, -1.0], [0.0, 0.0, 1.0]));
+}
+
+#[test]
+fn camera_viewport_test() {
+    let mut camera = Camera::default();
+    camera.set_window_size((800, 400));
+    assert_eq!(camera.view_aspect_ratio, 2.0);
+    //a minimized window doesn't change the ratio
+    camera.set_window_size((0, 0));
+    assert_eq!(camera.view_aspect_ratio, 2.0);
+    assert_eq!(camera.viewport((800, 400)), glium::Rect { left: 0, bottom: 0, width: 800, height: 400 });
+    assert_eq!(camera.window_to_ndc((800.0, 0.0), (800, 400)), Some([1.0, 1.0]));
+
+    let mut letterboxed = Camera::new(CameraSettings { letterbox: Some(1.0), .. Default::default() });
+    letterboxed.set_window_size((800, 400));
+    assert_eq!(letterboxed.view_aspect_ratio, 1.0);
+    assert_eq!(letterboxed.viewport((800, 400)), glium::Rect { left: 200, bottom: 0, width: 400, height: 400 });
+    assert_eq!(letterboxed.viewport((400, 800)), glium::Rect { left: 0, bottom: 200, width: 400, height: 400 });
+    assert_eq!(letterboxed.window_to_ndc((400.0, 200.0), (800, 400)), Some([0.0, 0.0]));
+    assert_eq!(letterboxed.window_to_ndc((100.0, 200.0), (800, 400)), None);
+}
+
+#[test]
+fn camera_delta_time_test() {
+    //the same distance in one long step or in many short ones
+    let mut camera = Camera::default();
+    camera.begin_step();
+    camera.apply_movement(vec![CameraMovement::MovForward], 1.0);
+    let mut stepped = Camera::default();
+    for _ in 0..10 {
+        stepped.begin_step();
+        stepped.apply_movement(vec![CameraMovement::MovForward], 0.1);
     }
+    assert!((camera.position[2] - stepped.position[2]).abs() < 1e-5);
+    assert!((camera.position[2] - (-1.0 + camera.mov_speed)).abs() < 1e-5);
+
+    assert_eq!(camera.interpolated(0.0).position, [0.0, 0.0, -1.0]);
+    assert!((camera.interpolated(0.5).position[2] - (-1.0 + 0.5 * camera.mov_speed)).abs() < 1e-5);
+    assert_eq!(camera.interpolated(1.0).position, camera.position);
+}
+
+#[test]
+fn camera_orientation_test() {
+    let mut camera = Camera::default();
+    //a long way past straight up stops just short of it
+    for _ in 0..1000 {
+        camera.apply_movement(vec![CameraMovement::RotateDir(3.0, -5.0)], 0.0);
+    }
+    assert_eq!(camera.pitch, MAX_PITCH);
+
+    //the basis stays orthonormal and the right vector level, so there is no roll
+    let length = |v: [f32; 3]| linalg::dot(v, v).sqrt();
+    assert!((length(camera.front) - 1.0).abs() < 1e-5);
+    assert!((length(camera.right) - 1.0).abs() < 1e-5);
+    assert!((length(camera.up) - 1.0).abs() < 1e-5);
+    assert!(linalg::dot(camera.front, camera.right).abs() < 1e-5);
+    assert!(linalg::dot(camera.front, camera.up).abs() < 1e-5);
+    assert!(camera.right[1].abs() < 1e-6);
+    assert!(camera.up[1] > 0.0);
+
+    //turning right from +z faces +x
+    camera.set_orientation(0.5 * PI, 0.0);
+    assert!(linalg::mag(linalg::sub(camera.front, [1.0, 0.0, 0.0])) < 1e-5);
+    assert!(linalg::mag(linalg::sub(camera.right, [0.0, 0.0, -1.0])) < 1e-5);
+
+    let mut inverted = Camera::new(CameraSettings { invert_y: true, .. Default::default() });
+    inverted.apply_movement(vec![CameraMovement::RotateDir(0.0, 10.0)], 0.0);
+    assert!(inverted.pitch > 0.0);
 }
 
+#[test]
+fn camera_orbit_test() {
+    let mut camera = Camera::new(CameraSettings { controller: Controller::Orbit, orbit_target: [1.0, 0.0, 0.0], .. Default::default() });
+    assert_eq!(camera.position, [1.0, 0.0, -ORBIT_DISTANCE]);
+
+    //circling keeps the distance and looks at the target, the movement keys do nothing
+    camera.apply_frame_movement(vec![CameraMovement::RotateDir(90.0, -30.0), CameraMovement::MovForward]);
+    let offset = linalg::sub(camera.orbit_target, camera.position);
+    assert!((linalg::mag(offset) - ORBIT_DISTANCE).abs() < 1e-4);
+    assert!(linalg::mag(linalg::sub(linalg::norm(offset), camera.front)) < 1e-5);
+
+    camera.apply_frame_movement(vec![CameraMovement::Zoom(2.0)]);
+    assert!(linalg::mag(linalg::sub(camera.orbit_target, camera.position)) < ORBIT_DISTANCE);
+
+    //panning moves camera and target together
+    let before = camera.orbit_target;
+    camera.apply_frame_movement(vec![CameraMovement::Pan(10.0, 0.0)]);
+    let shift = linalg::sub(camera.orbit_target, before);
+    assert!(linalg::dot(shift, camera.right) < 0.0);
+    assert!(linalg::dot(shift, camera.front).abs() < 1e-5);
+
+    //a unit sphere fits into the view and the interpolation jumps along
+    camera.apply_frame_movement(vec![CameraMovement::Frame([0.0, 10.0, 0.0], 1.0)]);
+    assert_eq!(camera.orbit_target, [0.0, 10.0, 0.0]);
+    let distance = linalg::mag(linalg::sub(camera.orbit_target, camera.position));
+    assert!((distance * (0.5 * camera.fov).sin() - 1.0).abs() < 1e-4);
+    assert_eq!(camera.interpolated(0.0).position, camera.position);
+
+    camera.apply_frame_movement(vec![CameraMovement::ToggleController]);
+    assert_eq!(camera.controller, Controller::FreeFly);
+}
@@ -11,7 +11,6 @@ pub fn norm(vec: [f32; 3]) -> [f32; 3] {
     }
 }
 
-#[allow(dead_code)]
 pub fn cross(vec0: [f32; 3], vec1: [f32; 3]) -> [f32; 3] {
     [
         vec0[1] * vec1[2] - vec0[2] * vec1[1],
@@ -20,35 +19,220 @@ pub fn cross(vec0: [f32; 3], vec1: [f32; 3]) -> [f32; 3] {
     ]
 }
 
-pub fn matmulvec3(mat: [[f32; 3]; 3], vec: [f32; 3]) -> [f32; 3] {
+pub fn dot(vec0: [f32; 3], vec1: [f32; 3]) -> f32 {
+    vec0[0] * vec1[0] + vec0[1] * vec1[1] + vec0[2] * vec1[2]
+}
+
+pub fn add(vec0: [f32; 3], vec1: [f32; 3]) -> [f32; 3] {
+    [ vec0[0] + vec1[0], vec0[1] + vec1[1], vec0[2] + vec1[2] ]
+}
+
+pub fn sub(vec0: [f32; 3], vec1: [f32; 3]) -> [f32; 3] {
+    [ vec0[0] - vec1[0], vec0[1] - vec1[1], vec0[2] - vec1[2] ]
+}
+
+pub fn scale(vec: [f32; 3], factor: f32) -> [f32; 3] {
+    [ factor * vec[0], factor * vec[1], factor * vec[2] ]
+}
+
+//all 4x4 matrices are stored column by column, the way the shaders expect them
+pub fn mat4mul(mat0: [[f32; 4]; 4], mat1: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut res : [[f32; 4]; 4] = [[0.0; 4]; 4];
+    for (col, res_col) in res.iter_mut().enumerate() {
+        for (row, entry) in res_col.iter_mut().enumerate() {
+            *entry = (0..4).map(|k| mat0[k][row] * mat1[col][k]).sum();
+        }
+    }
+    res
+}
+
+pub fn mat4mulvec4(mat: [[f32; 4]; 4], vec: [f32; 4]) -> [f32; 4] {
+    let mut res : [f32; 4] = [0.0; 4];
+    for (row, entry) in res.iter_mut().enumerate() {
+        *entry = (0..4).map(|k| mat[k][row] * vec[k]).sum();
+    }
+    res
+}
+
+pub fn identity4() -> [[f32; 4]; 4] {
     [
-        mat[0][0] * vec[0] + mat[0][1] * vec[1] + mat[0][2] * vec[2],
-        mat[1][0] * vec[0] + mat[1][1] * vec[1] + mat[1][2] * vec[2],
-        mat[2][0] * vec[0] + mat[2][1] * vec[1] + mat[2][2] * vec[2]           
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0]
     ]
 }
 
-pub fn rotmat(axis: [f32; 3], angle: f32) -> [[f32; 3]; 3] {
-    let norm : f32 = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
-    let vn : [f32; 3] = [axis[0] / norm, axis[1] / norm, axis[2] / norm];
-    let sinth : f32 = (0.5 * angle).sin();
-    let costh : f32 = (0.5 * angle).cos();
+/* scales uniformly, rotates around z, then x, then y by the angles in radians and translates by offset,
+ * the usual order for placing objects with a heading */
+pub fn model_matrix(offset: [f32; 3], rotation: [f32; 3], scale: f32) -> [[f32; 4]; 4] {
+    let (sx, cx) = rotation[0].sin_cos();
+    let (sy, cy) = rotation[1].sin_cos();
+    let (sz, cz) = rotation[2].sin_cos();
+    let rot_x = [[1.0, 0.0, 0.0, 0.0], [0.0, cx, sx, 0.0], [0.0, -sx, cx, 0.0], [0.0, 0.0, 0.0, 1.0]];
+    let rot_y = [[cy, 0.0, -sy, 0.0], [0.0, 1.0, 0.0, 0.0], [sy, 0.0, cy, 0.0], [0.0, 0.0, 0.0, 1.0]];
+    let rot_z = [[cz, sz, 0.0, 0.0], [-sz, cz, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+    let mut res = mat4mul(rot_y, mat4mul(rot_x, rot_z));
+    for col in res.iter_mut().take(3) {
+        for entry in col.iter_mut().take(3) {
+            *entry *= scale;
+        }
+    }
+    res[3] = [offset[0], offset[1], offset[2], 1.0];
+    res
+}
+
+//maps world space into a space with x to the right, y up and z along the viewing direction
+pub fn view_matrix(position: [f32; 3], front: [f32; 3], up: [f32; 3]) -> [[f32; 4]; 4] {
+    let f = norm(front);
+    let r = norm(cross(up, f));
+    let u = cross(f, r);
     [
-        [
-            1.0 - 2.0 * (vn[1] * vn[1] + vn[2] * vn[2]) * sinth * sinth,
-            2.0 * (vn[0] * vn[1] * sinth * sinth + vn[2] * sinth * costh),
-            2.0 * (vn[0] * vn[2] * sinth * sinth - vn[1] * sinth * costh)
-        ],
-        [
-            2.0 * (vn[0] * vn[1] * sinth * sinth - vn[2] * sinth * costh),
-            1.0 - 2.0 * (vn[0] * vn[0] + vn[2] * vn[2]) * sinth * sinth,
-            2.0 * (vn[1] * vn[2] * sinth * sinth + vn[0] * sinth * costh)
-        ],
-        [
-            2.0 * (vn[0] * vn[2] * sinth * sinth + vn[1] * sinth * costh),
-            2.0 * (vn[1] * vn[2] * sinth * sinth - vn[0] * sinth * costh),
-            1.0 - 2.0 * (vn[0] * vn[0] + vn[1] * vn[1]) * sinth * sinth
-        ]
-    ]    
+        [r[0], u[0], f[0], 0.0],
+        [r[1], u[1], f[1], 0.0],
+        [r[2], u[2], f[2], 0.0],
+        [-dot(r, position), -dot(u, position), -dot(f, position), 1.0]
+    ]
+}
+
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    [
+        [2.0 / (right - left), 0.0, 0.0, 0.0],
+        [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+        [0.0, 0.0, 2.0 / (far - near), 0.0],
+        [-(right + left) / (right - left), -(top + bottom) / (top - bottom), -(far + near) / (far - near), 1.0]
+    ]
+}
+
+pub fn perspective(fov: f32, aspect_ratio: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    let t = (0.5 * fov).tan();
+    [
+        [1.0 / (t * aspect_ratio), 0.0, 0.0, 0.0],
+        [0.0, 1.0 / t, 0.0, 0.0],
+        [0.0, 0.0, (far + near) / (far - near), 1.0],
+        [0.0, 0.0, -2.0 * far * near / (far - near), 0.0]
+    ]
 }
 
+//the limit of perspective for an infinitely far away far plane, which then lands exactly on depth 1
+pub fn perspective_infinite(fov: f32, aspect_ratio: f32, near: f32) -> [[f32; 4]; 4] {
+    let t = (0.5 * fov).tan();
+    [
+        [1.0 / (t * aspect_ratio), 0.0, 0.0, 0.0],
+        [0.0, 1.0 / t, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 1.0],
+        [0.0, 0.0, -2.0 * near, 0.0]
+    ]
+}
+
+/* general inverse through the cofactors, the identity for matrices that can't be inverted. there is
+ * no tolerance on the determinant, projections of large volumes have tiny ones but are fine to invert */
+pub fn inverse4(mat: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let m : Vec<f32> = mat.iter().flatten().cloned().collect();
+    let mut inv = [0.0f32; 16];
+
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+        + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+        - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+        + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+        - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+        - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+        + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+        - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+        + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+        + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+        - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+        + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+        - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+        - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+        + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+        - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+        + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det == 0.0 || !det.is_finite() {
+        return identity4();
+    }
+
+    let mut res : [[f32; 4]; 4] = [[0.0; 4]; 4];
+    for (i, entry) in inv.iter().enumerate() {
+        res[i / 4][i % 4] = entry / det;
+    }
+    if res.iter().flatten().any(|entry| !entry.is_finite()) {
+        return identity4();
+    }
+    res
+}
+
+#[test]
+fn model_matrix_test() {
+    let model = model_matrix([1.0, 2.0, 3.0], [0.0, 0.0, 0.0], 2.0);
+    assert_eq!(mat4mulvec4(model, [1.0, 1.0, 1.0, 1.0]), [3.0, 4.0, 5.0, 1.0]);
+
+    //a quarter turn around y takes +x to -z
+    let model = model_matrix([0.0, 0.0, 0.0], [0.0, 0.5 * std::f32::consts::PI, 0.0], 1.0);
+    let turned = mat4mulvec4(model, [1.0, 0.0, 0.0, 1.0]);
+    assert!((turned[0]).abs() < 1e-6 && (turned[2] + 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn view_matrix_test() {
+    let view = view_matrix([0.0, 0.0, -1.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]);
+    assert_eq!(mat4mulvec4(view, [1.0, 2.0, 3.0, 1.0]), [1.0, 2.0, 4.0, 1.0]);
+}
+
+#[test]
+fn projection_depth_range_test() {
+    let near_point = mat4mulvec4(perspective(1.0, 1.0, 0.5, 10.0), [0.0, 0.0, 0.5, 1.0]);
+    let far_point = mat4mulvec4(perspective(1.0, 1.0, 0.5, 10.0), [0.0, 0.0, 10.0, 1.0]);
+    assert!((near_point[2] / near_point[3] + 1.0).abs() < 1e-5);
+    assert!((far_point[2] / far_point[3] - 1.0).abs() < 1e-5);
+
+    let ortho = orthographic(-1.0, 1.0, -2.0, 2.0, 0.0, 4.0);
+    assert_eq!(mat4mulvec4(ortho, [1.0, -2.0, 4.0, 1.0]), [1.0, -1.0, 1.0, 1.0]);
+    assert_eq!(mat4mul(ortho, view_matrix([0.0; 3], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0])), ortho);
+}
+
+#[test]
+fn inverse_test() {
+    let mat = mat4mul(perspective(1.0, 1.5, 0.5, 100.0), view_matrix([1.0, 2.0, 3.0], [0.0, 0.6, 0.8], [0.0, 1.0, 0.0]));
+    let product = mat4mul(inverse4(mat), mat);
+    for (col, column) in product.iter().enumerate() {
+        for (row, entry) in column.iter().enumerate() {
+            let expected = if col == row { 1.0 } else { 0.0 };
+            assert!((entry - expected).abs() < 1e-4);
+        }
+    }
+
+    //points far away approach depth 1 without a far plane
+    let far_point = mat4mulvec4(perspective_infinite(1.0, 1.0, 0.5), [0.0, 0.0, 1.0e6, 1.0]);
+    assert!((far_point[2] / far_point[3] - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn inverse_large_volume_test() {
+    //the determinant of this one is far below f32::EPSILON
+    let projection = orthographic(-177.8, 177.8, -100.0, 100.0, 0.5, 1000.0);
+    let inverse = inverse4(projection);
+    assert_ne!(inverse, identity4());
+    let corner = mat4mulvec4(inverse, [1.0, 1.0, 1.0, 1.0]);
+    assert!((corner[0] - 177.8).abs() < 1e-2 && (corner[1] - 100.0).abs() < 1e-3 && (corner[2] - 1000.0).abs() < 1e-2);
+
+    assert_eq!(inverse4([[0.0; 4]; 4]), identity4());
+}
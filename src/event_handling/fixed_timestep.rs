@@ -0,0 +1,56 @@
+//simulation steps at 60 per second
+pub const DEFAULT_STEP: f32 = 1.0 / 60.0;
+//after a long stall the simulation gives up catching up instead of freezing the next frames as well
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/* runs the simulation in steps of the same length no matter how long the frames take. the time
+ * left over after the last whole step carries over into the next frame */
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> FixedTimestep {
+        FixedTimestep {
+            step,
+            accumulator: 0.0
+        }
+    }
+
+    pub fn get_step(&self) -> f32 {
+        self.step
+    }
+
+    //adds the duration of a frame and returns how many steps are due
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+        let steps = (self.accumulator / self.step).floor() as u32;
+        if steps > MAX_STEPS_PER_FRAME {
+            self.accumulator = 0.0;
+            return MAX_STEPS_PER_FRAME;
+        }
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    //how far the frame is into the next step, between 0 and 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[test]
+fn fixed_timestep_test() {
+    let mut timestep = FixedTimestep::new(0.25);
+    assert_eq!(timestep.advance(0.125), 0);
+    assert_eq!(timestep.alpha(), 0.5);
+    //the leftover of the first frame completes a step
+    assert_eq!(timestep.advance(0.25), 1);
+    assert_eq!(timestep.alpha(), 0.5);
+    assert_eq!(timestep.advance(0.625), 3);
+    assert_eq!(timestep.alpha(), 0.0);
+
+    assert_eq!(timestep.advance(100.0), MAX_STEPS_PER_FRAME);
+    assert_eq!(timestep.alpha(), 0.0);
+}
@@ -8,26 +8,41 @@ use glium::glutin;
 use glium::glutin::event as ev;
 use glium::glutin::event_loop as evl;
 
+use crate::drawing::antialiasing::{self, AntiAliasing};
+use crate::drawing::bloom::Bloom;
+use crate::drawing::culling;
+use crate::drawing::deferred::RenderPath;
+use crate::drawing::environment::Environment;
+use crate::drawing::lighting::{Light, LightSet};
+use crate::drawing::material::MaterialLibrary;
 use crate::drawing::mesh::Mesh;
-use crate::drawing::mesh;
+use crate::drawing::postprocessing::PostProcessChain;
+use crate::drawing::shadows::ShadowMaps;
+use crate::drawing::skybox::Skybox;
+use crate::drawing::ssao::Ssao;
 use crate::drawing::shader_compilation::ShaderProg;
 use crate::drawing::texture::Texture;
 
 mod drawing;
 mod event_handling;
+mod scene;
 
 struct Params {
     full_screen: bool,
-    post_pr_id: u16,
+    post_pr_ids: Vec<u16>,
     buffer_data: bool,
-    scene_file: Option<String>
+    scene_file: Option<String>,
+    anti_aliasing: Option<AntiAliasing>,
+    render_path: RenderPath
 }
 
 fn parse_params(params: Vec<String>) -> Result<Params, String> {
     let mut full_screen = false;
-    let mut post_pr_id : u16 = 0;
+    let mut post_pr_ids : Vec<u16> = Vec::new();
     let mut buffer_data = false;
     let mut scene_file : Option<String> = None;
+    let mut anti_aliasing : Option<AntiAliasing> = None;
+    let mut render_path = RenderPath::Forward;
 
     let mut param_iter = params.iter();
 
@@ -43,9 +58,12 @@ fn parse_params(params: Vec<String>) -> Result<Params, String> {
                 match param_iter.next() {
                     None => { return Err("No shader for postprocessing given.".to_owned()); },
                     Some(opt) => {
-                        match u16::from_str(opt.as_str()) {
-                            Ok(id) => { post_pr_id = id; },
-                            Err(_) => { return Err("Invalid shader for postprocessing given.".to_owned()); }
+                        //several passes can be chained by separating their ids with commas
+                        for id_str in opt.split(',') {
+                            match u16::from_str(id_str) {
+                                Ok(id) => { post_pr_ids.push(id); },
+                                Err(_) => { return Err("Invalid shader for postprocessing given.".to_owned()); }
+                            }
                         }
                     }
                 }
@@ -61,6 +79,25 @@ fn parse_params(params: Vec<String>) -> Result<Params, String> {
                     }
                 }
             },
+            "-a" => {
+                match param_iter.next() {
+                    None => { return Err("No anti-aliasing mode given.".to_owned()); },
+                    Some(mode) => {
+                        match AntiAliasing::parse(mode) {
+                            Some(mode) => { anti_aliasing = Some(mode); },
+                            None => { return Err("Invalid anti-aliasing mode given.".to_owned()); }
+                        }
+                    }
+                }
+            },
+            "-r" => {
+                match param_iter.next().map(|path| path.as_str()) {
+                    None => { return Err("No render path given.".to_owned()); },
+                    Some("forward") => { render_path = RenderPath::Forward; },
+                    Some("deferred") => { render_path = RenderPath::Deferred; },
+                    Some(_) => { return Err("Invalid render path given.".to_owned()); }
+                }
+            },
             ukwn => {
                 let error_message = "Invalid argument given: ".to_owned() + ukwn;
                 return Err(error_message);
@@ -70,17 +107,19 @@ fn parse_params(params: Vec<String>) -> Result<Params, String> {
 
     Ok(Params {
         full_screen: full_screen,
-        post_pr_id: post_pr_id,
+        post_pr_ids: post_pr_ids,
         buffer_data: buffer_data,
-        scene_file: scene_file
+        scene_file: scene_file,
+        anti_aliasing,
+        render_path
     })
 }
 
-fn event_handler_gen<T>(display: glium::Display, scene_file: &str, post_pr_id: u16) ->
+fn event_handler_gen<T>(display: glium::Display,
+                        conf: scene::SceneConfig,
+                        post_pr_ids: Vec<u16>,
+                        render_path: RenderPath) ->
 impl FnMut(ev::Event<'_, T>, &evl::EventLoopWindowTarget<T>, &mut evl::ControlFlow){
-    //load the scene
-    let conf = mesh::SceneConfig::load_scene_config(scene_file).unwrap();
-    
     //load meshes and extract shader_id, texture_id
     let mut meshes = conf.construct_meshes();
     
@@ -90,16 +129,20 @@ impl FnMut(ev::Event<'_, T>, &evl::EventLoopWindowTarget<T>, &mut evl::ControlFl
     for mesh in &mut meshes {
         mesh.load_geometry();
         mesh.buffer_unindexed(&display);
+        mesh.buffer_lods(&display);
     }
     
             //start timer
     let start_time = std::time::Instant::now();
+    let mut last_frame = start_time;
     
     //create an event handler and regster the camera<
     let mut ev_handler = event_handling::EventHandler::new();
-    let camera = event_handling::camera_transformations::Camera::default();
+    let mut camera = event_handling::camera_transformations::Camera::new(conf.camera);
+    camera.set_window_size(display.get_framebuffer_dimensions());
     let cam_binding = event_handling::ModelType::Camera(camera);
     ev_handler.add_model(cam_binding);
+    ev_handler.set_camera_paths(conf.camera_paths);
 
     //fetch the shaders and textures
     let mut shaders : Vec<ShaderProg> = Vec::new();
@@ -113,7 +156,44 @@ impl FnMut(ev::Event<'_, T>, &evl::EventLoopWindowTarget<T>, &mut evl::ControlFl
         textures.push(Texture::from_file(texture_id, &display));
     }
     
-    let shaderpp_prog = ShaderProg::load_from_file_pp(post_pr_id, &display);
+    //passes given on the command line replace the ones declared in the scene
+    let postpr_configs = if !post_pr_ids.is_empty() {
+        post_pr_ids.into_iter().map(scene::PostProcessConfig::with_shader).collect()
+    } else if !conf.post_processing.is_empty() {
+        conf.post_processing
+    } else {
+        vec![scene::PostProcessConfig::with_shader(0)]
+    };
+    let postpr_chain = PostProcessChain::from_configs(&postpr_configs, &display);
+
+    //scenes without any lights keep the light the standard shader used to hardcode
+    let lights = if conf.lights.is_empty() { vec![Light::default()] } else { conf.lights };
+    let light_set = LightSet::new(&display, lights);
+    let shadow_maps = ShadowMaps::new(&display, light_set.get_lights(), conf.shadows);
+
+    let materials = MaterialLibrary::new(&display, conf.materials);
+    let environment = match &conf.environment {
+        Some(source) => Environment::from_source(&display, source),
+        None => Environment::uniform(&display, [0.05, 0.05, 0.05])
+    };
+    let skybox = conf.skybox.map(|settings| Skybox::new(&display, settings));
+
+    let ssao = conf.ssao.map(|settings| Ssao::new(&display, settings));
+    let bloom = conf.bloom.map(|settings| Bloom::new(&display, settings));
+
+    let mut renderer = drawing::Renderer::new(&display,
+                                              postpr_chain,
+                                              light_set,
+                                              shadow_maps,
+                                              materials,
+                                              environment,
+                                              skybox,
+                                              conf.fog,
+                                              ssao,
+                                              bloom,
+                                              conf.tone_mapping,
+                                              conf.anti_aliasing,
+                                              render_path);
     
     move |ev, _, control_flow: &mut glutin::event_loop::ControlFlow| {    
         
@@ -129,17 +209,37 @@ impl FnMut(ev::Event<'_, T>, &evl::EventLoopWindowTarget<T>, &mut evl::ControlFl
             ev::Event::RedrawRequested(_) => {
                 let this_frame = std::time::Instant::now();
                 let time = this_frame.duration_since(start_time).as_secs_f32();
-                ev_handler.modify_models();
-                let camera = ev_handler.get_camera().unwrap();
-                drawing::render_meshes(meshes.iter().collect(),
+                ev_handler.modify_models(this_frame.duration_since(last_frame).as_secs_f32());
+                last_frame = this_frame;
+                if std::mem::take(&mut ev_handler.params.frame_selected) {
+                    if let Some(bounds) = ev_handler.params.selected.and_then(|i| meshes[i].world_bounds()) {
+                        ev_handler.frame(bounds.sphere.center, bounds.sphere.radius);
+                    }
+                }
+                //picking and drawing use the same interpolated camera, so clicks hit what is on screen
+                let camera = ev_handler.get_render_camera().unwrap();
+                if let Some(cursor) = ev_handler.params.pick.take() {
+                    ev_handler.params.selected = camera.window_to_ndc(cursor, display.get_framebuffer_dimensions())
+                        .and_then(|ndc| {
+                            let (origin, direction) = camera.screen_ray(ndc);
+                            culling::pick(&meshes, origin, direction)
+                        });
+                }
+                renderer.set_wireframe(ev_handler.params.wireframe);
+                renderer.render_meshes(meshes.iter().collect(),
                                        &camera,
                                        &display,
                                        shaders.iter().collect(),
-                                       Some(&shaderpp_prog),
                                        textures.iter().collect(),
                                        time);
             },
             ev::Event::WindowEvent { event, .. } => {
+                //some platforms don't resize the gl surface along with the window
+                match &event {
+                    ev::WindowEvent::Resized(size) => display.gl_window().resize(*size),
+                    ev::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => display.gl_window().resize(**new_inner_size),
+                    _ => {}
+                }
                 ev_handler.register_window_event(event);
             },
             ev::Event::DeviceEvent { event, .. } => {
@@ -174,11 +274,39 @@ fn main() {
                             Ok(()) => println!("Successfully buffered the floor data!"),
                             Err(err) => err.print_formatted()
                         }
-                    } else {          
+                    } else {
+                        let mut conf = match scene::SceneConfig::load_scene_config(&scene_file) {
+                            Ok(conf) => conf,
+                            Err(err) => {
+                                err.print_formatted();
+                                return;
+                            }
+                        };
+                        //a mode given on the command line replaces the one from the scene
+                        if let Some(anti_aliasing) = par.anti_aliasing {
+                            conf.anti_aliasing = anti_aliasing;
+                        }
+
                         let event_loop = glutin::event_loop::EventLoop::new();
                         let wb = glutin::window::WindowBuilder::new();
                         let cb = glutin::ContextBuilder::new().with_depth_buffer(24);
                         let display = glium::Display::new(wb, cb, &event_loop).unwrap();
+
+                        //more samples than the driver takes would fail to create the msaa targets
+                        let supported = conf.anti_aliasing.supported(antialiasing::max_samples(&display));
+                        if supported != conf.anti_aliasing {
+                            println!("{:?} is not supported by the graphics driver, using {:?} instead.",
+                                     conf.anti_aliasing,
+                                     supported);
+                            conf.anti_aliasing = supported;
+                        }
+                        let for_render_path = conf.anti_aliasing.for_render_path(par.render_path);
+                        if for_render_path != conf.anti_aliasing {
+                            println!("{:?} is not supported with deferred shading, using {:?} instead.",
+                                     conf.anti_aliasing,
+                                     for_render_path);
+                            conf.anti_aliasing = for_render_path;
+                        }
                         
                         if par.full_screen {
                             let monitor_handle = display
@@ -199,7 +327,7 @@ fn main() {
                                 .unwrap();
                         }
                         
-                        event_loop.run(event_handler_gen(display, &scene_file, par.post_pr_id));
+                        event_loop.run(event_handler_gen(display, conf, par.post_pr_ids, par.render_path));
                     }
                 },
                 None => {
@@ -0,0 +1,952 @@
+use glium::draw_parameters::DepthTest;
+
+use crate::drawing::antialiasing::AntiAliasing;
+use crate::drawing::bloom::BloomSettings;
+use crate::drawing::environment::EnvironmentSource;
+use crate::drawing::fog::{FogMode, FogSettings};
+use crate::drawing::lighting::{Light, LightKind};
+use crate::drawing::lod::{LodConfig, LodSource, LodSwitch};
+use crate::drawing::material::{BlendMode, MaterialConfig};
+use crate::drawing::mesh::Mesh;
+use crate::event_handling::camera_path::{CameraPath, Keyframe};
+use crate::event_handling::camera_transformations::{CameraSettings, Controller, Projection, angles_to_direction};
+use crate::drawing::postprocessing::PassUniformValue;
+use crate::drawing::render_state::{CullMode, PolygonMode, RenderStateOverride};
+use crate::drawing::shadows::ShadowSettings;
+use crate::drawing::skybox::SkyboxSettings;
+use crate::drawing::ssao::{SsaoSettings, MAX_SSAO_SAMPLES};
+use crate::drawing::tonemapping::{ToneMapOperator, ToneMapSettings};
+use crate::drawing::mesh::model_loading::parsing::Parser;
+use crate::event_handling::camera_transformations::linalg;
+use parsing::{SceneItem, SceneValue, find_key};
+use scatter::ScatterConfig;
+
+pub mod parsing;
+pub mod scatter;
+
+struct MeshConfig {
+    id: u16,
+    shader: u16,
+    texture: u16,
+    offset: (f32, f32, f32),
+    scale: f32,
+    material: Option<u16>,
+    cast_shadows: bool,
+    receive_shadows: bool,
+    instances: Vec<[[f32; 4]; 4]>,
+    lods: Vec<LodConfig>,
+    lod_hysteresis: f32,
+    render_state: RenderStateOverride
+}
+
+//an instance is either just an offset or a block with offset, rotation in degrees and scale
+fn instance_from_value(value: &SceneValue) -> Result<[[f32; 4]; 4], SceneLoadError> {
+    if let Some(offset) = value.as_vec3() {
+        return Ok(linalg::model_matrix(offset, [0.0, 0.0, 0.0], 1.0));
+    }
+
+    let entries = value.as_block().ok_or(SceneLoadError::invalid("mesh", "instances"))?;
+    let rotation = optional_vec3(entries, "instance", "rotation", [0.0, 0.0, 0.0])?;
+    Ok(linalg::model_matrix(optional_vec3(entries, "instance", "offset", [0.0, 0.0, 0.0])?,
+                            [rotation[0].to_radians(), rotation[1].to_radians(), rotation[2].to_radians()],
+                            optional_f32(entries, "instance", "scale", 1.0)?))
+}
+
+//a lod names either another model or a simplification ratio, and either a distance or a screen size
+fn lod_from_value(value: &SceneValue) -> Result<LodConfig, SceneLoadError> {
+    let entries = value.as_block().ok_or(SceneLoadError::invalid("mesh", "lods"))?;
+
+    let source = match (maybe_u16(entries, "lod", "id")?, find_key(entries, "simplify")) {
+        (Some(id), None) => LodSource::Model(id),
+        (None, Some(_)) => LodSource::Simplified(optional_f32(entries, "lod", "simplify", 1.0)?),
+        (None, None) => { return Err(SceneLoadError::MissingKey("lod".to_string(), "id".to_string())); },
+        (Some(_), Some(_)) => { return Err(SceneLoadError::invalid("lod", "simplify")); }
+    };
+
+    let switch = match (find_key(entries, "distance"), find_key(entries, "screen_size")) {
+        (Some(_), None) => LodSwitch::Distance(optional_f32(entries, "lod", "distance", 0.0)?),
+        (None, Some(_)) => LodSwitch::ScreenSize(optional_f32(entries, "lod", "screen_size", 0.0)?),
+        (None, None) => { return Err(SceneLoadError::MissingKey("lod".to_string(), "distance".to_string())); },
+        (Some(_), Some(_)) => { return Err(SceneLoadError::invalid("lod", "screen_size")); }
+    };
+
+    Ok(LodConfig { source, switch })
+}
+
+fn scatter_from_entries(entries: &[(&str, SceneValue)]) -> Result<ScatterConfig, SceneLoadError> {
+    let scale = match find_key(entries, "scale") {
+        Some(value) => match value.as_floats() {
+            Some(floats) if floats.len() == 2 => (floats[0], floats[1]),
+            _ => { return Err(SceneLoadError::invalid("scatter", "scale")); }
+        },
+        None => (1.0, 1.0)
+    };
+    let seed = optional_u16(entries, "scatter", "seed", 1)?;
+
+    Ok(ScatterConfig {
+        count: required_u16(entries, "scatter", "count")?,
+        min: required_vec3(entries, "scatter", "min")?,
+        max: required_vec3(entries, "scatter", "max")?,
+        scale,
+        rotate: optional_bool(entries, "scatter", "rotate", false)?,
+        seed: seed as u32
+    })
+}
+
+impl MeshConfig {
+    fn from_entries(entries: &[(&str, SceneValue)]) -> Result<Self, SceneLoadError> {
+        let offset = match find_key(entries, "offset") {
+            Some(value) => {
+                let offset = value.as_vec3().ok_or(SceneLoadError::invalid("mesh", "offset"))?;
+                (offset[0], offset[1], offset[2])
+            },
+            None => (0.0, 0.0, 0.0)
+        };
+
+        let mut instances : Vec<[[f32; 4]; 4]> = Vec::new();
+        if let Some(value) = find_key(entries, "instances") {
+            for instance in value.as_list().ok_or(SceneLoadError::invalid("mesh", "instances"))? {
+                instances.push(instance_from_value(instance)?);
+            }
+        }
+        if let Some(value) = find_key(entries, "scatter") {
+            let scatter = scatter_from_entries(value.as_block().ok_or(SceneLoadError::invalid("mesh", "scatter"))?)?;
+            instances.extend(scatter::scatter_transforms(&scatter));
+        }
+
+        let mut lods : Vec<LodConfig> = Vec::new();
+        if let Some(value) = find_key(entries, "lods") {
+            for lod in value.as_list().ok_or(SceneLoadError::invalid("mesh", "lods"))? {
+                lods.push(lod_from_value(lod)?);
+            }
+        }
+
+        Ok(MeshConfig {
+            id: required_u16(entries, "mesh", "id")?,
+            shader: required_u16(entries, "mesh", "shader")?,
+            texture: optional_u16(entries, "mesh", "texture", 0)?,
+            offset,
+            scale: optional_f32(entries, "mesh", "scale", 1.0)?,
+            material: maybe_u16(entries, "mesh", "material")?,
+            cast_shadows: optional_bool(entries, "mesh", "cast_shadows", true)?,
+            receive_shadows: optional_bool(entries, "mesh", "receive_shadows", true)?,
+            instances,
+            lods,
+            lod_hysteresis: optional_f32(entries, "mesh", "lod_hysteresis", 0.1)?,
+            render_state: render_state_from_entries(entries, "mesh")?
+        })
+    }
+}
+
+pub struct PostProcessConfig {
+    pub shader: u16,
+    pub scale: f32,
+    pub uniforms: Vec<(String, PassUniformValue)>
+}
+
+impl PostProcessConfig {
+    pub fn with_shader(shader: u16) -> PostProcessConfig {
+        PostProcessConfig {
+            shader,
+            scale: 1.0,
+            uniforms: Vec::new()
+        }
+    }
+
+    fn from_entries(entries: &[(&str, SceneValue)]) -> Result<Self, SceneLoadError> {
+        let mut uniforms : Vec<(String, PassUniformValue)> = Vec::new();
+
+        if let Some(value) = find_key(entries, "uniforms") {
+            let uniform_entries = value.as_block().ok_or(SceneLoadError::invalid("postprocess", "uniforms"))?;
+            for (name, uniform) in uniform_entries {
+                let uniform_value = match (uniform.as_f32(), uniform.as_floats()) {
+                    (Some(x), _) => PassUniformValue::Float(x),
+                    (None, Some(floats)) if floats.len() == 2 => {
+                        PassUniformValue::Vec2([floats[0], floats[1]])
+                    },
+                    (None, Some(floats)) if floats.len() == 3 => {
+                        PassUniformValue::Vec3([floats[0], floats[1], floats[2]])
+                    },
+                    (None, Some(floats)) if floats.len() == 4 => {
+                        PassUniformValue::Vec4([floats[0], floats[1], floats[2], floats[3]])
+                    },
+                    _ => { return Err(SceneLoadError::invalid("postprocess", name)); }
+                };
+                uniforms.push((name.to_string(), uniform_value));
+            }
+        }
+
+        let scale = optional_f32(entries, "postprocess", "scale", 1.0)?;
+        if scale <= 0.0 {
+            return Err(SceneLoadError::invalid("postprocess", "scale"));
+        }
+
+        Ok(PostProcessConfig {
+            shader: required_u16(entries, "postprocess", "shader")?,
+            scale,
+            uniforms
+        })
+    }
+}
+
+fn light_from_entries(entries: &[(&str, SceneValue)]) -> Result<Light, SceneLoadError> {
+    let kind = match find_key(entries, "type").map(|value| value.as_ident()) {
+        Some(Some("directional")) => LightKind::Directional,
+        Some(Some("point")) => LightKind::Point,
+        Some(Some("spot")) => LightKind::Spot,
+        Some(_) => { return Err(SceneLoadError::invalid("light", "type")); },
+        None => { return Err(SceneLoadError::MissingKey("light".to_string(), "type".to_string())); }
+    };
+
+    let default = Light::default();
+    let position = match kind {
+        LightKind::Directional => optional_vec3(entries, "light", "position", default.position)?,
+        _ => required_vec3(entries, "light", "position")?
+    };
+    let direction = match kind {
+        LightKind::Point => optional_vec3(entries, "light", "direction", default.direction)?,
+        _ => required_vec3(entries, "light", "direction")?
+    };
+
+    Ok(Light {
+        kind,
+        position,
+        direction,
+        color: optional_vec3(entries, "light", "color", default.color)?,
+        intensity: optional_f32(entries, "light", "intensity", default.intensity)?,
+        attenuation: optional_vec3(entries, "light", "attenuation", default.attenuation)?,
+        inner_angle: optional_f32(entries, "light", "inner_angle", default.inner_angle)?,
+        outer_angle: optional_f32(entries, "light", "outer_angle", default.outer_angle)?,
+        cast_shadows: optional_bool(entries, "light", "cast_shadows", default.cast_shadows)?
+    })
+}
+
+//materials and meshes take the same keys, a mesh only overrides what it sets
+fn render_state_from_entries(entries: &[(&str, SceneValue)], item: &str) -> Result<RenderStateOverride, SceneLoadError> {
+    let cull = match find_key(entries, "cull").map(|value| value.as_ident()) {
+        Some(Some("back")) => Some(CullMode::Back),
+        Some(Some("front")) => Some(CullMode::Front),
+        Some(Some("none")) => Some(CullMode::TwoSided),
+        Some(_) => { return Err(SceneLoadError::invalid(item, "cull")); },
+        None => None
+    };
+
+    let polygon_mode = match find_key(entries, "polygon_mode").map(|value| value.as_ident()) {
+        Some(Some("fill")) => Some(PolygonMode::Fill),
+        Some(Some("line")) => Some(PolygonMode::Line),
+        Some(Some("point")) => Some(PolygonMode::Point),
+        Some(_) => { return Err(SceneLoadError::invalid(item, "polygon_mode")); },
+        None => None
+    };
+
+    let depth_test = match find_key(entries, "depth_test").map(|value| value.as_ident()) {
+        Some(Some("less")) => Some(DepthTest::IfLess),
+        Some(Some("less_equal")) => Some(DepthTest::IfLessOrEqual),
+        Some(Some("greater")) => Some(DepthTest::IfMore),
+        Some(Some("greater_equal")) => Some(DepthTest::IfMoreOrEqual),
+        Some(Some("equal")) => Some(DepthTest::IfEqual),
+        Some(Some("always")) => Some(DepthTest::Overwrite),
+        Some(_) => { return Err(SceneLoadError::invalid(item, "depth_test")); },
+        None => None
+    };
+
+    Ok(RenderStateOverride { cull, polygon_mode, depth_test })
+}
+
+fn material_from_entries(entries: &[(&str, SceneValue)]) -> Result<MaterialConfig, SceneLoadError> {
+    let default = MaterialConfig::default();
+    let base_color = match find_key(entries, "base_color") {
+        Some(value) => match value.as_floats() {
+            Some(floats) if floats.len() == 3 => [floats[0], floats[1], floats[2], 1.0],
+            Some(floats) if floats.len() == 4 => [floats[0], floats[1], floats[2], floats[3]],
+            _ => { return Err(SceneLoadError::invalid("material", "base_color")); }
+        },
+        None => default.base_color
+    };
+
+    let blend_mode = match find_key(entries, "blend").map(|value| value.as_ident()) {
+        Some(Some("opaque")) => BlendMode::Opaque,
+        Some(Some("cutout")) => BlendMode::Cutout,
+        Some(Some("blended")) => BlendMode::Blended,
+        Some(_) => { return Err(SceneLoadError::invalid("material", "blend")); },
+        None => default.blend_mode
+    };
+
+    Ok(MaterialConfig {
+        id: required_u16(entries, "material", "id")?,
+        base_color,
+        metallic: optional_f32(entries, "material", "metallic", default.metallic)?,
+        roughness: optional_f32(entries, "material", "roughness", default.roughness)?,
+        emissive: optional_vec3(entries, "material", "emissive", default.emissive)?,
+        albedo_map: maybe_u16(entries, "material", "albedo_map")?,
+        metallic_roughness_map: maybe_u16(entries, "material", "metallic_roughness_map")?,
+        normal_map: maybe_u16(entries, "material", "normal_map")?,
+        ao_map: maybe_u16(entries, "material", "ao_map")?,
+        blend_mode,
+        alpha_cutoff: optional_f32(entries, "material", "alpha_cutoff", default.alpha_cutoff)?,
+        render_state: render_state_from_entries(entries, "material")?.apply(default.render_state)
+    })
+}
+
+//environment and skybox blocks take the same keys, item is used for error messages
+fn environment_from_entries(entries: &[(&str, SceneValue)], item: &str) -> Result<EnvironmentSource, SceneLoadError> {
+    match (find_key(entries, "faces"), find_key(entries, "equirect")) {
+        (Some(value), None) => {
+            let faces = value.as_list().ok_or(SceneLoadError::invalid(item, "faces"))?;
+            let names : Vec<String> = faces.iter()
+                .filter_map(|face| face.as_text().map(|name| name.to_string()))
+                .collect();
+            let names : [String; 6] = names.try_into().map_err(|_| SceneLoadError::invalid(item, "faces"))?;
+            Ok(EnvironmentSource::Faces(names))
+        },
+        (None, Some(value)) => {
+            let name = value.as_text().ok_or(SceneLoadError::invalid(item, "equirect"))?;
+            Ok(EnvironmentSource::Equirect(name.to_string()))
+        },
+        (Some(_), Some(_)) => Err(SceneLoadError::invalid(item, "equirect")),
+        (None, None) => Err(SceneLoadError::MissingKey(item.to_string(), "faces".to_string()))
+    }
+}
+
+fn shadow_settings_from_entries(entries: &[(&str, SceneValue)]) -> Result<ShadowSettings, SceneLoadError> {
+    let default = ShadowSettings::default();
+    let resolution = match find_key(entries, "resolution") {
+        Some(value) => value.as_u16().filter(|res| *res > 0).ok_or(SceneLoadError::invalid("shadows", "resolution"))?,
+        None => default.resolution as u16
+    };
+
+    Ok(ShadowSettings {
+        distance: optional_f32(entries, "shadows", "distance", default.distance)?,
+        resolution: resolution as u32,
+        cascade_lambda: optional_f32(entries, "shadows", "cascade_lambda", default.cascade_lambda)?
+    })
+}
+
+fn tone_mapping_from_entries(entries: &[(&str, SceneValue)]) -> Result<ToneMapSettings, SceneLoadError> {
+    let default = ToneMapSettings::default();
+    let operator = match find_key(entries, "operator").map(|value| value.as_ident()) {
+        Some(Some("reinhard")) => ToneMapOperator::Reinhard,
+        Some(Some("aces")) => ToneMapOperator::Aces,
+        Some(_) => { return Err(SceneLoadError::invalid("tonemapping", "operator")); },
+        None => default.operator
+    };
+
+    Ok(ToneMapSettings {
+        operator,
+        exposure: optional_f32(entries, "tonemapping", "exposure", default.exposure)?,
+        auto_exposure: optional_bool(entries, "tonemapping", "auto_exposure", default.auto_exposure)?,
+        key: optional_f32(entries, "tonemapping", "key", default.key)?,
+        adaptation_speed: optional_f32(entries, "tonemapping", "adaptation_speed", default.adaptation_speed)?
+    })
+}
+
+fn bloom_settings_from_entries(entries: &[(&str, SceneValue)]) -> Result<BloomSettings, SceneLoadError> {
+    let default = BloomSettings::default();
+    let levels = match find_key(entries, "levels") {
+        Some(value) => value.as_u16().filter(|levels| *levels > 0).ok_or(SceneLoadError::invalid("bloom", "levels"))?,
+        None => default.levels as u16
+    };
+
+    Ok(BloomSettings {
+        threshold: optional_f32(entries, "bloom", "threshold", default.threshold)?,
+        knee: optional_f32(entries, "bloom", "knee", default.knee)?,
+        intensity: optional_f32(entries, "bloom", "intensity", default.intensity)?,
+        levels: levels as u32,
+        radius: optional_f32(entries, "bloom", "radius", default.radius)?
+    })
+}
+
+//fov is given in degrees, without far the far plane is at infinity
+fn camera_settings_from_entries(entries: &[(&str, SceneValue)]) -> Result<CameraSettings, SceneLoadError> {
+    let default = CameraSettings::default();
+    let fov = optional_f32(entries, "camera", "fov", default.fov.to_degrees())?;
+    if fov <= 0.0 || fov >= 180.0 {
+        return Err(SceneLoadError::invalid("camera", "fov"));
+    }
+    let near = optional_f32(entries, "camera", "near", default.near)?;
+    if near <= 0.0 {
+        return Err(SceneLoadError::invalid("camera", "near"));
+    }
+    let far = match find_key(entries, "far") {
+        Some(value) => Some(value.as_f32().filter(|far| *far > near).ok_or(SceneLoadError::invalid("camera", "far"))?),
+        None => None
+    };
+
+    let projection = match find_key(entries, "projection").map(|value| value.as_ident()) {
+        Some(Some("perspective")) | None => Projection::Perspective,
+        Some(Some("orthographic")) => Projection::Orthographic,
+        Some(_) => { return Err(SceneLoadError::invalid("camera", "projection")); }
+    };
+    let ortho_height = optional_f32(entries, "camera", "ortho_height", default.ortho_height)?;
+    if ortho_height <= 0.0 {
+        return Err(SceneLoadError::invalid("camera", "ortho_height"));
+    }
+    let controller = match find_key(entries, "controller").map(|value| value.as_ident()) {
+        Some(Some("free_fly")) | None => Controller::FreeFly,
+        Some(Some("orbit")) => Controller::Orbit,
+        Some(_) => { return Err(SceneLoadError::invalid("camera", "controller")); }
+    };
+    let letterbox = match find_key(entries, "letterbox") {
+        Some(value) => Some(value.as_f32().filter(|aspect| *aspect > 0.0).ok_or(SceneLoadError::invalid("camera", "letterbox"))?),
+        None => None
+    };
+
+    Ok(CameraSettings {
+        fov: fov.to_radians(),
+        near,
+        far,
+        reversed_z: optional_bool(entries, "camera", "reversed_z", default.reversed_z)?,
+        projection,
+        ortho_height,
+        letterbox,
+        sensitivity: optional_f32(entries, "camera", "sensitivity", default.sensitivity)?,
+        invert_y: optional_bool(entries, "camera", "invert_y", default.invert_y)?,
+        controller,
+        orbit_target: optional_vec3(entries, "camera", "orbit_target", default.orbit_target)?
+    })
+}
+
+/* a keyframe looks either at a point with look_at or along yaw and pitch in degrees, yaw turning
+ * right from +z and pitch up from the horizon */
+fn keyframe_from_value(value: &SceneValue) -> Result<Keyframe, SceneLoadError> {
+    let entries = value.as_block().ok_or(SceneLoadError::invalid("camera_path", "keyframes"))?;
+    let time = match find_key(entries, "time") {
+        Some(value) => value.as_f32().ok_or(SceneLoadError::invalid("keyframe", "time"))?,
+        None => { return Err(SceneLoadError::MissingKey("keyframe".to_string(), "time".to_string())); }
+    };
+    let position = required_vec3(entries, "keyframe", "position")?;
+
+    let direction = match find_key(entries, "look_at") {
+        Some(value) => {
+            let target = value.as_vec3().ok_or(SceneLoadError::invalid("keyframe", "look_at"))?;
+            let direction = linalg::sub(target, position);
+            if linalg::mag(direction) < f32::EPSILON {
+                return Err(SceneLoadError::invalid("keyframe", "look_at"));
+            }
+            linalg::norm(direction)
+        },
+        None => {
+            angles_to_direction(optional_f32(entries, "keyframe", "yaw", 0.0)?.to_radians(),
+                                optional_f32(entries, "keyframe", "pitch", 0.0)?.to_radians())
+        }
+    };
+
+    Ok(Keyframe { time, position, direction })
+}
+
+fn camera_path_from_entries(entries: &[(&str, SceneValue)]) -> Result<CameraPath, SceneLoadError> {
+    let mut keyframes : Vec<Keyframe> = Vec::new();
+    match find_key(entries, "keyframes") {
+        Some(value) => {
+            for keyframe in value.as_list().ok_or(SceneLoadError::invalid("camera_path", "keyframes"))? {
+                keyframes.push(keyframe_from_value(keyframe)?);
+            }
+        },
+        None => { return Err(SceneLoadError::MissingKey("camera_path".to_string(), "keyframes".to_string())); }
+    }
+    if keyframes.is_empty() {
+        return Err(SceneLoadError::invalid("camera_path", "keyframes"));
+    }
+
+    Ok(CameraPath::new(keyframes,
+                       optional_bool(entries, "camera_path", "looping", false)?,
+                       optional_bool(entries, "camera_path", "autoplay", false)?))
+}
+
+//only the keys of the chosen mode matter, the others keep their defaults
+fn fog_settings_from_entries(entries: &[(&str, SceneValue)]) -> Result<FogSettings, SceneLoadError> {
+    let default = FogSettings::default();
+    let mode = match find_key(entries, "mode").map(|value| value.as_ident()) {
+        Some(Some("off")) => FogMode::Off,
+        Some(Some("linear")) | None => FogMode::Linear,
+        Some(Some("exponential")) => FogMode::Exponential,
+        Some(Some("height")) => FogMode::Height,
+        Some(_) => { return Err(SceneLoadError::invalid("fog", "mode")); }
+    };
+    let start = optional_f32(entries, "fog", "start", default.start)?;
+    let end = optional_f32(entries, "fog", "end", default.end)?;
+    if mode == FogMode::Linear && end <= start {
+        return Err(SceneLoadError::invalid("fog", "end"));
+    }
+
+    Ok(FogSettings {
+        mode,
+        color: optional_vec3(entries, "fog", "color", default.color)?,
+        start,
+        end,
+        density: optional_f32(entries, "fog", "density", default.density)?,
+        height: optional_f32(entries, "fog", "height", default.height)?,
+        height_falloff: optional_f32(entries, "fog", "height_falloff", default.height_falloff)?,
+        sky_blend: optional_f32(entries, "fog", "sky_blend", default.sky_blend)?
+    })
+}
+
+fn ssao_settings_from_entries(entries: &[(&str, SceneValue)]) -> Result<SsaoSettings, SceneLoadError> {
+    let default = SsaoSettings::default();
+    let samples = optional_u16(entries, "ssao", "samples", default.samples as u16)?;
+    if samples == 0 || samples as usize > MAX_SSAO_SAMPLES {
+        return Err(SceneLoadError::invalid("ssao", "samples"));
+    }
+
+    Ok(SsaoSettings {
+        radius: optional_f32(entries, "ssao", "radius", default.radius)?,
+        bias: optional_f32(entries, "ssao", "bias", default.bias)?,
+        intensity: optional_f32(entries, "ssao", "intensity", default.intensity)?,
+        samples: samples as u32,
+        blur_radius: optional_u16(entries, "ssao", "blur_radius", default.blur_radius as u16)? as u32
+    })
+}
+
+//mode is off, msaa or fxaa, samples only matters for msaa
+fn anti_aliasing_from_entries(entries: &[(&str, SceneValue)]) -> Result<AntiAliasing, SceneLoadError> {
+    match find_key(entries, "mode").map(|value| value.as_ident()) {
+        Some(Some("off")) => Ok(AntiAliasing::Off),
+        Some(Some("fxaa")) => Ok(AntiAliasing::Fxaa),
+        Some(Some("msaa")) => {
+            let samples = optional_u16(entries, "antialiasing", "samples", 4)? as u32;
+            if AntiAliasing::valid_samples(samples) {
+                Ok(AntiAliasing::Msaa(samples))
+            } else {
+                Err(SceneLoadError::invalid("antialiasing", "samples"))
+            }
+        },
+        Some(_) => Err(SceneLoadError::invalid("antialiasing", "mode")),
+        None => Err(SceneLoadError::MissingKey("antialiasing".to_string(), "mode".to_string()))
+    }
+}
+
+pub struct SceneConfig {
+    meshes: Vec<MeshConfig>,
+    pub post_processing: Vec<PostProcessConfig>,
+    pub lights: Vec<Light>,
+    pub shadows: ShadowSettings,
+    pub materials: Vec<MaterialConfig>,
+    pub environment: Option<EnvironmentSource>,
+    //a skybox block sets the environment and also draws it behind the scene
+    pub skybox: Option<SkyboxSettings>,
+    pub tone_mapping: ToneMapSettings,
+    //bloom only runs if the scene has a bloom block
+    pub bloom: Option<BloomSettings>,
+    pub camera: CameraSettings,
+    //off unless the scene has a fog block
+    pub fog: FogSettings,
+    //like bloom only there if the scene asks for it
+    pub ssao: Option<SsaoSettings>,
+    pub camera_paths: Vec<CameraPath>,
+    pub anti_aliasing: AntiAliasing
+}
+
+impl SceneConfig {
+    pub fn load_scene_config(source: &str) -> Result<Self, SceneLoadError> {
+        let mut pathstr = std::string::String::new();
+        pathstr.push_str("./resources/scenes/");
+        pathstr.push_str(source);
+        let path = std::path::Path::new(&pathstr);
+
+        match std::fs::read_to_string(path) {
+            Ok(source) => {
+                match parsing::scene_file_p().parse(&source) {
+                    Some(("", items)) => {
+                        Self::from_items(items)
+                    },
+                    _ => {
+                        Err(SceneLoadError::ParseError)
+                    }
+                }
+            },
+            Err(err) => {
+                Err(SceneLoadError::IOError(err))
+            }
+        }
+    }
+
+    fn from_items(items: Vec<SceneItem>) -> Result<Self, SceneLoadError> {
+        let mut conf = SceneConfig {
+            meshes: Vec::new(),
+            post_processing: Vec::new(),
+            lights: Vec::new(),
+            shadows: ShadowSettings::default(),
+            materials: Vec::new(),
+            environment: None,
+            skybox: None,
+            tone_mapping: ToneMapSettings::default(),
+            bloom: None,
+            camera: CameraSettings::default(),
+            fog: FogSettings::default(),
+            ssao: None,
+            camera_paths: Vec::new(),
+            anti_aliasing: AntiAliasing::default()
+        };
+
+        for item in items {
+            match item.kind {
+                //blocks without a name are meshes to stay compatible with older scene files
+                None | Some("mesh") => {
+                    conf.meshes.push(MeshConfig::from_entries(&item.entries)?);
+                },
+                Some("postprocess") => {
+                    conf.post_processing.push(PostProcessConfig::from_entries(&item.entries)?);
+                },
+                Some("light") => {
+                    conf.lights.push(light_from_entries(&item.entries)?);
+                },
+                Some("material") => {
+                    conf.materials.push(material_from_entries(&item.entries)?);
+                },
+                Some("environment") => {
+                    conf.environment = Some(environment_from_entries(&item.entries, "environment")?);
+                },
+                Some("skybox") => {
+                    conf.environment = Some(environment_from_entries(&item.entries, "skybox")?);
+                    conf.skybox = Some(SkyboxSettings {
+                        intensity: optional_f32(&item.entries, "skybox", "intensity", SkyboxSettings::default().intensity)?
+                    });
+                },
+                Some("shadows") => {
+                    conf.shadows = shadow_settings_from_entries(&item.entries)?;
+                },
+                Some("tonemapping") => {
+                    conf.tone_mapping = tone_mapping_from_entries(&item.entries)?;
+                },
+                Some("bloom") => {
+                    conf.bloom = Some(bloom_settings_from_entries(&item.entries)?);
+                },
+                Some("camera") => {
+                    conf.camera = camera_settings_from_entries(&item.entries)?;
+                },
+                Some("fog") => {
+                    conf.fog = fog_settings_from_entries(&item.entries)?;
+                },
+                Some("ssao") => {
+                    conf.ssao = Some(ssao_settings_from_entries(&item.entries)?);
+                },
+                Some("antialiasing") => {
+                    conf.anti_aliasing = anti_aliasing_from_entries(&item.entries)?;
+                },
+                Some("camera_path") => {
+                    conf.camera_paths.push(camera_path_from_entries(&item.entries)?);
+                },
+                Some(kind) => {
+                    return Err(SceneLoadError::UnknownItem(kind.to_string()));
+                }
+            }
+        }
+
+        Ok(conf)
+    }
+
+    pub fn construct_meshes(&self) -> Vec<Mesh> {
+        let mut res : Vec<Mesh> = Vec::new();
+
+        for mesh_config in &self.meshes {
+            let mut mesh = Mesh::new_with_id_shader_tex(
+                mesh_config.id,
+                mesh_config.shader,
+                mesh_config.texture
+            );
+            mesh.set_offset(mesh_config.offset);
+            mesh.set_scale(mesh_config.scale);
+            mesh.set_material(mesh_config.material);
+            mesh.set_shadows(mesh_config.cast_shadows, mesh_config.receive_shadows);
+            mesh.set_instances(mesh_config.instances.clone());
+            mesh.set_lods(mesh_config.lods.clone(), mesh_config.lod_hysteresis);
+            mesh.set_render_state(mesh_config.render_state);
+
+            res.push(mesh);
+        }
+
+        res
+    }
+}
+
+fn required_u16(entries: &[(&str, SceneValue)], item: &str, key: &str) -> Result<u16, SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_u16().ok_or(SceneLoadError::invalid(item, key)),
+        None => Err(SceneLoadError::MissingKey(item.to_string(), key.to_string()))
+    }
+}
+
+fn optional_u16(entries: &[(&str, SceneValue)], item: &str, key: &str, default: u16) -> Result<u16, SceneLoadError> {
+    Ok(maybe_u16(entries, item, key)?.unwrap_or(default))
+}
+
+fn maybe_u16(entries: &[(&str, SceneValue)], item: &str, key: &str) -> Result<Option<u16>, SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_u16().map(Some).ok_or(SceneLoadError::invalid(item, key)),
+        None => Ok(None)
+    }
+}
+
+fn optional_f32(entries: &[(&str, SceneValue)], item: &str, key: &str, default: f32) -> Result<f32, SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_f32().ok_or(SceneLoadError::invalid(item, key)),
+        None => Ok(default)
+    }
+}
+
+fn optional_bool(entries: &[(&str, SceneValue)], item: &str, key: &str, default: bool) -> Result<bool, SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_bool().ok_or(SceneLoadError::invalid(item, key)),
+        None => Ok(default)
+    }
+}
+
+fn required_vec3(entries: &[(&str, SceneValue)], item: &str, key: &str) -> Result<[f32; 3], SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_vec3().ok_or(SceneLoadError::invalid(item, key)),
+        None => Err(SceneLoadError::MissingKey(item.to_string(), key.to_string()))
+    }
+}
+
+fn optional_vec3(entries: &[(&str, SceneValue)], item: &str, key: &str, default: [f32; 3]) -> Result<[f32; 3], SceneLoadError> {
+    match find_key(entries, key) {
+        Some(value) => value.as_vec3().ok_or(SceneLoadError::invalid(item, key)),
+        None => Ok(default)
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneLoadError {
+    IOError(std::io::Error),
+    ParseError,
+    UnknownItem(String),
+    MissingKey(String, String),
+    InvalidValue(String, String)
+}
+
+impl SceneLoadError {
+    fn invalid(item: &str, key: &str) -> SceneLoadError {
+        SceneLoadError::InvalidValue(item.to_string(), key.to_string())
+    }
+
+    pub fn print_formatted(&self) {
+        match self {
+            SceneLoadError::IOError(err) => {
+                println!("The scene file could not be read:");
+                println!("{:#?}", err);
+            },
+            SceneLoadError::ParseError => {
+                println!("The scene file is not well formed.");
+            },
+            SceneLoadError::UnknownItem(kind) => {
+                println!("Unknown scene item '{}'.", kind);
+            },
+            SceneLoadError::MissingKey(item, key) => {
+                println!("The {} is missing the key '{}'.", item, key);
+            },
+            SceneLoadError::InvalidValue(item, key) => {
+                println!("The {} has an invalid value for '{}'.", item, key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse_scene(source: &str) -> Result<SceneConfig, SceneLoadError> {
+    let (_, items) = parsing::scene_file_p().parse(source).unwrap();
+    SceneConfig::from_items(items)
+}
+
+#[test]
+fn scene_items_test() {
+    let source = "{ id: 1, shader: 1, texture: 2, offset: (0.0, 1.0, 0.0), scale: 2.0 },\n\
+                  postprocess { shader: 1, scale: 0.5, uniforms: { strength: 0.3, tint: (1.0, 0.5, 0.5) } }";
+    let conf = parse_scene(source).unwrap();
+
+    assert_eq!(conf.meshes.len(), 1);
+    assert_eq!(conf.meshes[0].texture, 2);
+    assert_eq!(conf.meshes[0].offset, (0.0, 1.0, 0.0));
+    assert_eq!(conf.post_processing.len(), 1);
+    assert_eq!(conf.post_processing[0].scale, 0.5);
+    assert_eq!(conf.post_processing[0].uniforms.len(), 2);
+}
+
+#[test]
+fn scene_missing_key_test() {
+    assert!(matches!(parse_scene("mesh { id: 1, texture: 2 }"),
+                     Err(SceneLoadError::MissingKey(_, _))));
+}
+
+#[test]
+fn scene_file_test() {
+    let conf = SceneConfig::load_scene_config("scene.sce").unwrap();
+    assert_eq!(conf.construct_meshes().len(), 2);
+}
+
+#[test]
+fn scene_lights_test() {
+    let source = "light { type: spot, position: (0.0, 5.0, 0.0), direction: (0.0, -1.0, 0.0), outer_angle: 40.0 },\n\
+                  light { type: directional, direction: (1.0, -1.0, 0.0), color: (1.0, 0.5, 0.25) }";
+    let conf = parse_scene(source).unwrap();
+
+    assert_eq!(conf.lights.len(), 2);
+    assert_eq!(conf.lights[0].kind, LightKind::Spot);
+    assert_eq!(conf.lights[0].outer_angle, 40.0);
+    assert_eq!(conf.lights[1].color, [1.0, 0.5, 0.25]);
+
+    assert!(matches!(parse_scene("light { type: point }"),
+                     Err(SceneLoadError::MissingKey(_, _))));
+}
+
+#[test]
+fn scene_materials_test() {
+    let source = "material { id: 3, base_color: (1.0, 0.5, 0.25), metallic: 1.0, normal_map: 4 },\n\
+                  mesh { id: 1, shader: 2, material: 3 },\n\
+                  environment { faces: [\"px.png\", \"nx.png\", \"py.png\", \"ny.png\", \"pz.png\", \"nz.png\"] }";
+    let conf = parse_scene(source).unwrap();
+
+    assert_eq!(conf.materials[0].base_color, [1.0, 0.5, 0.25, 1.0]);
+    assert_eq!(conf.materials[0].normal_map, Some(4));
+    assert_eq!(conf.materials[0].albedo_map, None);
+    assert_eq!(conf.construct_meshes()[0].material_id, Some(3));
+    match conf.environment.unwrap() {
+        EnvironmentSource::Faces(faces) => assert_eq!(faces[3], "ny.png"),
+        EnvironmentSource::Equirect(_) => panic!("expected cubemap faces")
+    }
+    assert!(conf.skybox.is_none());
+
+    assert!(matches!(parse_scene("environment { faces: [\"px.png\"] }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+}
+
+#[test]
+fn scene_blend_mode_test() {
+    let source = "material { id: 1, base_color: (1.0, 1.0, 1.0, 0.5), blend: blended },\n\
+                  material { id: 2, blend: cutout, alpha_cutoff: 0.25 },\n\
+                  material { id: 3 }";
+    let conf = parse_scene(source).unwrap();
+
+    assert_eq!(conf.materials[0].blend_mode, BlendMode::Blended);
+    assert_eq!(conf.materials[1].blend_mode, BlendMode::Cutout);
+    assert_eq!(conf.materials[1].alpha_cutoff, 0.25);
+    assert_eq!(conf.materials[2].blend_mode, BlendMode::Opaque);
+
+    assert!(matches!(parse_scene("material { id: 1, blend: additive }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+}
+
+#[test]
+fn scene_render_state_test() {
+    let source = "material { id: 1, cull: none, depth_test: less_equal },\n\
+                  mesh { id: 1, shader: 1, material: 1, polygon_mode: line }";
+    let conf = parse_scene(source).unwrap();
+
+    assert_eq!(conf.materials[0].render_state.cull, CullMode::TwoSided);
+    assert_eq!(conf.materials[0].render_state.depth_test, DepthTest::IfLessOrEqual);
+    assert_eq!(conf.materials[0].render_state.polygon_mode, PolygonMode::Fill);
+    let mesh_state = conf.construct_meshes()[0].render_state;
+    assert_eq!(mesh_state, RenderStateOverride { polygon_mode: Some(PolygonMode::Line), .. Default::default() });
+
+    assert!(matches!(parse_scene("mesh { id: 1, shader: 1, cull: sideways }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+}
+
+#[test]
+fn scene_instances_test() {
+    let source = "mesh { id: 1, shader: 1, instances: [(1.0, 0.0, 0.0), { offset: (0.0, 2.0, 0.0), scale: 0.5 }] },\n\
+                  mesh { id: 2, shader: 1, scatter: { count: 20, min: (-5.0, 0.0, -5.0), max: (5.0, 0.0, 5.0), seed: 3 } }";
+    let meshes = parse_scene(source).unwrap().construct_meshes();
+
+    assert_eq!(meshes[0].instances.len(), 2);
+    assert_eq!(meshes[0].instances[0][3], [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(meshes[0].instances[1][3], [0.0, 2.0, 0.0, 1.0]);
+    assert_eq!(meshes[0].instances[1][0][0], 0.5);
+    assert_eq!(meshes[1].instances.len(), 20);
+
+    assert!(matches!(parse_scene("mesh { id: 1, shader: 1, scatter: { count: 5 } }"),
+                     Err(SceneLoadError::MissingKey(_, _))));
+}
+
+#[test]
+fn scene_lods_test() {
+    let source = "mesh { id: 1, shader: 1, lods: [{ id: 7, distance: 40.0 }, { simplify: 0.25, screen_size: 0.125 }], lod_hysteresis: 0.25 }";
+    let meshes = parse_scene(source).unwrap().construct_meshes();
+
+    assert_eq!(meshes[0].lods.len(), 2);
+    assert_eq!(meshes[0].lods[0].config, LodConfig { source: LodSource::Model(7), switch: LodSwitch::Distance(40.0) });
+    assert_eq!(meshes[0].lods[1].config, LodConfig { source: LodSource::Simplified(0.25), switch: LodSwitch::ScreenSize(0.125) });
+    assert_eq!(meshes[0].lod_hysteresis, 0.25);
+
+    assert!(matches!(parse_scene("mesh { id: 1, shader: 1, lods: [{ id: 7, simplify: 0.5, distance: 10.0 }] }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+}
+
+#[test]
+fn scene_settings_test() {
+    let source = "tonemapping { operator: aces, exposure: 1.5 },\n\
+                  bloom { intensity: 0.25 },\n\
+                  antialiasing { mode: msaa, samples: 8 },\n\
+                  skybox { equirect: \"sky.hdr\", intensity: 2.0 },\n\
+                  ssao { samples: 32 },\n\
+                  fog { mode: height, height: 2.0 }";
+    let conf = parse_scene(source).unwrap();
+    assert_eq!(conf.tone_mapping.operator, ToneMapOperator::Aces);
+    assert_eq!(conf.tone_mapping.exposure, 1.5);
+    assert_eq!(conf.bloom.map(|bloom| bloom.intensity), Some(0.25));
+    assert_eq!(conf.anti_aliasing, AntiAliasing::Msaa(8));
+    assert_eq!(conf.environment, Some(EnvironmentSource::Equirect("sky.hdr".to_string())));
+    assert_eq!(conf.skybox.map(|skybox| skybox.intensity), Some(2.0));
+    assert_eq!(conf.ssao.map(|ssao| ssao.samples), Some(32));
+    assert_eq!(conf.fog.mode, FogMode::Height);
+    //keys that aren't given keep their defaults
+    assert_eq!(conf.fog.height_falloff, FogSettings::default().height_falloff);
+    assert_eq!(conf.bloom.map(|bloom| bloom.levels), Some(BloomSettings::default().levels));
+
+    for source in ["tonemapping { operator: filmic }",
+                   "antialiasing { mode: msaa, samples: 3 }",
+                   "ssao { samples: 65 }",
+                   "fog { start: 50.0, end: 20.0 }"] {
+        assert!(matches!(parse_scene(source), Err(SceneLoadError::InvalidValue(_, _))), "{}", source);
+    }
+    assert!(matches!(parse_scene("skybox { intensity: 2.0 }"),
+                     Err(SceneLoadError::MissingKey(_, _))));
+    //the range only matters to linear fog
+    assert!(parse_scene("fog { mode: exponential, start: 50.0, end: 20.0 }").is_ok());
+}
+
+#[test]
+fn scene_camera_test() {
+    let camera = parse_scene("camera { near: 0.25, far: 500.0, reversed_z: true }").unwrap().camera;
+    assert_eq!(camera.near, 0.25);
+    assert_eq!(camera.far, Some(500.0));
+    assert!(camera.reversed_z);
+    assert_eq!(camera.fov, CameraSettings::default().fov);
+    assert_eq!(camera.projection, Projection::Perspective);
+
+    let camera = parse_scene("camera { projection: orthographic, ortho_height: 20.0 }").unwrap().camera;
+    assert_eq!(camera.projection, Projection::Orthographic);
+    assert_eq!(camera.ortho_height, 20.0);
+    assert_eq!(camera.letterbox, None);
+
+    assert_eq!(parse_scene("camera { letterbox: 2.5 }").unwrap().camera.letterbox, Some(2.5));
+
+    let camera = parse_scene("camera { sensitivity: 0.25, invert_y: true }").unwrap().camera;
+    assert_eq!(camera.sensitivity, 0.25);
+    assert!(camera.invert_y);
+    assert_eq!(camera.controller, Controller::FreeFly);
+
+    let camera = parse_scene("camera { controller: orbit, orbit_target: (1.0, 2.0, 3.0) }").unwrap().camera;
+    assert_eq!(camera.controller, Controller::Orbit);
+    assert_eq!(camera.orbit_target, [1.0, 2.0, 3.0]);
+
+    assert!(matches!(parse_scene("camera { near: 2.0, far: 1.0 }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+}
+
+#[test]
+fn scene_camera_path_test() {
+    let source = "camera_path { looping: true, keyframes: [{ time: 0.0, position: (0.0, 0.0, -5.0), look_at: (0.0, 0.0, 0.0) }, { time: 2.0, position: (5.0, 0.0, 0.0), yaw: -90.0 }] }";
+    let paths = parse_scene(source).unwrap().camera_paths;
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].looping && !paths[0].autoplay);
+    assert_eq!(paths[0].duration(), 2.0);
+    assert_eq!(paths[0].sample(0.0), ([0.0, 0.0, -5.0], [0.0, 0.0, 1.0]));
+    let (position, direction) = paths[0].sample(2.0);
+    assert_eq!(position, [5.0, 0.0, 0.0]);
+    assert!(linalg::mag(linalg::sub(direction, [-1.0, 0.0, 0.0])) < 1e-5);
+
+    assert!(matches!(parse_scene("camera_path { keyframes: [] }"),
+                     Err(SceneLoadError::InvalidValue(_, _))));
+    assert!(matches!(parse_scene("camera_path { keyframes: [{ position: (0.0, 0.0, 0.0) }] }"),
+                     Err(SceneLoadError::MissingKey(_, _))));
+}
@@ -0,0 +1,261 @@
+use crate::drawing::mesh::model_loading::parsing::{self, Parser};
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum SceneValue<'a> {
+    Number(f32),
+    Ident(&'a str),
+    Text(&'a str),
+    Tuple(Vec<SceneValue<'a>>),
+    List(Vec<SceneValue<'a>>),
+    Block(Vec<(&'a str, SceneValue<'a>)>)
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct SceneItem<'a> {
+    pub kind: Option<&'a str>,
+    pub entries: Vec<(&'a str, SceneValue<'a>)>
+}
+
+impl<'a> SceneValue<'a> {
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            SceneValue::Number(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    pub fn as_u16(&self) -> Option<u16> {
+        match self {
+            SceneValue::Number(x) if *x >= 0.0 && x.fract() == 0.0 && *x <= u16::MAX as f32 => {
+                Some(*x as u16)
+            },
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SceneValue::Ident("true") => Some(true),
+            SceneValue::Ident("false") => Some(false),
+            _ => None
+        }
+    }
+
+    pub fn as_ident(&self) -> Option<&'a str> {
+        match self {
+            SceneValue::Ident(name) => Some(name),
+            _ => None
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&'a str> {
+        match self {
+            SceneValue::Text(text) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn as_floats(&self) -> Option<Vec<f32>> {
+        match self {
+            SceneValue::Tuple(values) => {
+                let mut res : Vec<f32> = Vec::new();
+                for value in values {
+                    res.push(value.as_f32()?);
+                }
+                Some(res)
+            },
+            _ => None
+        }
+    }
+
+    pub fn as_vec3(&self) -> Option<[f32; 3]> {
+        match self.as_floats() {
+            Some(floats) if floats.len() == 3 => Some([floats[0], floats[1], floats[2]]),
+            _ => None
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<SceneValue<'a>>> {
+        match self {
+            SceneValue::List(values) => Some(values),
+            _ => None
+        }
+    }
+
+    pub fn as_block(&self) -> Option<&Vec<(&'a str, SceneValue<'a>)>> {
+        match self {
+            SceneValue::Block(entries) => Some(entries),
+            _ => None
+        }
+    }
+}
+
+pub fn find_key<'a, 'b>(entries: &'b [(&'a str, SceneValue<'a>)], key: &str) -> Option<&'b SceneValue<'a>> {
+    entries.iter().find(|(name, _)| *name == key).map(|(_, value)| value)
+}
+
+fn parse_delim<'a>() -> impl Parser<(), &'a str> {
+    move |input: &'a str| {
+        parsing::parse_ws().maybe().and(
+            parsing::parse_token(",")
+        ).and(
+            parsing::parse_ws().maybe()
+        ).map(|_| ()).parse(input)
+    }
+}
+
+pub fn parse_ident<'a>() -> impl Parser<&'a str, &'a str> {
+    move |input: &'a str| {
+        match input.chars().next() {
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let end = input
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(input.len());
+                Some((&input[end..], &input[..end]))
+            },
+            _ => None
+        }
+    }
+}
+
+fn parse_text<'a>() -> impl Parser<&'a str, &'a str> {
+    move |input: &'a str| {
+        let rest = input.strip_prefix('"')?;
+        let end = rest.find('"')?;
+        Some((&rest[end + 1..], &rest[..end]))
+    }
+}
+
+fn scene_values_p<'a>(open: &'static str, close: &'static str) -> impl Parser<Vec<SceneValue<'a>>, &'a str> {
+    move |input: &'a str| {
+        parsing::parse_token(open).and(
+            parsing::parse_ws().maybe()
+        ).and(
+            scene_value_p().many_delim(parse_delim()).maybe()
+        ).and(
+            parsing::parse_ws().maybe()
+        ).and(
+            parsing::parse_token(close)
+        ).map(
+            |((((_open, _ws0), values), _ws1), _close)| values
+        ).parse(input)
+    }
+}
+
+fn scene_entry_p<'a>() -> impl Parser<(&'a str, SceneValue<'a>), &'a str> {
+    move |input: &'a str| {
+        parse_ident().and(
+            parsing::parse_ws().maybe()
+        ).and(
+            parsing::parse_token(":")
+        ).and(
+            parsing::parse_ws().maybe()
+        ).and(
+            scene_value_p()
+        ).map(
+            |((((name, _ws0), _colon), _ws1), value)| (name, value)
+        ).parse(input)
+    }
+}
+
+fn scene_block_p<'a>() -> impl Parser<Vec<(&'a str, SceneValue<'a>)>, &'a str> {
+    move |input: &'a str| {
+        parsing::parse_token("{").and(
+            parsing::parse_ws().maybe()
+        ).and(
+            scene_entry_p().many_delim(parse_delim()).maybe()
+        ).and(
+            parsing::parse_ws().maybe()
+        ).and(
+            parsing::parse_token("}")
+        ).map(
+            |((((_open, _ws0), entries), _ws1), _close)| entries
+        ).parse(input)
+    }
+}
+
+pub fn scene_value_p<'a>() -> impl Parser<SceneValue<'a>, &'a str> {
+    move |input: &'a str| {
+        parsing::parse_scientific().map(SceneValue::Number).or(
+            parse_ident().map(SceneValue::Ident)
+        ).or(
+            parse_text().map(SceneValue::Text)
+        ).or(
+            scene_values_p("(", ")").map(SceneValue::Tuple)
+        ).or(
+            scene_values_p("[", "]").map(SceneValue::List)
+        ).or(
+            scene_block_p().map(SceneValue::Block)
+        ).parse(input)
+    }
+}
+
+pub fn scene_item_p<'a>() -> impl Parser<SceneItem<'a>, &'a str> {
+    move |input: &'a str| {
+        parse_ident().and(
+            parsing::parse_ws().maybe()
+        ).map(
+            |(kind, _ws)| Some(kind)
+        ).maybe().and(
+            scene_block_p()
+        ).map(
+            |(kind, entries)| SceneItem { kind, entries }
+        ).parse(input)
+    }
+}
+
+pub fn scene_file_p<'a>() -> impl Parser<Vec<SceneItem<'a>>, &'a str> {
+    move |input: &'a str| {
+        parsing::parse_ws().maybe().and(
+            scene_item_p().many_delim(
+                parse_delim().or(parsing::parse_ws())
+            ).maybe()
+        ).and(
+            parsing::parse_ws().maybe()
+        ).map(
+            |((_ws0, items), _ws1)| items
+        ).parse(input)
+    }
+}
+
+#[test]
+fn parse_scene_value_test() {
+    assert_eq!(scene_value_p().parse("-1.5, rest"), Some((", rest", SceneValue::Number(-1.5))));
+    assert_eq!(scene_value_p().parse("point}"), Some(("}", SceneValue::Ident("point"))));
+    assert_eq!(scene_value_p().parse("\"sky.png\""), Some(("", SceneValue::Text("sky.png"))));
+    assert_eq!(scene_value_p().parse("(0.0 , 1.0 , 2)"),
+               Some(("", SceneValue::Tuple(vec![
+                   SceneValue::Number(0.0),
+                   SceneValue::Number(1.0),
+                   SceneValue::Number(2.0)
+               ]))));
+}
+
+#[test]
+fn parse_scene_block_test() {
+    assert_eq!(scene_value_p().parse("{ a: 1, b: [x, y], }"),
+               Some(("", SceneValue::Block(vec![
+                   ("a", SceneValue::Number(1.0)),
+                   ("b", SceneValue::List(vec![SceneValue::Ident("x"), SceneValue::Ident("y")]))
+               ]))));
+    assert_eq!(scene_value_p().parse("{}"), Some(("", SceneValue::Block(vec![]))));
+}
+
+#[test]
+fn parse_scene_file_test() {
+    let source = "{\n    id: 1,\n    scale: 1.0\n},\npostprocess { shader: 2 }\n";
+    let (rest, items) = scene_file_p().parse(source).unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(items, vec![
+        SceneItem {
+            kind: None,
+            entries: vec![("id", SceneValue::Number(1.0)), ("scale", SceneValue::Number(1.0))]
+        },
+        SceneItem {
+            kind: Some("postprocess"),
+            entries: vec![("shader", SceneValue::Number(2.0))]
+        }
+    ]);
+}
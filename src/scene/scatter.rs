@@ -0,0 +1,88 @@
+use crate::event_handling::camera_transformations::linalg;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct ScatterConfig {
+    pub count: u16,
+    //corners of the box the instances are placed in
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    //range the uniform scale of each instance is picked from
+    pub scale: (f32, f32),
+    //random rotation around the y axis
+    pub rotate: bool,
+    pub seed: u32
+}
+
+//xorshift, so a scene always scatters the same way without pulling in a dependency
+struct ScatterRng {
+    state: u32
+}
+
+impl ScatterRng {
+    fn new(seed: u32) -> ScatterRng {
+        //a zero state would only ever produce zeros
+        ScatterRng { state: seed.max(1) }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    //uniform in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+pub fn scatter_transforms(config: &ScatterConfig) -> Vec<[[f32; 4]; 4]> {
+    let mut rng = ScatterRng::new(config.seed);
+    let mut res : Vec<[[f32; 4]; 4]> = Vec::with_capacity(config.count as usize);
+
+    for _ in 0..config.count {
+        let offset = [
+            rng.range(config.min[0], config.max[0]),
+            rng.range(config.min[1], config.max[1]),
+            rng.range(config.min[2], config.max[2])
+        ];
+        let scale = rng.range(config.scale.0, config.scale.1);
+        let heading = if config.rotate { rng.range(0.0, 2.0 * std::f32::consts::PI) } else { 0.0 };
+        res.push(linalg::model_matrix(offset, [0.0, heading, 0.0], scale));
+    }
+
+    res
+}
+
+#[test]
+fn scatter_transforms_test() {
+    let config = ScatterConfig {
+        count: 50,
+        min: [-10.0, 0.0, -10.0],
+        max: [10.0, 0.0, 10.0],
+        scale: (0.5, 2.0),
+        rotate: true,
+        seed: 7
+    };
+    let transforms = scatter_transforms(&config);
+
+    assert_eq!(transforms.len(), 50);
+    for transform in transforms.iter() {
+        let offset = transform[3];
+        assert!(offset[0] >= -10.0 && offset[0] < 10.0);
+        assert_eq!(offset[1], 0.0);
+        assert!(offset[2] >= -10.0 && offset[2] < 10.0);
+        //rotation around y keeps the length of the y column at the scale
+        assert!(transform[1][1] >= 0.5 && transform[1][1] < 2.0);
+    }
+    //the same seed scatters the same way
+    assert_eq!(transforms, scatter_transforms(&config));
+}